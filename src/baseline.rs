@@ -0,0 +1,153 @@
+//! Breaking change baselines of the workspaces, loaded in the background.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use async_lsp::ClientSocket;
+use async_lsp::lsp_types::Url;
+use tracing::error;
+
+use crate::config::WorkspaceProtoConfigs;
+use crate::descriptor::FileDescriptor;
+use crate::state::load_baseline;
+
+/// Event emitted to the server when a baseline is loaded.
+pub struct BaselineLoaded {
+    workspace: Url,
+    path: PathBuf,
+    files: Vec<FileDescriptor>,
+}
+
+struct Entry {
+    /// The `breaking.against` path the baseline is loaded from.
+    path: PathBuf,
+    /// `None` while the baseline loads.
+    files: Option<Vec<FileDescriptor>>,
+    /// The files checked while the baseline was loading.
+    waiting: HashSet<Url>,
+}
+
+/// The baseline of each workspace, loaded on first use and kept until its
+/// path changes or [`Self::invalidate`] is told of a change to its files.
+#[derive(Default)]
+pub struct Baselines {
+    entries: HashMap<Url, Entry>,
+}
+
+impl Baselines {
+    /// Returns the baseline of `workspace` found at `path`, or `None` while
+    /// it loads. Loading happens in the background and ends with
+    /// [`BaselineLoaded`] emitted to `client`; `uri` is then returned by
+    /// [`Self::finish`] to be checked again.
+    pub fn get(
+        &mut self,
+        client: &ClientSocket,
+        workspace: &Url,
+        path: &Path,
+        configs: &WorkspaceProtoConfigs,
+        uri: &Url,
+    ) -> Option<&[FileDescriptor]> {
+        if self
+            .entries
+            .get(workspace)
+            .is_none_or(|entry| entry.path != path)
+        {
+            let event_workspace = workspace.clone();
+            let event_path = path.to_path_buf();
+            let configs = configs.detached();
+            let client = client.clone();
+            tokio::task::spawn_blocking(move || {
+                let files = load_baseline(&event_path, &configs).unwrap_or_else(|e| {
+                    error!(error=%e, "failed to load the breaking change baseline");
+                    vec![]
+                });
+                let _ = client.emit(BaselineLoaded {
+                    workspace: event_workspace,
+                    path: event_path,
+                    files,
+                });
+            });
+            self.entries.insert(
+                workspace.clone(),
+                Entry {
+                    path: path.to_path_buf(),
+                    files: None,
+                    waiting: HashSet::new(),
+                },
+            );
+        }
+
+        let entry = self.entries.get_mut(workspace)?;
+        if entry.files.is_none() {
+            entry.waiting.insert(uri.clone());
+        }
+        entry.files.as_deref()
+    }
+
+    /// Records a loaded baseline and returns the files to check again against
+    /// it, or nothing if it was superseded by a newer load.
+    pub fn finish(&mut self, event: BaselineLoaded) -> Vec<Url> {
+        match self.entries.get_mut(&event.workspace) {
+            Some(entry) if entry.path == event.path && entry.files.is_none() => {
+                entry.files = Some(event.files);
+                entry.waiting.drain().collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Forgets the baselines loaded from `path` or from a directory holding
+    /// it, such as a file changed in a baseline directory.
+    pub fn invalidate(&mut self, path: &Path) {
+        self.entries
+            .retain(|_, entry| !path.starts_with(&entry.path));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn loading(baselines: &mut Baselines, workspace: &Url, path: &str, waiting: &Url) -> PathBuf {
+        baselines.entries.insert(
+            workspace.clone(),
+            Entry {
+                path: PathBuf::from(path),
+                files: None,
+                waiting: HashSet::from([waiting.clone()]),
+            },
+        );
+        PathBuf::from(path)
+    }
+
+    #[test]
+    fn test_finish_ignores_superseded_loads() {
+        let workspace = Url::parse("file:///ws").unwrap();
+        let uri = Url::parse("file:///ws/a.proto").unwrap();
+        let mut baselines = Baselines::default();
+        let old = loading(&mut baselines, &workspace, "/old", &uri);
+        let new = loading(&mut baselines, &workspace, "/new", &uri);
+
+        let event = |path| BaselineLoaded {
+            workspace: workspace.clone(),
+            path,
+            files: vec![],
+        };
+        assert!(baselines.finish(event(old)).is_empty());
+        assert_eq!(baselines.finish(event(new.clone())), vec![uri]);
+        assert!(baselines.finish(event(new)).is_empty());
+    }
+
+    #[test]
+    fn test_invalidate_files_of_a_baseline_directory() {
+        let workspace = Url::parse("file:///ws").unwrap();
+        let uri = Url::parse("file:///ws/a.proto").unwrap();
+        let mut baselines = Baselines::default();
+        loading(&mut baselines, &workspace, "/baseline", &uri);
+
+        baselines.invalidate(Path::new("/other/a.proto"));
+        assert!(baselines.entries.contains_key(&workspace));
+        baselines.invalidate(Path::new("/baseline/a.proto"));
+        assert!(baselines.entries.is_empty());
+    }
+}
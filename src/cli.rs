@@ -1,4 +1,6 @@
-use clap::Parser;
+use std::path::PathBuf;
+
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
 use const_format::concatcp;
 
 use crate::FALLBACK_INCLUDE_PATH;
@@ -28,7 +30,7 @@ const BUILD_INFO: &str = concatcp!(
 )]
 pub struct Cli {
     /// Include paths for proto files, comma-separated (can be used multiple times)
-    #[arg(short, long, value_delimiter = ',')]
+    #[arg(short, long, value_delimiter = ',', global = true)]
     pub include_paths: Option<Vec<String>>,
 
     /// Use stdin/stdout for communication (default)
@@ -64,6 +66,90 @@ pub struct Cli {
         help_heading = "Transport"
     )]
     pub pipe: Option<String>,
+
+    /// Runs a one-off command instead of the language server.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// One-off commands, using the configuration of a workspace like the server.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Write the descriptor set of a proto file and of everything it imports
+    DescriptorSet {
+        /// The proto file to describe
+        file: PathBuf,
+
+        /// Where to write the descriptor set, "-" for stdout
+        #[arg(short, long, value_name = "PATH")]
+        output: PathBuf,
+
+        /// Workspace root, whose protols.toml sets the include paths
+        #[arg(short, long, value_name = "DIR", default_value = ".")]
+        workspace: PathBuf,
+    },
+
+    /// Report the diagnostics of every proto file of a workspace, failing on errors
+    Check {
+        /// Workspace root, whose protols.toml configures the checks
+        #[arg(default_value = ".")]
+        workspace: PathBuf,
+
+        /// How to print the diagnostics
+        #[arg(short, long, value_enum, default_value_t = CheckFormat::Human)]
+        format: CheckFormat,
+    },
+
+    /// Report the changes of a workspace breaking compatibility with a baseline
+    #[command(group(ArgGroup::new("baseline").required(true)))]
+    Breaking {
+        /// Workspace root, whose protols.toml configures the include paths
+        #[arg(default_value = ".")]
+        workspace: PathBuf,
+
+        /// Directory of proto files, or `FileDescriptorSet`, to compare against
+        #[arg(long, value_name = "PATH", group = "baseline")]
+        against: Option<PathBuf>,
+
+        /// Git reference whose version of the workspace to compare against
+        #[arg(long, value_name = "REF", group = "baseline")]
+        against_git: Option<String>,
+
+        /// How to print the breaking changes
+        #[arg(short, long, value_enum, default_value_t = CheckFormat::Human)]
+        format: CheckFormat,
+    },
+
+    /// Format proto files with the formatter configured for the workspace
+    #[command(group(ArgGroup::new("mode").required(true)))]
+    Format {
+        /// Files or directories to format
+        #[arg(default_value = ".")]
+        paths: Vec<PathBuf>,
+
+        /// Print a diff of the files that are not formatted and fail if any
+        #[arg(long, group = "mode")]
+        check: bool,
+
+        /// Rewrite the files that are not formatted
+        #[arg(long, group = "mode")]
+        write: bool,
+
+        /// Workspace root, whose protols.toml configures the formatter
+        #[arg(short, long, value_name = "DIR", default_value = ".")]
+        workspace: PathBuf,
+    },
+}
+
+/// Output formats of the `check` command.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckFormat {
+    /// One `file:line:column: severity: message` line per diagnostic
+    Human,
+    /// A JSON array of diagnostics
+    Json,
+    /// A SARIF 2.1.0 log, for code scanning tools
+    Sarif,
 }
 
 impl Cli {
@@ -169,6 +255,142 @@ mod test {
         assert_eq!(cli.socket.as_deref(), Some("192.168.1.20:7301"));
     }
 
+    #[test]
+    fn test_descriptor_set_command() {
+        let args = vec![
+            "protols",
+            "descriptor-set",
+            "api/service.proto",
+            "-o",
+            "out.binpb",
+            "-i",
+            "vendor",
+        ];
+        let cli = Cli::try_parse_from(args).unwrap();
+        assert_eq!(cli.get_include_paths(), [PathBuf::from("vendor")]);
+
+        let Some(Command::DescriptorSet {
+            file,
+            output,
+            workspace,
+        }) = cli.command
+        else {
+            panic!("expected the descriptor-set command");
+        };
+        assert_eq!(file, PathBuf::from("api/service.proto"));
+        assert_eq!(output, PathBuf::from("out.binpb"));
+        assert_eq!(workspace, PathBuf::from("."));
+
+        let args = vec!["protols", "descriptor-set", "a.proto"];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn test_check_command() {
+        let cli = Cli::try_parse_from(vec!["protols", "check"]).unwrap();
+        let Some(Command::Check { workspace, format }) = cli.command else {
+            panic!("expected the check command");
+        };
+        assert_eq!(workspace, PathBuf::from("."));
+        assert_eq!(format, CheckFormat::Human);
+
+        let cli = Cli::try_parse_from(vec!["protols", "check", "protos", "-f", "sarif"]).unwrap();
+        let Some(Command::Check { workspace, format }) = cli.command else {
+            panic!("expected the check command");
+        };
+        assert_eq!(workspace, PathBuf::from("protos"));
+        assert_eq!(format, CheckFormat::Sarif);
+
+        assert!(Cli::try_parse_from(vec!["protols", "check", "-f", "xml"]).is_err());
+    }
+
+    #[test]
+    fn test_format_command() {
+        let cli = Cli::try_parse_from(vec!["protols", "format", "--check"]).unwrap();
+        let Some(Command::Format {
+            paths,
+            check,
+            write,
+            workspace,
+        }) = cli.command
+        else {
+            panic!("expected the format command");
+        };
+        assert_eq!(paths, vec![PathBuf::from(".")]);
+        assert!(check && !write);
+        assert_eq!(workspace, PathBuf::from("."));
+
+        let cli =
+            Cli::try_parse_from(vec!["protols", "format", "--write", "a.proto", "protos"]).unwrap();
+        let Some(Command::Format { paths, write, .. }) = cli.command else {
+            panic!("expected the format command");
+        };
+        assert_eq!(
+            paths,
+            vec![PathBuf::from("a.proto"), PathBuf::from("protos")]
+        );
+        assert!(write);
+
+        // Exactly one of --check and --write is required.
+        assert!(Cli::try_parse_from(vec!["protols", "format"]).is_err());
+        assert!(Cli::try_parse_from(vec!["protols", "format", "--check", "--write"]).is_err());
+    }
+
+    #[test]
+    fn test_breaking_command() {
+        let cli = Cli::try_parse_from(vec!["protols", "breaking", "--against", "old"]).unwrap();
+        let Some(Command::Breaking {
+            workspace,
+            against,
+            against_git,
+            format,
+        }) = cli.command
+        else {
+            panic!("expected the breaking command");
+        };
+        assert_eq!(workspace, PathBuf::from("."));
+        assert_eq!(against, Some(PathBuf::from("old")));
+        assert_eq!(against_git, None);
+        assert_eq!(format, CheckFormat::Human);
+
+        let cli = Cli::try_parse_from(vec![
+            "protols",
+            "breaking",
+            "protos",
+            "--against-git",
+            "main",
+            "-f",
+            "json",
+        ])
+        .unwrap();
+        let Some(Command::Breaking {
+            workspace,
+            against_git,
+            format,
+            ..
+        }) = cli.command
+        else {
+            panic!("expected the breaking command");
+        };
+        assert_eq!(workspace, PathBuf::from("protos"));
+        assert_eq!(against_git.as_deref(), Some("main"));
+        assert_eq!(format, CheckFormat::Json);
+
+        // Exactly one baseline is required.
+        assert!(Cli::try_parse_from(vec!["protols", "breaking"]).is_err());
+        assert!(
+            Cli::try_parse_from(vec![
+                "protols",
+                "breaking",
+                "--against",
+                "old",
+                "--against-git",
+                "main"
+            ])
+            .is_err()
+        );
+    }
+
     #[test]
     fn test_default_is_empty() {
         let args = vec!["protols"];
@@ -178,5 +400,6 @@ mod test {
         assert!(cli.port.is_none());
         assert!(cli.socket.is_none());
         assert!(cli.pipe.is_none());
+        assert!(cli.command.is_none());
     }
 }
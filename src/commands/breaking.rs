@@ -0,0 +1,217 @@
+//! `protols breaking`: reports the changes of a workspace that break
+//! compatibility with a baseline, for CI.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+use async_lsp::lsp_types::{DiagnosticSeverity, Range, Url};
+use tempfile::TempDir;
+
+use crate::cli::{CheckFormat, Cli};
+use crate::config::{CONFIG_FILE_NAMES, WorkspaceProtoConfigs};
+use crate::descriptor::{FileDescriptor, breaking_changes};
+use crate::state::{ProtoLanguageState, breaking_change_range, breaking_diagnostic, load_baseline};
+
+use super::check::{Report, relative_path, render};
+use super::workspace_configs;
+
+/// Prints the breaking changes of `workspace` against the baseline at
+/// `against`, or against its version at the git reference `against_git`.
+/// The exit code is 1 when there is any.
+pub(super) fn run(
+    cli: &Cli,
+    workspace: &Path,
+    against: Option<&Path>,
+    against_git: Option<&str>,
+    format: CheckFormat,
+) -> Result<i32, Vec<String>> {
+    let configs = workspace_configs(cli, workspace).map_err(|e| vec![e])?;
+    let root = std::path::absolute(workspace)
+        .map_err(|e| vec![format!("{}: {e}", workspace.display())])?;
+
+    let baseline = match (against, against_git) {
+        (Some(path), _) => load_baseline(path, &configs),
+        (None, Some(reference)) => {
+            git_baseline(&root, reference).and_then(|dir| load_baseline(dir.path(), &configs))
+        }
+        (None, None) => Err(String::from("no baseline to compare against")),
+    }
+    .map_err(|e| vec![e])?;
+
+    let reports = compare(&root, &configs, &baseline);
+    print!("{}", render(&reports, format));
+
+    let failed = reports.iter().any(|report| !report.diagnostics.is_empty());
+    Ok(i32::from(failed))
+}
+
+/// Compiles the workspace at `root` and reports, for each of its files and
+/// each file deleted from `baseline`, the breaking changes as errors.
+fn compare(
+    root: &Path,
+    configs: &WorkspaceProtoConfigs,
+    baseline: &[FileDescriptor],
+) -> Vec<Report> {
+    let mut state = ProtoLanguageState::new();
+    let (uris, current): (Vec<Url>, Vec<FileDescriptor>) =
+        state.compile_workspace(root, configs).into_iter().unzip();
+
+    let mut reports: Vec<Report> = uris
+        .iter()
+        .map(|uri| Report {
+            path: relative_path(root, &uri.to_file_path().unwrap_or_default()),
+            diagnostics: vec![],
+        })
+        .collect();
+    let indexes: HashMap<&str, usize> = current
+        .iter()
+        .enumerate()
+        .map(|(index, file)| (file.name.as_str(), index))
+        .collect();
+
+    for change in breaking_changes(baseline, &current) {
+        let (index, range) = if let Some(&index) = indexes.get(change.file.as_str()) {
+            let range = state
+                .get_document(&uris[index])
+                .map(|document| breaking_change_range(&document, &change))
+                .unwrap_or_default();
+            (index, range)
+        } else {
+            reports.push(Report {
+                path: change.file.clone(),
+                diagnostics: vec![],
+            });
+            (reports.len() - 1, Range::default())
+        };
+        let mut diagnostic = breaking_diagnostic(&change, range);
+        diagnostic.severity = Some(DiagnosticSeverity::ERROR);
+        reports[index].diagnostics.push(diagnostic);
+    }
+
+    for report in &mut reports {
+        report
+            .diagnostics
+            .sort_by_key(|diagnostic| (diagnostic.range.start, diagnostic.range.end));
+    }
+    reports
+}
+
+/// Writes the proto files and the `protols.toml` of the workspace at `root`,
+/// as of the git reference `reference`, into a temporary directory.
+fn git_baseline(root: &Path, reference: &str) -> Result<TempDir, String> {
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(root)
+            .args(args)
+            .output()
+            .map_err(|e| format!("failed to run git: {e}"))?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+        Ok(output.stdout)
+    };
+
+    // Paths are listed relative to `root`, and limited to it.
+    let listing = git(&["ls-tree", "-r", "--name-only", reference])?;
+    let dir = tempfile::tempdir().map_err(|e| format!("failed to create a directory: {e}"))?;
+    for path in String::from_utf8_lossy(&listing).lines() {
+        let file = Path::new(path);
+        let is_proto = file.extension().is_some_and(|ext| ext == "proto");
+        let is_config = file
+            .file_name()
+            .is_some_and(|name| CONFIG_FILE_NAMES.iter().any(|c| name == *c));
+        if !is_proto && !is_config {
+            continue;
+        }
+        let content = git(&["show", &format!("{reference}:./{path}")])?;
+        let target = dir.path().join(path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("{}: {e}", parent.display()))?;
+        }
+        std::fs::write(&target, content).map_err(|e| format!("{}: {e}", target.display()))?;
+    }
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    use insta::assert_snapshot;
+
+    use crate::cli::{CheckFormat, Cli};
+    use crate::commands::workspace_configs;
+    use crate::state::load_baseline;
+
+    use super::{compare, git_baseline, render};
+
+    #[test]
+    fn test_breaking() {
+        let input = std::env::current_dir()
+            .unwrap()
+            .join("src/state/input/breaking");
+        let root = input.join("current");
+        let configs = workspace_configs(&Cli::default(), &root).unwrap();
+        let baseline = load_baseline(&input.join("baseline"), &configs).unwrap();
+
+        let reports = compare(&root, &configs, &baseline);
+        assert_snapshot!(render(&reports, CheckFormat::Human));
+    }
+
+    #[test]
+    fn test_git_baseline() {
+        let repo = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(repo.path())
+                .args([
+                    "-c",
+                    "user.name=protols",
+                    "-c",
+                    "user.email=protols@localhost",
+                ])
+                .args(args)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {args:?} failed");
+        };
+        let write = |path: &str, content: &str| {
+            let path = repo.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+
+        git(&["init", "-q"]);
+        write(
+            "protos/a.proto",
+            "syntax = \"proto3\";\nmessage A {\n  int32 id = 1;\n}\n",
+        );
+        write("protos/protols.toml", "[config]\n");
+        write("README.md", "");
+        git(&["add", "-A"]);
+        git(&["commit", "-q", "-m", "baseline"]);
+        write("protos/a.proto", "syntax = \"proto3\";\nmessage A {}\n");
+
+        let workspace = repo.path().join("protos");
+        let dir = git_baseline(&workspace, "HEAD").unwrap();
+        let mut files: Vec<PathBuf> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into())
+            .collect();
+        files.sort();
+        assert_eq!(files, [Path::new("a.proto"), Path::new("protols.toml")]);
+
+        let configs = workspace_configs(&Cli::default(), &workspace).unwrap();
+        let baseline = load_baseline(dir.path(), &configs).unwrap();
+        let reports = compare(&workspace, &configs, &baseline);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].diagnostics.len(), 1);
+
+        assert!(git_baseline(&workspace, "no-such-ref").is_err());
+    }
+}
@@ -0,0 +1,302 @@
+//! `protols check`: reports the diagnostics of a whole workspace, for CI.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use async_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Url};
+use serde_json::{Value, json};
+use walkdir::WalkDir;
+
+use crate::cli::{CheckFormat, Cli};
+use crate::config::{DiagnosticsEngine, WorkspaceProtoConfigs};
+use crate::state::ProtoLanguageState;
+
+use super::workspace_configs;
+
+/// The diagnostics of one file of the workspace.
+pub(super) struct Report {
+    /// Path of the file relative to the workspace root, with `/` separators.
+    pub(super) path: String,
+    pub(super) diagnostics: Vec<Diagnostic>,
+}
+
+/// Prints the diagnostics of every proto file under `workspace` in `format`.
+/// The exit code is 1 when any of them is an error.
+pub(super) fn run(cli: &Cli, workspace: &Path, format: CheckFormat) -> Result<i32, Vec<String>> {
+    let configs = workspace_configs(cli, workspace).map_err(|e| vec![e])?;
+    let reports = check(&configs, workspace)?;
+    print!("{}", render(&reports, format));
+
+    let failed = reports
+        .iter()
+        .flat_map(|report| &report.diagnostics)
+        .any(|diagnostic| severity(diagnostic) == DiagnosticSeverity::ERROR);
+    Ok(i32::from(failed))
+}
+
+/// Indexes the workspace and computes the diagnostics of each of its proto
+/// files, as the language server would publish them when opening it.
+fn check(configs: &WorkspaceProtoConfigs, workspace: &Path) -> Result<Vec<Report>, Vec<String>> {
+    let root = std::path::absolute(workspace)
+        .map_err(|e| vec![format!("{}: {e}", workspace.display())])?;
+    if !root.is_dir() {
+        return Err(vec![format!("{}: not a directory", workspace.display())]);
+    }
+
+    let mut files: Vec<PathBuf> = WalkDir::new(&root)
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(walkdir::DirEntry::into_path)
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "proto"))
+        .collect();
+    files.sort();
+
+    let mut state = ProtoLanguageState::new();
+    state.parse_all_from_workspace(&root, None);
+
+    let mut reports = Vec::new();
+    let mut errors = Vec::new();
+    for file in files {
+        let Ok(uri) = Url::from_file_path(&file) else {
+            continue;
+        };
+        let (Some(ipath), Some(pconf)) = (
+            configs.get_include_paths(&uri),
+            configs.get_config_for_uri(&uri),
+        ) else {
+            continue;
+        };
+        let content = match std::fs::read_to_string(&file) {
+            Ok(content) => content,
+            Err(e) => {
+                errors.push(format!("{}: {e}", file.display()));
+                continue;
+            }
+        };
+
+        let protoc = pconf.config.diagnostics.engine == DiagnosticsEngine::Protoc;
+        let mut diagnostics: Vec<Diagnostic> = state
+            .upsert_file(&uri, &content, &ipath, 8, &pconf.config, protoc)
+            .into_iter()
+            .filter(|params| params.uri == uri)
+            .flat_map(|params| params.diagnostics)
+            .collect();
+        diagnostics.extend(state.lint_diagnostics(&uri, &ipath, &pconf.config.lint));
+        diagnostics.sort_by_key(|diagnostic| (diagnostic.range.start, diagnostic.range.end));
+
+        reports.push(Report {
+            path: relative_path(&root, &file),
+            diagnostics,
+        });
+    }
+
+    if errors.is_empty() {
+        Ok(reports)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Returns the path of `file` relative to `root`, with `/` separators.
+pub(super) fn relative_path(root: &Path, file: &Path) -> String {
+    file.strip_prefix(root)
+        .unwrap_or(file)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub(super) fn render(reports: &[Report], format: CheckFormat) -> String {
+    match format {
+        CheckFormat::Human => render_human(reports),
+        CheckFormat::Json => render_json(reports),
+        CheckFormat::Sarif => render_sarif(reports),
+    }
+}
+
+/// One `file:line:column: severity: message` line per diagnostic, followed by
+/// a summary. Lines and columns are one-based, like compiler output.
+fn render_human(reports: &[Report]) -> String {
+    let mut out = String::new();
+    let (mut errors, mut warnings) = (0, 0);
+    for report in reports {
+        for diagnostic in &report.diagnostics {
+            let severity = severity(diagnostic);
+            if severity == DiagnosticSeverity::ERROR {
+                errors += 1;
+            } else if severity == DiagnosticSeverity::WARNING {
+                warnings += 1;
+            }
+            let _ = writeln!(
+                out,
+                "{}:{}:{}: {}: {}",
+                report.path,
+                diagnostic.range.start.line + 1,
+                diagnostic.range.start.character + 1,
+                severity_name(severity),
+                diagnostic.message
+            );
+        }
+    }
+    let plural = |n: usize| if n == 1 { "" } else { "s" };
+    let _ = writeln!(
+        out,
+        "{} file{} checked: {errors} error{}, {warnings} warning{}",
+        reports.len(),
+        plural(reports.len()),
+        plural(errors),
+        plural(warnings)
+    );
+    out
+}
+
+/// A JSON array with one object per diagnostic, using one-based positions.
+fn render_json(reports: &[Report]) -> String {
+    let diagnostics: Vec<Value> = reports
+        .iter()
+        .flat_map(|report| {
+            report.diagnostics.iter().map(|diagnostic| {
+                json!({
+                    "file": report.path,
+                    "line": diagnostic.range.start.line + 1,
+                    "column": diagnostic.range.start.character + 1,
+                    "end_line": diagnostic.range.end.line + 1,
+                    "end_column": diagnostic.range.end.character + 1,
+                    "severity": severity_name(severity(diagnostic)),
+                    "source": diagnostic.source,
+                    "code": diagnostic.code.as_ref().map(code_name),
+                    "message": diagnostic.message,
+                })
+            })
+        })
+        .collect();
+    format!("{:#}\n", Value::Array(diagnostics))
+}
+
+/// A SARIF 2.1.0 log with a single run, whose artifact locations are relative
+/// to the workspace root.
+fn render_sarif(reports: &[Report]) -> String {
+    let results: Vec<Value> = reports
+        .iter()
+        .flat_map(|report| {
+            report.diagnostics.iter().map(|diagnostic| {
+                let level = match severity(diagnostic) {
+                    DiagnosticSeverity::ERROR => "error",
+                    DiagnosticSeverity::WARNING => "warning",
+                    _ => "note",
+                };
+                let mut result = json!({
+                    "level": level,
+                    "message": { "text": diagnostic.message },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": {
+                                "uri": report.path,
+                                "uriBaseId": "%SRCROOT%",
+                            },
+                            "region": {
+                                "startLine": diagnostic.range.start.line + 1,
+                                "startColumn": diagnostic.range.start.character + 1,
+                                "endLine": diagnostic.range.end.line + 1,
+                                "endColumn": diagnostic.range.end.character + 1,
+                            },
+                        },
+                    }],
+                });
+                if let Some(code) = &diagnostic.code {
+                    result["ruleId"] = Value::String(code_name(code));
+                }
+                result
+            })
+        })
+        .collect();
+
+    let log = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                },
+            },
+            "columnKind": "utf16CodeUnits",
+            "results": results,
+        }],
+    });
+    format!("{log:#}\n")
+}
+
+/// Diagnostics without a severity are errors, as clients treat them.
+fn severity(diagnostic: &Diagnostic) -> DiagnosticSeverity {
+    diagnostic.severity.unwrap_or(DiagnosticSeverity::ERROR)
+}
+
+fn severity_name(severity: DiagnosticSeverity) -> &'static str {
+    match severity {
+        DiagnosticSeverity::WARNING => "warning",
+        DiagnosticSeverity::INFORMATION => "info",
+        DiagnosticSeverity::HINT => "hint",
+        _ => "error",
+    }
+}
+
+fn code_name(code: &NumberOrString) -> String {
+    match code {
+        NumberOrString::Number(n) => n.to_string(),
+        NumberOrString::String(s) => s.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use insta::{assert_snapshot, assert_yaml_snapshot};
+    use serde_json::Value;
+
+    use crate::cli::{CheckFormat, Cli};
+    use crate::commands::workspace_configs;
+
+    use super::{check, render};
+
+    #[test]
+    fn test_check() {
+        let workspace = PathBuf::from("src/state/input/semantic");
+        let configs = workspace_configs(&Cli::default(), &workspace).unwrap();
+        let reports = check(&configs, &workspace).unwrap();
+
+        let paths: Vec<&str> = reports.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "common.proto",
+                "cycle_a.proto",
+                "cycle_b.proto",
+                "errors.proto",
+                "hidden.proto",
+                "proto2.proto",
+                "valid.proto",
+                "via.proto"
+            ]
+        );
+
+        assert_snapshot!("human", render(&reports, CheckFormat::Human));
+        let json: Value = serde_json::from_str(&render(&reports, CheckFormat::Json)).unwrap();
+        assert_yaml_snapshot!("json", json);
+        let sarif: Value = serde_json::from_str(&render(&reports, CheckFormat::Sarif)).unwrap();
+        assert_yaml_snapshot!("sarif", sarif, {
+            ".runs[0].tool.driver.version" => "[version]",
+        });
+    }
+
+    #[test]
+    fn test_check_missing_workspace() {
+        let workspace = PathBuf::from("src/state/input/missing");
+        let configs = workspace_configs(&Cli::default(), &workspace).unwrap();
+        assert!(check(&configs, &workspace).is_err());
+    }
+}
@@ -0,0 +1,40 @@
+//! `protols descriptor-set`: writes the `FileDescriptorSet` of a file.
+
+use std::io::Write;
+use std::path::Path;
+
+use crate::cli::Cli;
+use crate::descriptor::encode_file_descriptor_set;
+use crate::state::ProtoLanguageState;
+use crate::utils::resolve_import;
+
+use super::workspace_configs;
+
+/// Writes the descriptor set of `file` and of everything it imports to
+/// `output`, or to stdout when it is `-`.
+pub(super) fn run(
+    cli: &Cli,
+    file: &Path,
+    output: &Path,
+    workspace: &Path,
+) -> Result<(), Vec<String>> {
+    let configs = workspace_configs(cli, workspace).map_err(|e| vec![e])?;
+    let cwd = std::env::current_dir().map_err(|e| vec![e.to_string()])?;
+    let uri = resolve_import(file, &[cwd])
+        .ok_or_else(|| vec![format!("{}: no such file", file.display())])?;
+    let ipath = configs
+        .get_include_paths(&uri)
+        .ok_or_else(|| vec![format!("{}: not inside the workspace", file.display())])?;
+    let content =
+        std::fs::read_to_string(file).map_err(|e| vec![format!("{}: {e}", file.display())])?;
+
+    let files = ProtoLanguageState::new().descriptor_set(&uri, &content, &ipath)?;
+    let bytes = encode_file_descriptor_set(&files);
+
+    let written = if output == Path::new("-") {
+        std::io::stdout().write_all(&bytes)
+    } else {
+        std::fs::write(output, bytes)
+    };
+    written.map_err(|e| vec![format!("{}: {e}", output.display())])
+}
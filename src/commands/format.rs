@@ -0,0 +1,194 @@
+//! `protols format`: formats files the way the editor does, for hooks and CI.
+
+use std::path::{Path, PathBuf};
+
+use similar::TextDiff;
+use walkdir::WalkDir;
+
+use crate::cli::Cli;
+use crate::config::WorkspaceProtoConfigs;
+use crate::formatter::{ProtoFormatter, apply_edits};
+use crate::utils::resolve_import;
+
+use super::workspace_configs;
+
+/// Formats the proto files among `paths`, descending into directories.
+///
+/// With `check`, prints a unified diff of every file that is not formatted
+/// and returns 1 if there is any; otherwise rewrites those files.
+pub(super) fn run(
+    cli: &Cli,
+    paths: &[PathBuf],
+    check: bool,
+    workspace: &Path,
+) -> Result<i32, Vec<String>> {
+    let configs = workspace_configs(cli, workspace).map_err(|e| vec![e])?;
+
+    let mut errors = Vec::new();
+    let mut unformatted = false;
+    for file in proto_files(paths, &mut errors) {
+        let (content, formatted) = match format_file(&configs, &file) {
+            Ok(result) => result,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+        if content == formatted {
+            continue;
+        }
+        unformatted = true;
+
+        if check {
+            print!("{}", diff(&file, &content, &formatted));
+        } else if let Err(e) = std::fs::write(&file, formatted) {
+            errors.push(format!("{}: {e}", file.display()));
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(i32::from(check && unformatted))
+}
+
+/// Returns the files of `paths` and the proto files found under the
+/// directories among them, in order.
+fn proto_files(paths: &[PathBuf], errors: &mut Vec<String>) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_file() {
+            files.push(path.clone());
+        } else if path.is_dir() {
+            let mut found: Vec<PathBuf> = WalkDir::new(path)
+                .into_iter()
+                .filter_map(Result::ok)
+                .map(walkdir::DirEntry::into_path)
+                .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "proto"))
+                .collect();
+            found.sort();
+            files.extend(found);
+        } else {
+            errors.push(format!("{}: no such file or directory", path.display()));
+        }
+    }
+    files
+}
+
+/// Reads `file` and formats it with the formatter of its workspace.
+fn format_file(configs: &WorkspaceProtoConfigs, file: &Path) -> Result<(String, String), String> {
+    let cwd = std::env::current_dir().map_err(|e| e.to_string())?;
+    let uri =
+        resolve_import(file, &[cwd]).ok_or_else(|| format!("{}: no such file", file.display()))?;
+    let content = std::fs::read_to_string(file).map_err(|e| format!("{}: {e}", file.display()))?;
+    let edits = configs
+        .get_formatter_for_uri(&uri)
+        .ok_or_else(|| format!("{}: not inside the workspace", file.display()))?
+        .format_document(uri.path(), &content)
+        .ok_or_else(|| format!("{}: could not be formatted", file.display()))?;
+    let formatted = apply_edits(&content, &edits);
+    Ok((content, formatted))
+}
+
+/// Unified diff turning `content` into `formatted`.
+fn diff(file: &Path, content: &str, formatted: &str) -> String {
+    let name = file.display().to_string();
+    TextDiff::from_lines(content, formatted)
+        .unified_diff()
+        .header(&name, &name)
+        .to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use insta::assert_snapshot;
+
+    use crate::cli::Cli;
+    use crate::commands::workspace_configs;
+
+    use super::{diff, format_file, run};
+
+    const UNFORMATTED: &str =
+        "syntax = \"proto3\";\npackage fmt;\nmessage Foo {\nstring name = 1;\n  int32 id=2;\n}\n";
+
+    fn setup() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("protols.toml"),
+            "[config.formatter]\nengine = \"native\"\n",
+        )
+        .unwrap();
+        std::fs::create_dir(dir.path().join("nested")).unwrap();
+        std::fs::write(dir.path().join("nested/foo.proto"), UNFORMATTED).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_format_check() {
+        let dir = setup();
+        let workspace = dir.path().to_path_buf();
+        let file = workspace.join("nested/foo.proto");
+
+        let configs = workspace_configs(&Cli::default(), &workspace).unwrap();
+        let (content, formatted) = format_file(&configs, &file).unwrap();
+        assert_snapshot!(diff(
+            &PathBuf::from("nested/foo.proto"),
+            &content,
+            &formatted
+        ));
+
+        assert_eq!(
+            run(
+                &Cli::default(),
+                std::slice::from_ref(&workspace),
+                true,
+                &workspace
+            ),
+            Ok(1)
+        );
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), UNFORMATTED);
+    }
+
+    #[test]
+    fn test_format_write() {
+        let dir = setup();
+        let workspace = dir.path().to_path_buf();
+        let file = workspace.join("nested/foo.proto");
+
+        assert_eq!(
+            run(
+                &Cli::default(),
+                std::slice::from_ref(&workspace),
+                false,
+                &workspace
+            ),
+            Ok(0)
+        );
+        let formatted = std::fs::read_to_string(&file).unwrap();
+        assert_ne!(formatted, UNFORMATTED);
+
+        assert_eq!(run(&Cli::default(), &[file], true, &workspace), Ok(0));
+    }
+
+    #[test]
+    fn test_format_errors() {
+        let dir = setup();
+        let workspace = dir.path().to_path_buf();
+        std::fs::write(workspace.join("broken.proto"), "message {").unwrap();
+
+        let errors = run(
+            &Cli::default(),
+            std::slice::from_ref(&workspace),
+            true,
+            &workspace,
+        )
+        .unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].ends_with("broken.proto: could not be formatted"));
+
+        let missing = workspace.join("missing.proto");
+        assert!(run(&Cli::default(), &[missing], true, &workspace).is_err());
+    }
+}
@@ -0,0 +1,73 @@
+//! One-off commands run from the command line instead of the language
+//! server. They read the configuration of a workspace the way the server does
+//! for a workspace folder, so that both agree on include paths.
+
+use std::path::{Path, PathBuf};
+
+use async_lsp::lsp_types::{Url, WorkspaceFolder};
+
+use crate::FALLBACK_INCLUDE_PATH;
+use crate::cli::{Cli, Command};
+use crate::config::WorkspaceProtoConfigs;
+
+mod breaking;
+mod check;
+mod descriptor_set;
+mod format;
+
+/// Runs `command` and returns the exit code of the process.
+pub fn run(command: &Command, cli: &Cli) -> i32 {
+    let result = match command {
+        Command::DescriptorSet {
+            file,
+            output,
+            workspace,
+        } => descriptor_set::run(cli, file, output, workspace).map(|()| 0),
+        Command::Check { workspace, format } => check::run(cli, workspace, *format),
+        Command::Breaking {
+            workspace,
+            against,
+            against_git,
+            format,
+        } => breaking::run(
+            cli,
+            workspace,
+            against.as_deref(),
+            against_git.as_deref(),
+            *format,
+        ),
+        Command::Format {
+            paths,
+            check,
+            write: _,
+            workspace,
+        } => format::run(cli, paths, *check, workspace),
+    };
+    match result {
+        Ok(code) => code,
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{error}");
+            }
+            1
+        }
+    }
+}
+
+/// Loads the configuration of the workspace rooted at `root`.
+fn workspace_configs(cli: &Cli, root: &Path) -> Result<WorkspaceProtoConfigs, String> {
+    let root = std::path::absolute(root).map_err(|e| format!("{}: {e}", root.display()))?;
+    let uri = Url::from_directory_path(&root)
+        .map_err(|()| format!("{}: not a valid workspace path", root.display()))?;
+    let name = root
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut configs = WorkspaceProtoConfigs::new(
+        cli.get_include_paths(),
+        FALLBACK_INCLUDE_PATH.map(PathBuf::from),
+    );
+    configs.add_workspace(&WorkspaceFolder { uri, name });
+    Ok(configs)
+}
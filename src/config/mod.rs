@@ -1,6 +1,8 @@
 mod workspace;
 
-pub use workspace::WorkspaceProtoConfigs;
+pub use workspace::{CONFIG_FILE_NAMES, WorkspaceProtoConfigs};
+
+use std::collections::BTreeMap;
 
 use serde::{Deserialize, Serialize};
 
@@ -16,6 +18,10 @@ pub struct Config {
     pub include_paths: Vec<String>,
     pub path: PathConfig,
     pub rename: RenameConfig,
+    pub formatter: FormatterConfig,
+    pub diagnostics: DiagnosticsConfig,
+    pub breaking: BreakingConfig,
+    pub lint: LintConfig,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -45,6 +51,80 @@ pub struct RenameConfig {
     pub chain_rpc_request_response: bool,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct FormatterConfig {
+    /// Which formatter handles formatting requests.
+    pub engine: FormatterEngine,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FormatterEngine {
+    /// Runs the `clang-format` binary from `path.clang_format`.
+    #[default]
+    Clang,
+    /// Uses the formatter built into protols, no external tool needed.
+    Native,
+    /// Uses the built-in formatter with the conventions of `buf format`.
+    Buf,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct DiagnosticsConfig {
+    /// Which backend reports semantic errors.
+    pub engine: DiagnosticsEngine,
+    /// How long protoc may run, in milliseconds, before it is killed.
+    pub protoc_timeout_ms: u64,
+}
+
+impl Default for DiagnosticsConfig {
+    fn default() -> Self {
+        Self {
+            engine: DiagnosticsEngine::default(),
+            protoc_timeout_ms: 10_000,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticsEngine {
+    /// Runs the `protoc` binary from `path.protoc` in the background.
+    #[default]
+    Protoc,
+    /// Uses the checker built into protols, no external tool needed.
+    Native,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct BreakingConfig {
+    /// Baseline the files are compared against to report breaking changes:
+    /// a directory of proto files or a `FileDescriptorSet`, relative to the
+    /// workspace root. Nothing is compared when unset.
+    pub against: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct LintConfig {
+    /// Whether the lint rules run at all. Off by default.
+    pub enabled: bool,
+    /// Rules turned on (`true`) or off (`false`) by id, overriding whether
+    /// they run by default.
+    pub rules: BTreeMap<String, bool>,
+}
+
+impl LintConfig {
+    /// Whether the rule `id`, which runs unless configured otherwise when
+    /// `default` is set, is turned on.
+    pub fn is_enabled(&self, id: &str, default: bool) -> bool {
+        self.enabled && self.rules.get(id).copied().unwrap_or(default)
+    }
+}
+
 fn default_clang_format_path() -> String {
     "clang-format".to_string()
 }
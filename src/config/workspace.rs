@@ -7,16 +7,16 @@ use std::{
 use async_lsp::lsp_types::{Url, WorkspaceFolder};
 use pkg_config::Config;
 
-use crate::formatter::ClangFormatter;
+use crate::formatter::{BufFormatter, ClangFormatter, Formatter, NativeFormatter};
 
-use crate::config::ProtolsConfig;
+use crate::config::{FormatterEngine, ProtolsConfig};
 
-const CONFIG_FILE_NAMES: [&str; 2] = [".protols.toml", "protols.toml"];
+pub const CONFIG_FILE_NAMES: [&str; 2] = [".protols.toml", "protols.toml"];
 
 pub struct WorkspaceProtoConfigs {
     workspaces: HashSet<Url>,
     configs: HashMap<Url, ProtolsConfig>,
-    formatters: HashMap<Url, ClangFormatter>,
+    formatters: HashMap<Url, Formatter>,
     protoc_include_prefix: Vec<PathBuf>,
     cli_include_paths: Vec<PathBuf>,
     init_include_paths: Vec<PathBuf>,
@@ -47,6 +47,28 @@ impl WorkspaceProtoConfigs {
         }
     }
 
+    /// Returns configurations for the single workspace `w`, read from its
+    /// own `protols.toml`, with the same extra include paths as these.
+    pub fn for_root(&self, w: &WorkspaceFolder) -> Self {
+        let mut configs = self.detached();
+        configs.add_workspace(w);
+        configs
+    }
+
+    /// Returns configurations without any workspace, with the same extra
+    /// include paths as these.
+    pub fn detached(&self) -> Self {
+        Self {
+            workspaces: HashSet::new(),
+            formatters: HashMap::new(),
+            configs: HashMap::new(),
+            fallback_include_path: self.fallback_include_path.clone(),
+            protoc_include_prefix: self.protoc_include_prefix.clone(),
+            cli_include_paths: self.cli_include_paths.clone(),
+            init_include_paths: self.init_include_paths.clone(),
+        }
+    }
+
     fn get_config_file_path(wpath: &PathBuf) -> Option<PathBuf> {
         for file in CONFIG_FILE_NAMES {
             let p = Path::new(&wpath).join(file);
@@ -67,22 +89,29 @@ impl WorkspaceProtoConfigs {
         let content = std::fs::read_to_string(path).unwrap_or_default();
 
         let wr: ProtolsConfig = basic_toml::from_str(&content).unwrap_or_default();
-        let fmt = ClangFormatter::new(
-            &wr.config.path.clang_format,
-            Some(wpath.to_str().expect("non-utf8 path")),
-        );
+        let fmt = Self::create_formatter(&wr, Some(wpath.to_str().expect("non-utf8 path")));
 
         self.workspaces.insert(w.uri.clone());
         self.configs.insert(w.uri.clone(), wr);
         self.formatters.insert(w.uri.clone(), fmt);
     }
 
+    fn create_formatter(wr: &ProtolsConfig, wdir: Option<&str>) -> Formatter {
+        match wr.config.formatter.engine {
+            FormatterEngine::Clang => {
+                Formatter::Clang(ClangFormatter::new(&wr.config.path.clang_format, wdir))
+            }
+            FormatterEngine::Native => Formatter::Native(NativeFormatter),
+            FormatterEngine::Buf => Formatter::Buf(BufFormatter),
+        }
+    }
+
     pub fn get_config_for_uri(&self, u: &Url) -> Option<&ProtolsConfig> {
         self.get_workspace_for_uri(u)
             .and_then(|w| self.configs.get(w))
     }
 
-    pub fn get_formatter_for_uri(&self, u: &Url) -> Option<&ClangFormatter> {
+    pub fn get_formatter_for_uri(&self, u: &Url) -> Option<&Formatter> {
         self.get_workspace_for_uri(u)
             .and_then(|w| self.formatters.get(w))
     }
@@ -94,6 +123,17 @@ impl WorkspaceProtoConfigs {
             .find(|&k| upath.starts_with(k.to_file_path().unwrap()))
     }
 
+    /// Returns the `breaking.against` path of each workspace setting one.
+    pub fn breaking_baselines(&self) -> Vec<PathBuf> {
+        self.workspaces
+            .iter()
+            .filter_map(|w| {
+                let against = self.configs.get(w)?.config.breaking.against.as_ref()?;
+                Some(w.to_file_path().ok()?.join(against))
+            })
+            .collect()
+    }
+
     pub fn set_init_include_paths(&mut self, paths: Vec<PathBuf>) {
         self.init_include_paths = paths;
     }
@@ -159,7 +199,7 @@ impl WorkspaceProtoConfigs {
             Ok(uri) => uri,
         };
 
-        let fmt = ClangFormatter::new(&wr.config.path.clang_format, None);
+        let fmt = Self::create_formatter(&wr, None);
 
         self.workspaces.insert(uri.clone());
         self.configs.insert(uri.clone(), wr);
@@ -175,6 +215,7 @@ mod test {
     use tempfile::tempdir;
 
     use super::{CONFIG_FILE_NAMES, WorkspaceProtoConfigs};
+    use crate::formatter::Formatter;
 
     #[test]
     fn test_get_for_workspace() {
@@ -234,14 +275,33 @@ mod test {
         let inworkspace2 = Url::from_file_path(tmpdir2.path().join("foobar.proto")).unwrap();
 
         assert!(ws.get_formatter_for_uri(&outworkspace).is_none());
-        assert_eq!(
-            ws.get_formatter_for_uri(&inworkspace).unwrap().path,
-            "/usr/bin/clang-format"
-        );
-        assert_eq!(
-            ws.get_formatter_for_uri(&inworkspace2).unwrap().path,
-            "clang-format"
-        );
+        let Some(Formatter::Clang(fmt)) = ws.get_formatter_for_uri(&inworkspace) else {
+            panic!("expected clang-format for {inworkspace}");
+        };
+        assert_eq!(fmt.path, "/usr/bin/clang-format");
+        let Some(Formatter::Clang(fmt)) = ws.get_formatter_for_uri(&inworkspace2) else {
+            panic!("expected clang-format for {inworkspace2}");
+        };
+        assert_eq!(fmt.path, "clang-format");
+    }
+
+    #[test]
+    fn test_get_native_formatter_for_uri() {
+        let tmpdir = tempdir().expect("failed to create temp directory");
+        let f = tmpdir.path().join("protols.toml");
+        std::fs::write(f, include_str!("input/protols-native-formatter.toml")).unwrap();
+
+        let mut ws = WorkspaceProtoConfigs::new(vec![], None);
+        ws.add_workspace(&WorkspaceFolder {
+            uri: Url::from_directory_path(tmpdir.path()).unwrap(),
+            name: "Test".to_string(),
+        });
+
+        let inworkspace = Url::from_file_path(tmpdir.path().join("foobar.proto")).unwrap();
+        assert!(matches!(
+            ws.get_formatter_for_uri(&inworkspace),
+            Some(Formatter::Native(_))
+        ));
     }
 
     #[test]
@@ -0,0 +1,578 @@
+//! Detection of changes that break clients built against a previous version
+//! of the same files, in the spirit of `buf breaking`.
+//!
+//! Files are matched by name and their definitions by name relative to the
+//! package, so that a renamed package is reported once rather than as the
+//! deletion of everything it contains. Fields and enum values are matched by
+//! number, which is what identifies them on the wire.
+
+use serde::Serialize;
+
+use super::{
+    EnumDescriptor, FieldDescriptor, FieldLabel, FieldType, FileDescriptor, MessageDescriptor,
+    ServiceDescriptor,
+};
+
+/// What a change breaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Breakage {
+    /// Messages or RPCs exchanged between the old and the new version are
+    /// misread or rejected.
+    Wire,
+    /// Code generated from the new version no longer builds against code
+    /// written for the old one.
+    Source,
+}
+
+/// A change breaking compatibility with the baseline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BreakingChange {
+    /// Name of the file the change is in.
+    pub file: String,
+    /// Fully qualified name, without leading dot, of the closest definition
+    /// of the current file the change can be shown on, enum values being
+    /// named inside their enum. `None` for changes about the file itself, or
+    /// about a deleted file.
+    pub anchor: Option<String>,
+    pub breakage: Breakage,
+    /// Identifier of the check, named after the matching `buf breaking` rule.
+    pub rule: &'static str,
+    pub message: String,
+}
+
+/// Reports the changes of `current` that break compatibility with
+/// `baseline`. Files of `current` absent from `baseline` are new and never
+/// breaking.
+pub fn breaking_changes(
+    baseline: &[FileDescriptor],
+    current: &[FileDescriptor],
+) -> Vec<BreakingChange> {
+    let pairs: Vec<(&FileDescriptor, Option<&FileDescriptor>)> = baseline
+        .iter()
+        .map(|old| (old, current.iter().find(|new| new.name == old.name)))
+        .collect();
+
+    let mut packages: Vec<(String, String)> = pairs
+        .iter()
+        .filter_map(|(old, new)| Some((old.package.clone(), new.as_ref()?.package.clone())))
+        .filter(|(old, new)| old != new)
+        .collect();
+    packages.sort_by_key(|(old, _)| std::cmp::Reverse(old.len()));
+    packages.dedup();
+
+    let mut changes = Vec::new();
+    for (old, new) in pairs {
+        let Some(new) = new else {
+            changes.push(BreakingChange {
+                file: old.name.clone(),
+                anchor: None,
+                breakage: Breakage::Source,
+                rule: "FILE_NO_DELETE",
+                message: format!("Previously present file \"{}\" was deleted.", old.name),
+            });
+            continue;
+        };
+        let mut checker = Checker {
+            file: &new.name,
+            packages: &packages,
+            changes: Vec::new(),
+        };
+        checker.file(old, new);
+        changes.extend(checker.changes);
+    }
+    changes
+}
+
+/// Compares the two versions of one file.
+struct Checker<'a> {
+    file: &'a str,
+    /// Renamed packages, the longest first.
+    packages: &'a [(String, String)],
+    changes: Vec<BreakingChange>,
+}
+
+/// Scopes of a definition in both versions, with the current one being the
+/// anchor of changes found in it.
+#[derive(Clone, Copy)]
+struct Scope<'s> {
+    old: &'s str,
+    new: &'s str,
+    /// Whether the current scope is a message, rather than a package.
+    in_message: bool,
+}
+
+impl Scope<'_> {
+    fn anchor(&self) -> Option<String> {
+        self.in_message.then(|| self.new.to_string())
+    }
+}
+
+impl Checker<'_> {
+    fn report(
+        &mut self,
+        anchor: Option<String>,
+        breakage: Breakage,
+        rule: &'static str,
+        message: String,
+    ) {
+        self.changes.push(BreakingChange {
+            file: self.file.to_string(),
+            anchor,
+            breakage,
+            rule,
+            message,
+        });
+    }
+
+    fn file(&mut self, old: &FileDescriptor, new: &FileDescriptor) {
+        if old.package != new.package {
+            self.report(
+                None,
+                Breakage::Wire,
+                "FILE_SAME_PACKAGE",
+                format!(
+                    "File \"{}\" changed package from \"{}\" to \"{}\".",
+                    new.name, old.package, new.package
+                ),
+            );
+        }
+        let scope = Scope {
+            old: &old.package,
+            new: &new.package,
+            in_message: false,
+        };
+        self.messages(&old.message_type, &new.message_type, scope);
+        self.enums(&old.enum_type, &new.enum_type, scope);
+        self.services(&old.service, &new.service, scope);
+    }
+
+    fn messages(&mut self, old: &[MessageDescriptor], new: &[MessageDescriptor], scope: Scope) {
+        for message in old.iter().filter(|m| !m.map_entry) {
+            let old_fqn = qualify(scope.old, &message.name);
+            let Some(current) = new.iter().find(|m| m.name == message.name) else {
+                self.report(
+                    scope.anchor(),
+                    Breakage::Source,
+                    "MESSAGE_NO_DELETE",
+                    format!("Previously present message \"{old_fqn}\" was deleted."),
+                );
+                continue;
+            };
+            let new_fqn = qualify(scope.new, &current.name);
+            self.message(message, current, &new_fqn);
+
+            let inner = Scope {
+                old: &old_fqn,
+                new: &new_fqn,
+                in_message: true,
+            };
+            self.messages(&message.nested_type, &current.nested_type, inner);
+            self.enums(&message.enum_type, &current.enum_type, inner);
+        }
+    }
+
+    fn message(&mut self, old: &MessageDescriptor, new: &MessageDescriptor, fqn: &str) {
+        for field in &old.field {
+            if let Some(current) = new.field.iter().find(|f| f.number == field.number) {
+                self.field(field, current, old, new, fqn);
+            } else if let Some(current) = new.field.iter().find(|f| f.name == field.name) {
+                self.report(
+                    Some(qualify(fqn, &current.name)),
+                    Breakage::Wire,
+                    "FIELD_SAME_NUMBER",
+                    format!(
+                        "Field \"{}\" on message \"{fqn}\" changed number from {} to {}.",
+                        field.name, field.number, current.number
+                    ),
+                );
+            } else if in_ranges(&new.reserved_range, field.number, false) {
+                self.report(
+                    Some(fqn.to_string()),
+                    Breakage::Source,
+                    "FIELD_NO_DELETE",
+                    format!(
+                        "Previously present field \"{}\" with name \"{}\" on message \"{fqn}\" was deleted.",
+                        field.number, field.name
+                    ),
+                );
+            } else {
+                self.report(
+                    Some(fqn.to_string()),
+                    Breakage::Wire,
+                    "FIELD_NO_DELETE_UNLESS_NUMBER_RESERVED",
+                    format!(
+                        "Previously present field \"{}\" with name \"{}\" on message \"{fqn}\" was deleted without reserving the number \"{}\".",
+                        field.number, field.name, field.number
+                    ),
+                );
+            }
+        }
+
+        for field in &new.field {
+            if in_ranges(&old.reserved_range, field.number, false)
+                && !old.field.iter().any(|f| f.number == field.number)
+            {
+                self.report(
+                    Some(qualify(fqn, &field.name)),
+                    Breakage::Wire,
+                    "RESERVED_NUMBER_NO_REUSE",
+                    format!(
+                        "Field \"{}\" on message \"{fqn}\" uses number {}, which was reserved.",
+                        field.name, field.number
+                    ),
+                );
+            }
+        }
+    }
+
+    fn field(
+        &mut self,
+        old: &FieldDescriptor,
+        new: &FieldDescriptor,
+        old_message: &MessageDescriptor,
+        new_message: &MessageDescriptor,
+        fqn: &str,
+    ) {
+        let anchor = Some(qualify(fqn, &new.name));
+        let field = format!(
+            "Field \"{}\" with name \"{}\" on message \"{fqn}\"",
+            new.number, new.name
+        );
+
+        if old.name != new.name {
+            self.report(
+                anchor.clone(),
+                Breakage::Source,
+                "FIELD_SAME_NAME",
+                format!(
+                    "Field \"{}\" on message \"{fqn}\" changed name from \"{}\" to \"{}\".",
+                    new.number, old.name, new.name
+                ),
+            );
+        }
+
+        if cardinality(old) != cardinality(new) {
+            self.report(
+                anchor.clone(),
+                Breakage::Wire,
+                "FIELD_SAME_CARDINALITY",
+                format!(
+                    "{field} changed cardinality from \"{}\" to \"{}\".",
+                    cardinality(old),
+                    cardinality(new)
+                ),
+            );
+        }
+
+        let old_type = self.type_name(old);
+        let new_type = type_name(new);
+        if old_type != new_type {
+            let breakage = if wire_compatible(old.r#type, new.r#type) {
+                Breakage::Source
+            } else {
+                Breakage::Wire
+            };
+            self.report(
+                anchor.clone(),
+                breakage,
+                "FIELD_SAME_TYPE",
+                format!("{field} changed type from \"{old_type}\" to \"{new_type}\"."),
+            );
+        }
+
+        let old_oneof = oneof(old, old_message);
+        let new_oneof = oneof(new, new_message);
+        if old_oneof != new_oneof {
+            let describe = |oneof: Option<&str>| match oneof {
+                Some(name) => format!("oneof \"{name}\""),
+                None => String::from("no oneof"),
+            };
+            self.report(
+                anchor,
+                Breakage::Wire,
+                "FIELD_SAME_ONEOF",
+                format!(
+                    "{field} moved from {} to {}.",
+                    describe(old_oneof),
+                    describe(new_oneof)
+                ),
+            );
+        }
+    }
+
+    fn enums(&mut self, old: &[EnumDescriptor], new: &[EnumDescriptor], scope: Scope) {
+        for enumeration in old {
+            let Some(current) = new.iter().find(|e| e.name == enumeration.name) else {
+                self.report(
+                    scope.anchor(),
+                    Breakage::Source,
+                    "ENUM_NO_DELETE",
+                    format!(
+                        "Previously present enum \"{}\" was deleted.",
+                        qualify(scope.old, &enumeration.name)
+                    ),
+                );
+                continue;
+            };
+            self.enumeration(enumeration, current, &qualify(scope.new, &current.name));
+        }
+    }
+
+    fn enumeration(&mut self, old: &EnumDescriptor, new: &EnumDescriptor, fqn: &str) {
+        for value in &old.value {
+            let same_number: Vec<&str> = new
+                .value
+                .iter()
+                .filter(|v| v.number == value.number)
+                .map(|v| v.name.as_str())
+                .collect();
+            if same_number.contains(&value.name.as_str()) {
+                continue;
+            }
+            if let Some(name) = same_number.first() {
+                self.report(
+                    Some(qualify(fqn, name)),
+                    Breakage::Source,
+                    "ENUM_VALUE_SAME_NAME",
+                    format!(
+                        "Enum value \"{}\" on enum \"{fqn}\" changed name from \"{}\" to \"{name}\".",
+                        value.number, value.name
+                    ),
+                );
+            } else if in_ranges(&new.reserved_range, value.number, true) {
+                self.report(
+                    Some(fqn.to_string()),
+                    Breakage::Source,
+                    "ENUM_VALUE_NO_DELETE",
+                    format!(
+                        "Previously present enum value \"{}\" with name \"{}\" on enum \"{fqn}\" was deleted.",
+                        value.number, value.name
+                    ),
+                );
+            } else {
+                self.report(
+                    Some(fqn.to_string()),
+                    Breakage::Wire,
+                    "ENUM_VALUE_NO_DELETE_UNLESS_NUMBER_RESERVED",
+                    format!(
+                        "Previously present enum value \"{}\" with name \"{}\" on enum \"{fqn}\" was deleted without reserving the number \"{}\".",
+                        value.number, value.name, value.number
+                    ),
+                );
+            }
+        }
+
+        for value in &new.value {
+            if in_ranges(&old.reserved_range, value.number, true)
+                && !old.value.iter().any(|v| v.number == value.number)
+            {
+                self.report(
+                    Some(qualify(fqn, &value.name)),
+                    Breakage::Wire,
+                    "RESERVED_NUMBER_NO_REUSE",
+                    format!(
+                        "Enum value \"{}\" on enum \"{fqn}\" uses number {}, which was reserved.",
+                        value.name, value.number
+                    ),
+                );
+            }
+        }
+    }
+
+    fn services(&mut self, old: &[ServiceDescriptor], new: &[ServiceDescriptor], scope: Scope) {
+        for service in old {
+            let Some(current) = new.iter().find(|s| s.name == service.name) else {
+                self.report(
+                    None,
+                    Breakage::Wire,
+                    "SERVICE_NO_DELETE",
+                    format!(
+                        "Previously present service \"{}\" was deleted.",
+                        qualify(scope.old, &service.name)
+                    ),
+                );
+                continue;
+            };
+            self.service(service, current, &qualify(scope.new, &current.name));
+        }
+    }
+
+    fn service(&mut self, old: &ServiceDescriptor, new: &ServiceDescriptor, fqn: &str) {
+        for method in &old.method {
+            let Some(current) = new.method.iter().find(|m| m.name == method.name) else {
+                self.report(
+                    Some(fqn.to_string()),
+                    Breakage::Wire,
+                    "RPC_NO_DELETE",
+                    format!(
+                        "Previously present RPC \"{}\" on service \"{fqn}\" was deleted.",
+                        method.name
+                    ),
+                );
+                continue;
+            };
+            let anchor = Some(qualify(fqn, &current.name));
+            let rpc = format!("RPC \"{}\" on service \"{fqn}\"", current.name);
+
+            let types = [
+                (
+                    &method.input_type,
+                    &current.input_type,
+                    "RPC_SAME_REQUEST_TYPE",
+                    "request",
+                ),
+                (
+                    &method.output_type,
+                    &current.output_type,
+                    "RPC_SAME_RESPONSE_TYPE",
+                    "response",
+                ),
+            ];
+            for (old_type, new_type, rule, what) in types {
+                let old_type = self.rename_package(old_type);
+                if old_type != *new_type {
+                    self.report(
+                        anchor.clone(),
+                        Breakage::Wire,
+                        rule,
+                        format!(
+                            "{rpc} changed {what} type from \"{}\" to \"{}\".",
+                            old_type.trim_start_matches('.'),
+                            new_type.trim_start_matches('.')
+                        ),
+                    );
+                }
+            }
+
+            let streaming = [
+                (
+                    method.client_streaming,
+                    current.client_streaming,
+                    "RPC_SAME_CLIENT_STREAMING",
+                    "client",
+                ),
+                (
+                    method.server_streaming,
+                    current.server_streaming,
+                    "RPC_SAME_SERVER_STREAMING",
+                    "server",
+                ),
+            ];
+            for (old_streaming, new_streaming, rule, side) in streaming {
+                if old_streaming != new_streaming {
+                    let now = if new_streaming { "now" } else { "no longer" };
+                    self.report(
+                        anchor.clone(),
+                        Breakage::Wire,
+                        rule,
+                        format!("{rpc} is {now} {side} streaming."),
+                    );
+                }
+            }
+        }
+    }
+
+    /// The type of a field of the baseline, with its package renamed to the
+    /// current one.
+    fn type_name(&self, field: &FieldDescriptor) -> String {
+        match &field.type_name {
+            Some(name) => self
+                .rename_package(name)
+                .trim_start_matches('.')
+                .to_string(),
+            None => type_name(field),
+        }
+    }
+
+    /// Rewrites a fully qualified name of the baseline into the current
+    /// packages.
+    fn rename_package(&self, name: &str) -> String {
+        for (old, new) in self.packages {
+            let prefix = format!(".{old}.");
+            if let Some(rest) = name.strip_prefix(&prefix) {
+                return qualify(&format!(".{new}"), rest);
+            }
+        }
+        name.to_string()
+    }
+}
+
+fn qualify(scope: &str, name: &str) -> String {
+    if scope.is_empty() || scope == "." {
+        format!("{scope}{name}")
+    } else {
+        format!("{scope}.{name}")
+    }
+}
+
+/// Whether `number` is in one of `ranges`, whose end is exclusive for
+/// messages and inclusive for enums.
+fn in_ranges(ranges: &[(i32, i32)], number: i32, inclusive: bool) -> bool {
+    ranges
+        .iter()
+        .any(|&(start, end)| number >= start && (number < end || (inclusive && number == end)))
+}
+
+fn cardinality(field: &FieldDescriptor) -> &'static str {
+    match field.label {
+        Some(FieldLabel::Repeated) => "repeated",
+        Some(FieldLabel::Required) => "required",
+        _ => "optional",
+    }
+}
+
+/// The oneof `field` is declared in, ignoring the synthetic oneofs of proto3
+/// `optional` fields.
+fn oneof<'m>(field: &FieldDescriptor, message: &'m MessageDescriptor) -> Option<&'m str> {
+    if field.proto3_optional {
+        return None;
+    }
+    let index = usize::try_from(field.oneof_index?).ok()?;
+    message.oneof_decl.get(index).map(String::as_str)
+}
+
+/// The type of a field as written in a proto file, fully qualified for
+/// messages and enums.
+fn type_name(field: &FieldDescriptor) -> String {
+    if let Some(name) = &field.type_name {
+        return name.trim_start_matches('.').to_string();
+    }
+    let name = match field.r#type {
+        Some(FieldType::Double) => "double",
+        Some(FieldType::Float) => "float",
+        Some(FieldType::Int64) => "int64",
+        Some(FieldType::Uint64) => "uint64",
+        Some(FieldType::Int32) => "int32",
+        Some(FieldType::Fixed64) => "fixed64",
+        Some(FieldType::Fixed32) => "fixed32",
+        Some(FieldType::Bool) => "bool",
+        Some(FieldType::String) => "string",
+        Some(FieldType::Bytes) => "bytes",
+        Some(FieldType::Uint32) => "uint32",
+        Some(FieldType::Sfixed32) => "sfixed32",
+        Some(FieldType::Sfixed64) => "sfixed64",
+        Some(FieldType::Sint32) => "sint32",
+        Some(FieldType::Sint64) => "sint64",
+        Some(FieldType::Message | FieldType::Enum) | None => "",
+    };
+    name.to_string()
+}
+
+/// Whether values of type `old` are still read correctly as `new`, which
+/// makes a change between them break only generated code.
+fn wire_compatible(old: Option<FieldType>, new: Option<FieldType>) -> bool {
+    use FieldType::{
+        Bool, Bytes, Enum, Fixed32, Fixed64, Int32, Int64, Sfixed32, Sfixed64, Sint32, Sint64,
+        String, Uint32, Uint64,
+    };
+    let group = |t: Option<FieldType>| match t {
+        Some(Int32 | Int64 | Uint32 | Uint64 | Bool | Enum) => 1,
+        Some(Sint32 | Sint64) => 2,
+        Some(Fixed32 | Sfixed32) => 3,
+        Some(Fixed64 | Sfixed64) => 4,
+        Some(String | Bytes) => 5,
+        _ => 0,
+    };
+    group(old) != 0 && group(old) == group(new)
+}
@@ -0,0 +1,937 @@
+//! Builds the descriptor of a document and reports what protoc would reject:
+//! names that do not resolve, misused or duplicate numbers and names, and the
+//! rules of each syntax.
+
+use std::collections::HashMap;
+
+use async_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Range};
+
+use crate::document::ProtoDocument;
+use crate::model::{
+    CardinalityKind, ElementKind, FieldCardinality, ModelElement, SyntaxKind, TypeReference,
+};
+
+use super::symbols::{Lookup, Origin, Symbol, SymbolKind, SymbolView, enum_value_fqn};
+use super::tree::{self, IMPLEMENTATION_RESERVED, MAX_FIELD_NUMBER, NumberRange};
+use super::{
+    EnumDescriptor, EnumValueDescriptor, FieldDescriptor, FieldLabel, FieldType, FileDescriptor,
+    MessageDescriptor, MethodDescriptor, ServiceDescriptor,
+};
+use crate::model::OptionEntry;
+use crate::utils::parent_scope;
+
+const FILE_OPTIONS: &[&str] = &[
+    "java_package",
+    "java_outer_classname",
+    "java_multiple_files",
+    "java_generate_equals_and_hash",
+    "java_string_check_utf8",
+    "optimize_for",
+    "go_package",
+    "cc_generic_services",
+    "java_generic_services",
+    "py_generic_services",
+    "deprecated",
+    "cc_enable_arenas",
+    "objc_class_prefix",
+    "csharp_namespace",
+    "swift_prefix",
+    "php_class_prefix",
+    "php_namespace",
+    "php_metadata_namespace",
+    "ruby_package",
+    "features",
+];
+const MESSAGE_OPTIONS: &[&str] = &[
+    "message_set_wire_format",
+    "no_standard_descriptor_accessor",
+    "deprecated",
+    "map_entry",
+    "deprecated_legacy_json_field_conflicts",
+    "features",
+];
+const FIELD_OPTIONS: &[&str] = &[
+    "ctype",
+    "packed",
+    "jstype",
+    "lazy",
+    "unverified_lazy",
+    "deprecated",
+    "weak",
+    "debug_redact",
+    "retention",
+    "targets",
+    "edition_defaults",
+    "features",
+    "feature_support",
+];
+const ONEOF_OPTIONS: &[&str] = &["features"];
+const ENUM_OPTIONS: &[&str] = &[
+    "allow_alias",
+    "deprecated",
+    "deprecated_legacy_json_field_conflicts",
+    "features",
+];
+const ENUM_VALUE_OPTIONS: &[&str] = &["deprecated", "features", "debug_redact", "feature_support"];
+const SERVICE_OPTIONS: &[&str] = &["deprecated", "features"];
+const METHOD_OPTIONS: &[&str] = &["deprecated", "idempotency_level", "features"];
+
+/// Builds the descriptor of `document`, known as `file`, resolving names
+/// against `symbols`, and returns it with the errors found.
+///
+/// With `missing_imports`, names that cannot be found are not reported: the
+/// imports that could not be found already explain them.
+pub fn compile(
+    document: &ProtoDocument,
+    content: &str,
+    file: &str,
+    symbols: &SymbolView,
+    missing_imports: bool,
+) -> (FileDescriptor, Vec<Diagnostic>) {
+    let mut compiler = Compiler {
+        document,
+        content,
+        file,
+        symbols,
+        report_not_found: !missing_imports,
+        diagnostics: Vec::new(),
+    };
+    let descriptor = compiler.file();
+    compiler.check_duplicates();
+    (descriptor, compiler.diagnostics)
+}
+
+/// The number ranges and names a message or enum reserves.
+struct Reservations {
+    ranges: Vec<NumberRange>,
+    names: Vec<(String, Range)>,
+}
+
+struct Compiler<'a> {
+    document: &'a ProtoDocument,
+    content: &'a str,
+    file: &'a str,
+    symbols: &'a SymbolView<'a>,
+    report_not_found: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Compiler<'a> {
+    fn error(&mut self, range: Range, message: String) {
+        self.diagnostics.push(Diagnostic {
+            range,
+            severity: Some(DiagnosticSeverity::ERROR),
+            source: Some(String::from("protols")),
+            message,
+            ..Default::default()
+        });
+    }
+
+    fn is_proto3(&self) -> bool {
+        self.document.syntax == SyntaxKind::Proto3
+    }
+
+    fn file(&mut self) -> FileDescriptor {
+        let document = self.document;
+        let (syntax, edition) = match &document.syntax {
+            SyntaxKind::Proto2 => ("proto2", None),
+            SyntaxKind::Proto3 => ("proto3", None),
+            SyntaxKind::Editions { edition } => ("editions", Some(edition.clone())),
+        };
+        let mut descriptor = FileDescriptor {
+            name: self.file.to_string(),
+            package: document.package.clone(),
+            options: document.options.clone(),
+            syntax: syntax.to_string(),
+            edition,
+            ..Default::default()
+        };
+
+        self.imports(&mut descriptor);
+        self.check_options(&document.options, &document.package, FILE_OPTIONS);
+
+        for element in document.elements.iter().filter(|e| e.parent_id.is_none()) {
+            match &element.kind {
+                ElementKind::Message { .. } => descriptor.message_type.push(self.message(element)),
+                ElementKind::Enum { .. } => descriptor.enum_type.push(self.enumeration(element)),
+                ElementKind::Service { .. } => descriptor.service.push(self.service(element)),
+                ElementKind::Field { .. } => descriptor.extension.extend(self.extension(element)),
+                _ => {}
+            }
+        }
+        descriptor
+    }
+
+    fn imports(&mut self, descriptor: &mut FileDescriptor) {
+        for element in &self.document.elements {
+            let ElementKind::Import { path } = &element.kind else {
+                continue;
+            };
+            if descriptor.dependency.contains(path) {
+                self.error(
+                    element.meta.range,
+                    format!("Import \"{path}\" was listed twice."),
+                );
+                continue;
+            }
+            let index = to_i32(descriptor.dependency.len());
+            match tree::node_at(&self.document.tree, element.meta.range, &["import"])
+                .and_then(tree::import_modifier)
+            {
+                Some("public") => descriptor.public_dependency.push(index),
+                Some("weak") => descriptor.weak_dependency.push(index),
+                _ => {}
+            }
+            descriptor.dependency.push(path.clone());
+        }
+    }
+
+    fn reservations(&self, node: Option<tree_sitter::Node>, max_value: i64) -> Reservations {
+        let body = node.and_then(tree::body);
+        Reservations {
+            ranges: body
+                .map(|b| tree::number_ranges(b, self.content, "reserved", max_value))
+                .unwrap_or_default(),
+            names: body
+                .map(|b| tree::reserved_names(b, self.content))
+                .unwrap_or_default(),
+        }
+    }
+
+    fn message(&mut self, element: &ModelElement) -> MessageDescriptor {
+        let document = self.document;
+        let fqn = element.kind.fqn().unwrap_or_default();
+        let node = tree::node_at(&self.document.tree, element.meta.range, &["message"]);
+        let reserved = self.reservations(node, MAX_FIELD_NUMBER);
+        let extension_ranges = node
+            .and_then(tree::body)
+            .map(|b| tree::number_ranges(b, self.content, "extensions", MAX_FIELD_NUMBER))
+            .unwrap_or_default();
+        if self.is_proto3()
+            && let Some(range) = extension_ranges.first()
+        {
+            self.error(
+                range.range,
+                "Extension ranges are not allowed in proto3.".to_string(),
+            );
+        }
+        self.check_options(&element.meta.options, parent_scope(fqn), MESSAGE_OPTIONS);
+
+        let mut message = MessageDescriptor {
+            name: element.meta.name.clone(),
+            options: element.meta.options.clone(),
+            extension_range: extension_ranges
+                .iter()
+                .map(|r| (to_i32(r.start), to_i32(r.end.saturating_add(1))))
+                .collect(),
+            reserved_range: reserved
+                .ranges
+                .iter()
+                .map(|r| (to_i32(r.start), to_i32(r.end.saturating_add(1))))
+                .collect(),
+            reserved_name: reserved.names.iter().map(|(n, _)| n.clone()).collect(),
+            ..Default::default()
+        };
+
+        // Regular fields, with the range of their name and number.
+        let mut fields: Vec<(Range, Range)> = Vec::new();
+        for child in element
+            .children
+            .iter()
+            .filter_map(|id| document.elements.get(*id))
+        {
+            match &child.kind {
+                ElementKind::Field { .. } => {
+                    if let Some(extension) = self.extension(child) {
+                        message.extension.push(extension);
+                    } else {
+                        let (field, number_range) = self.field(child, None);
+                        message.field.push(field);
+                        fields.push((child.meta.selection_range, number_range));
+                    }
+                }
+                ElementKind::MapField { .. } => {
+                    let (field, entry, number_range) = self.map_field(child, fqn);
+                    message.field.push(field);
+                    message.nested_type.push(entry);
+                    fields.push((child.meta.selection_range, number_range));
+                }
+                ElementKind::Oneof { .. } => {
+                    let index = to_i32(message.oneof_decl.len());
+                    message.oneof_decl.push(child.meta.name.clone());
+                    self.check_options(&child.meta.options, fqn, ONEOF_OPTIONS);
+                    for field in child
+                        .children
+                        .iter()
+                        .filter_map(|id| document.elements.get(*id))
+                    {
+                        let (descriptor, number_range) = self.field(field, Some(index));
+                        message.field.push(descriptor);
+                        fields.push((field.meta.selection_range, number_range));
+                    }
+                }
+                ElementKind::Message { .. } => message.nested_type.push(self.message(child)),
+                ElementKind::Enum { .. } => message.enum_type.push(self.enumeration(child)),
+                _ => {}
+            }
+        }
+
+        self.check_fields(fqn, &message.field, &fields, &reserved, &extension_ranges);
+        add_synthetic_oneofs(&mut message);
+        message
+    }
+
+    /// Checks the numbers and names of the regular fields of the message `fqn`.
+    fn check_fields(
+        &mut self,
+        fqn: &str,
+        descriptors: &[FieldDescriptor],
+        fields: &[(Range, Range)],
+        reserved: &Reservations,
+        extension_ranges: &[NumberRange],
+    ) {
+        let mut numbers: HashMap<i32, &str> = HashMap::new();
+        let mut json_names: HashMap<String, &str> = HashMap::new();
+        for (field, &(name_range, number_range)) in descriptors.iter().zip(fields) {
+            let name = field.name.as_str();
+            let number = i64::from(field.number);
+            self.check_number(number, number_range);
+
+            if let Some(other) = numbers.insert(field.number, name) {
+                self.error(
+                    number_range,
+                    format!(
+                        "Field number {number} has already been used in \"{fqn}\" by field \"{other}\"."
+                    ),
+                );
+            }
+            if reserved.ranges.iter().any(|r| r.contains(number)) {
+                self.error(
+                    number_range,
+                    format!("Field \"{name}\" uses reserved number {number}."),
+                );
+            }
+            if reserved.names.iter().any(|(n, _)| n == name) {
+                self.error(name_range, format!("Field name \"{name}\" is reserved."));
+            }
+            if let Some(range) = extension_ranges.iter().find(|r| r.contains(number)) {
+                self.error(
+                    number_range,
+                    format!(
+                        "Extension range {} to {} includes field \"{name}\" ({number}).",
+                        range.start, range.end
+                    ),
+                );
+            }
+
+            let json_name = to_json_name(name);
+            if self.is_proto3()
+                && let Some(other) = json_names.insert(json_name.clone(), name)
+            {
+                self.error(
+                    name_range,
+                    format!(
+                        "The default JSON name of field \"{name}\" (\"{json_name}\") conflicts with the default JSON name of field \"{other}\"."
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_number(&mut self, number: i64, range: Range) {
+        let message = if number <= 0 {
+            "Field numbers must be positive integers."
+        } else if number > MAX_FIELD_NUMBER {
+            "Field numbers cannot be greater than 536870911."
+        } else if IMPLEMENTATION_RESERVED.contains(&number) {
+            "Field numbers 19000 through 19999 are reserved for the protocol buffer library implementation."
+        } else {
+            return;
+        };
+        self.error(range, message.to_string());
+    }
+
+    /// Builds the descriptor of a field or oneof field and returns it with
+    /// the range of its number.
+    fn field(
+        &mut self,
+        element: &ModelElement,
+        oneof_index: Option<i32>,
+    ) -> (FieldDescriptor, Range) {
+        let (type_ref, cardinality, tag) = match &element.kind {
+            ElementKind::Field {
+                type_ref,
+                cardinality,
+                tag,
+                ..
+            } => (type_ref, cardinality.as_ref(), *tag),
+            ElementKind::OneofField { type_ref, tag, .. } => (type_ref, None, *tag),
+            _ => return (FieldDescriptor::default(), element.meta.selection_range),
+        };
+        let fqn = element.kind.fqn().unwrap_or_default();
+        let scope = parent_scope(fqn);
+
+        let (number, number_range) = tree::node_at(
+            &self.document.tree,
+            element.meta.range,
+            &["field", "oneof_field"],
+        )
+        .and_then(|n| tree::number(n, self.content))
+        .unwrap_or((i64::from(tag), element.meta.selection_range));
+
+        let label = self.label(cardinality, oneof_index.is_some(), type_ref.range);
+        let (r#type, type_name) = self.field_type(scope, type_ref);
+        let mut field = FieldDescriptor {
+            name: element.meta.name.clone(),
+            number: to_i32(number),
+            label: Some(label),
+            r#type,
+            type_name,
+            oneof_index,
+            proto3_optional: self.is_proto3()
+                && cardinality.is_some_and(|c| c.kind == CardinalityKind::Optional),
+            ..Default::default()
+        };
+        self.field_options(element, &mut field, scope);
+        (field, number_range)
+    }
+
+    fn label(
+        &mut self,
+        cardinality: Option<&FieldCardinality>,
+        in_oneof: bool,
+        type_range: Range,
+    ) -> FieldLabel {
+        match cardinality.map(|c| (&c.kind, c.range)) {
+            Some((CardinalityKind::Repeated, _)) => FieldLabel::Repeated,
+            Some((CardinalityKind::Required, range)) => {
+                if self.is_proto3() {
+                    self.error(
+                        range,
+                        "Required fields are not allowed in proto3.".to_string(),
+                    );
+                }
+                FieldLabel::Required
+            }
+            Some((CardinalityKind::Optional, _)) => FieldLabel::Optional,
+            None => {
+                if !in_oneof && self.document.syntax == SyntaxKind::Proto2 {
+                    self.error(
+                        type_range,
+                        "Expected \"required\", \"optional\", or \"repeated\".".to_string(),
+                    );
+                }
+                FieldLabel::Optional
+            }
+        }
+    }
+
+    /// Resolves the type of a field, returning the type name as written when
+    /// it does not resolve.
+    fn field_type(
+        &mut self,
+        scope: &str,
+        type_ref: &TypeReference,
+    ) -> (Option<FieldType>, Option<String>) {
+        if let Some(scalar) = FieldType::from_scalar(&type_ref.name) {
+            return (Some(scalar), None);
+        }
+        match self.resolve(scope, &type_ref.name, type_ref.range, true) {
+            Some((fqn, symbol)) => match symbol.kind {
+                SymbolKind::Message => (Some(FieldType::Message), Some(format!(".{fqn}"))),
+                SymbolKind::Enum => (Some(FieldType::Enum), Some(format!(".{fqn}"))),
+                _ => {
+                    self.error(
+                        type_ref.range,
+                        format!("\"{}\" is not a type.", type_ref.name),
+                    );
+                    (None, Some(type_ref.name.clone()))
+                }
+            },
+            None => (None, Some(type_ref.name.clone())),
+        }
+    }
+
+    /// Applies the `default` and `json_name` pseudo-options of a field and
+    /// checks its other options.
+    fn field_options(&mut self, element: &ModelElement, field: &mut FieldDescriptor, scope: &str) {
+        for option in &element.meta.options {
+            match option.name.as_str() {
+                "default" => {
+                    let message = if self.is_proto3() {
+                        "Explicit default values are not allowed in proto3."
+                    } else if field.label == Some(FieldLabel::Repeated) {
+                        "Repeated fields can't have default values."
+                    } else if field.r#type == Some(FieldType::Message) {
+                        "Messages can't have default values."
+                    } else {
+                        field.default_value = Some(unquote(&option.value));
+                        continue;
+                    };
+                    self.error(option.range, message.to_string());
+                }
+                "json_name" => field.json_name = Some(unquote(&option.value)),
+                _ => {
+                    self.check_options(std::slice::from_ref(option), scope, FIELD_OPTIONS);
+                    field.options.push(option.clone());
+                }
+            }
+        }
+        field
+            .json_name
+            .get_or_insert_with(|| to_json_name(&field.name));
+    }
+
+    /// Builds a map field and the entry message synthesized for it, and
+    /// returns them with the range of the field number.
+    fn map_field(
+        &mut self,
+        element: &ModelElement,
+        message_fqn: &str,
+    ) -> (FieldDescriptor, MessageDescriptor, Range) {
+        let ElementKind::MapField {
+            key_type_ref,
+            value_type_ref,
+            tag,
+            ..
+        } = &element.kind
+        else {
+            return (
+                FieldDescriptor::default(),
+                MessageDescriptor::default(),
+                element.meta.selection_range,
+            );
+        };
+        let (number, number_range) =
+            tree::node_at(&self.document.tree, element.meta.range, &["map_field"])
+                .and_then(|n| tree::number(n, self.content))
+                .unwrap_or((i64::from(*tag), element.meta.selection_range));
+
+        let key_type = FieldType::from_scalar(&key_type_ref.name);
+        if matches!(
+            key_type,
+            None | Some(FieldType::Float | FieldType::Double | FieldType::Bytes)
+        ) {
+            self.error(
+                key_type_ref.range,
+                "Key in map fields cannot be float/double, bytes or message types.".to_string(),
+            );
+        }
+        let (value_type, value_type_name) = self.field_type(message_fqn, value_type_ref);
+
+        let entry_field = |name: &str, number, r#type, type_name| FieldDescriptor {
+            name: name.to_string(),
+            number,
+            label: Some(FieldLabel::Optional),
+            r#type,
+            type_name,
+            json_name: Some(name.to_string()),
+            ..Default::default()
+        };
+        let entry = MessageDescriptor {
+            name: map_entry_name(&element.meta.name),
+            field: vec![
+                entry_field("key", 1, key_type, None),
+                entry_field("value", 2, value_type, value_type_name),
+            ],
+            map_entry: true,
+            ..Default::default()
+        };
+
+        let mut field = FieldDescriptor {
+            name: element.meta.name.clone(),
+            number: to_i32(number),
+            label: Some(FieldLabel::Repeated),
+            r#type: Some(FieldType::Message),
+            type_name: Some(format!(".{message_fqn}.{}", entry.name)),
+            ..Default::default()
+        };
+        self.field_options(element, &mut field, message_fqn);
+        (field, entry, number_range)
+    }
+
+    /// Builds the descriptor of a field declared in an `extend` block, or
+    /// returns `None` if `element` is a regular field.
+    fn extension(&mut self, element: &ModelElement) -> Option<FieldDescriptor> {
+        let node = tree::node_at(&self.document.tree, element.meta.range, &["field"])?;
+        let (extendee, extendee_range) = tree::extendee(node, self.content)?;
+        let (mut field, number_range) = self.field(element, None);
+        let number = i64::from(field.number);
+        self.check_number(number, number_range);
+
+        let scope = parent_scope(element.kind.fqn().unwrap_or_default());
+        let Some((fqn, symbol)) = self.resolve(scope, &extendee, extendee_range, false) else {
+            field.extendee = Some(extendee);
+            return Some(field);
+        };
+        if symbol.kind != SymbolKind::Message {
+            self.error(
+                extendee_range,
+                format!("\"{extendee}\" is not a message type."),
+            );
+        } else if !symbol.extension_ranges.iter().any(|r| r.contains(number)) {
+            self.error(
+                number_range,
+                format!("\"{fqn}\" does not declare {number} as an extension number."),
+            );
+        }
+        if self.is_proto3() && !(fqn.starts_with("google.protobuf.") && fqn.ends_with("Options")) {
+            self.error(
+                extendee_range,
+                "Extensions in proto3 are only allowed for defining options.".to_string(),
+            );
+        }
+        field.extendee = Some(format!(".{fqn}"));
+        Some(field)
+    }
+
+    fn enumeration(&mut self, element: &ModelElement) -> EnumDescriptor {
+        let document = self.document;
+        let fqn = element.kind.fqn().unwrap_or_default();
+        let node = tree::node_at(&self.document.tree, element.meta.range, &["enum"]);
+        let reserved = self.reservations(node, i64::from(i32::MAX));
+        self.check_options(&element.meta.options, parent_scope(fqn), ENUM_OPTIONS);
+
+        let mut descriptor = EnumDescriptor {
+            name: element.meta.name.clone(),
+            options: element.meta.options.clone(),
+            reserved_range: reserved
+                .ranges
+                .iter()
+                .map(|r| (to_i32(r.start), to_i32(r.end)))
+                .collect(),
+            reserved_name: reserved.names.iter().map(|(n, _)| n.clone()).collect(),
+            ..Default::default()
+        };
+
+        let mut numbers: HashMap<i64, String> = HashMap::new();
+        let mut aliased = false;
+        for value in element
+            .children
+            .iter()
+            .filter_map(|id| document.elements.get(*id))
+        {
+            let ElementKind::EnumValue {
+                fqn: value_fqn,
+                number,
+                ..
+            } = &value.kind
+            else {
+                continue;
+            };
+            let name = value.meta.name.as_str();
+            let (number, number_range) =
+                tree::node_at(&self.document.tree, value.meta.range, &["enum_field"])
+                    .and_then(|n| tree::number(n, self.content))
+                    .unwrap_or((i64::from(*number), value.meta.selection_range));
+
+            if descriptor.value.is_empty() && number != 0 && self.is_proto3() {
+                self.error(
+                    number_range,
+                    "The first enum value must be zero for open enums.".to_string(),
+                );
+            }
+            let value_fqn = enum_value_fqn(value_fqn);
+            if let Some(first) = numbers.get(&number) {
+                aliased = true;
+                if !is_alias_allowed(&element.meta.options) {
+                    self.error(
+                        number_range,
+                        format!(
+                            "\"{value_fqn}\" uses the same enum value as \"{first}\". If this is intended, set 'option allow_alias = true;' to the enum definition."
+                        ),
+                    );
+                }
+            } else {
+                numbers.insert(number, value_fqn);
+            }
+            if reserved.ranges.iter().any(|r| r.contains(number)) {
+                self.error(
+                    number_range,
+                    format!("Enum value \"{name}\" uses reserved number {number}."),
+                );
+            }
+            if reserved.names.iter().any(|(n, _)| n == name) {
+                self.error(
+                    value.meta.selection_range,
+                    format!("Enum value \"{name}\" is reserved."),
+                );
+            }
+
+            self.check_options(&value.meta.options, parent_scope(fqn), ENUM_VALUE_OPTIONS);
+            descriptor.value.push(EnumValueDescriptor {
+                name: name.to_string(),
+                options: value.meta.options.clone(),
+                number: to_i32(number),
+            });
+        }
+
+        if descriptor.value.is_empty() {
+            self.error(
+                element.meta.selection_range,
+                "Enums must contain at least one value.".to_string(),
+            );
+        }
+        if !aliased
+            && let Some(option) = element
+                .meta
+                .options
+                .iter()
+                .find(|o| o.name == "allow_alias" && o.value == "true")
+        {
+            self.error(
+                option.range,
+                format!(
+                    "\"{fqn}\" declares support for enum aliases but no enum values share field numbers. Please remove the unnecessary 'option allow_alias = true;' declaration."
+                ),
+            );
+        }
+        descriptor
+    }
+
+    fn service(&mut self, element: &ModelElement) -> ServiceDescriptor {
+        let document = self.document;
+        let fqn = element.kind.fqn().unwrap_or_default();
+        self.check_options(&element.meta.options, parent_scope(fqn), SERVICE_OPTIONS);
+
+        let mut descriptor = ServiceDescriptor {
+            name: element.meta.name.clone(),
+            options: element.meta.options.clone(),
+            ..Default::default()
+        };
+        for rpc in element
+            .children
+            .iter()
+            .filter_map(|id| document.elements.get(*id))
+        {
+            let ElementKind::Rpc {
+                request_type_ref,
+                request_stream,
+                response_type_ref,
+                response_stream,
+                ..
+            } = &rpc.kind
+            else {
+                continue;
+            };
+            self.check_options(&rpc.meta.options, fqn, METHOD_OPTIONS);
+            descriptor.method.push(MethodDescriptor {
+                name: rpc.meta.name.clone(),
+                options: rpc.meta.options.clone(),
+                input_type: self.message_type(fqn, request_type_ref),
+                output_type: self.message_type(fqn, response_type_ref),
+                client_streaming: request_stream.is_some(),
+                server_streaming: response_stream.is_some(),
+            });
+        }
+        descriptor
+    }
+
+    /// Resolves the request or response type of an rpc.
+    fn message_type(&mut self, scope: &str, type_ref: &TypeReference) -> String {
+        match self.resolve(scope, &type_ref.name, type_ref.range, false) {
+            Some((fqn, symbol)) if symbol.kind == SymbolKind::Message => format!(".{fqn}"),
+            Some(_) => {
+                self.error(
+                    type_ref.range,
+                    format!("\"{}\" is not a message type.", type_ref.name),
+                );
+                type_ref.name.clone()
+            }
+            None => type_ref.name.clone(),
+        }
+    }
+
+    /// Checks that the standard options are known and that custom options
+    /// resolve to an extension.
+    fn check_options(&mut self, options: &[OptionEntry], scope: &str, known: &[&str]) {
+        for option in options {
+            if let Some(name) = option
+                .name
+                .strip_prefix('(')
+                .and_then(|n| n.split_once(')'))
+                .map(|(n, _)| n)
+            {
+                let Some((_, symbol)) = self.resolve_option(scope, name, option.range) else {
+                    continue;
+                };
+                if symbol.kind != SymbolKind::Field {
+                    self.error(
+                        option.range,
+                        format!("Option \"({name})\" is not a field or extension."),
+                    );
+                }
+            } else {
+                let name = option.name.split('.').next().unwrap_or_default();
+                if !known.contains(&name) {
+                    self.error(option.range, format!("Option \"{name}\" unknown."));
+                }
+            }
+        }
+    }
+
+    fn resolve_option(
+        &mut self,
+        scope: &str,
+        name: &str,
+        range: Range,
+    ) -> Option<(String, &'a Symbol)> {
+        if let Lookup::NotFound = self.symbols.lookup(scope, name, false) {
+            if self.report_not_found {
+                self.error(
+                    range,
+                    format!(
+                        "Option \"({name})\" unknown. Ensure that your proto definition file imports the proto which defines the option."
+                    ),
+                );
+            }
+            return None;
+        }
+        self.resolve(scope, name, range, false)
+    }
+
+    /// Looks `name` up from `scope`, reporting why it does not resolve.
+    fn resolve(
+        &mut self,
+        scope: &str,
+        name: &str,
+        range: Range,
+        types_only: bool,
+    ) -> Option<(String, &'a Symbol)> {
+        let message = match self.symbols.lookup(scope, name, types_only) {
+            Lookup::Found { fqn, symbol } => return Some((fqn, symbol)),
+            Lookup::NotImported { file } => format!(
+                "\"{name}\" seems to be defined in \"{file}\", which is not imported by \"{}\".  To use it here, please add the necessary import.",
+                self.file
+            ),
+            Lookup::Shadowed { fqn } => format!(
+                "\"{name}\" is resolved to \"{fqn}\", which is not defined. The innermost scope is searched first in name resolution. Consider using a leading '.'(i.e., \".{name}\") to start from the outermost scope."
+            ),
+            Lookup::NotFound if self.report_not_found => format!("\"{name}\" is not defined."),
+            Lookup::NotFound => return None,
+        };
+        self.error(range, message);
+        None
+    }
+
+    /// Reports the definitions of the document whose name is already taken,
+    /// in the same file by an earlier definition or in a loaded file.
+    fn check_duplicates(&mut self) {
+        let document = self.document;
+        for element in &document.elements {
+            let Some(fqn) = element.kind.fqn() else {
+                continue;
+            };
+            let is_enum_value = matches!(element.kind, ElementKind::EnumValue { .. });
+            let fqn = if is_enum_value {
+                enum_value_fqn(fqn)
+            } else {
+                fqn.to_string()
+            };
+            let range = element.meta.selection_range;
+            let Some(other) = self.symbols.definitions(&fqn).find(|s| {
+                s.kind != SymbolKind::Package
+                    && (self.symbols.origin(s) != Origin::Current || s.range.start < range.start)
+            }) else {
+                continue;
+            };
+
+            let (scope, name) = fqn.rsplit_once('.').unwrap_or(("", &fqn));
+            let message = if self.symbols.origin(other) != Origin::Current {
+                format!(
+                    "\"{fqn}\" is already defined in file \"{}\".",
+                    self.symbols.file(other)
+                )
+            } else if scope.is_empty() {
+                format!("\"{name}\" is already defined.")
+            } else if is_enum_value {
+                let enum_name = element
+                    .parent_id
+                    .and_then(|id| document.elements.get(id))
+                    .map(|e| e.meta.name.as_str())
+                    .unwrap_or_default();
+                format!(
+                    "\"{name}\" is already defined in \"{scope}\". Note that enum values use C++ scoping rules, meaning that enum values are siblings of their type, not children of it.  Therefore, \"{name}\" must be unique within \"{scope}\", not just within \"{enum_name}\"."
+                )
+            } else {
+                format!("\"{name}\" is already defined in \"{scope}\".")
+            };
+            self.error(range, message);
+        }
+    }
+}
+
+/// Adds the oneofs protoc synthesizes for proto3 `optional` fields, after
+/// the declared ones.
+fn add_synthetic_oneofs(message: &mut MessageDescriptor) {
+    for index in 0..message.field.len() {
+        if !message.field[index].proto3_optional {
+            continue;
+        }
+        let mut name = format!("_{}", message.field[index].name);
+        while message.oneof_decl.contains(&name) || message.field.iter().any(|f| f.name == name) {
+            name.insert(0, 'X');
+        }
+        message.field[index].oneof_index = Some(to_i32(message.oneof_decl.len()));
+        message.oneof_decl.push(name);
+    }
+}
+
+fn is_alias_allowed(options: &[OptionEntry]) -> bool {
+    options
+        .iter()
+        .any(|o| o.name == "allow_alias" && o.value == "true")
+}
+
+fn to_i32(value: impl TryInto<i32>) -> i32 {
+    value.try_into().unwrap_or(i32::MAX)
+}
+
+/// Returns the contents of a string literal, or the text of any other
+/// constant.
+pub(super) fn unquote(value: &str) -> String {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        .unwrap_or(value)
+        .to_string()
+}
+
+/// Returns the JSON name protoc derives from a field name: underscores are
+/// dropped and the letter following each is capitalized.
+pub(super) fn to_json_name(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut capitalize = false;
+    for c in name.chars() {
+        if c == '_' {
+            capitalize = true;
+        } else if capitalize {
+            out.push(c.to_ascii_uppercase());
+            capitalize = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Returns the name of the entry message of a map field: `my_map` gives
+/// `MyMapEntry`.
+fn map_entry_name(field: &str) -> String {
+    let mut out = String::with_capacity(field.len() + 5);
+    let mut capitalize = true;
+    for c in field.chars() {
+        if c == '_' {
+            capitalize = true;
+        } else if capitalize {
+            out.push(c.to_ascii_uppercase());
+            capitalize = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out.push_str("Entry");
+    out
+}
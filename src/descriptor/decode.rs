@@ -0,0 +1,339 @@
+//! Reads a `google.protobuf.FileDescriptorSet` in the protobuf wire format,
+//! as written by `protoc --descriptor_set_out`, `buf build` or
+//! `protols descriptor-set`.
+//!
+//! Only what describes the shape of the files is kept: options are dropped,
+//! except `map_entry` which tells synthesized map entries apart.
+
+use super::{
+    EnumDescriptor, EnumValueDescriptor, FieldDescriptor, FieldLabel, FieldType, FileDescriptor,
+    MessageDescriptor, MethodDescriptor, ServiceDescriptor,
+};
+
+/// Decodes a `google.protobuf.FileDescriptorSet`.
+pub fn decode_file_descriptor_set(bytes: &[u8]) -> Result<Vec<FileDescriptor>, String> {
+    let mut files = Vec::new();
+    for (field, value) in Reader::new(bytes) {
+        if let (1, Value::Bytes(bytes)) = (field, value?) {
+            files.push(file_descriptor(bytes)?);
+        }
+    }
+    Ok(files)
+}
+
+fn file_descriptor(bytes: &[u8]) -> Result<FileDescriptor, String> {
+    let mut file = FileDescriptor {
+        syntax: String::from("proto2"),
+        ..Default::default()
+    };
+    for (field, value) in Reader::new(bytes) {
+        match (field, value?) {
+            (1, Value::Bytes(b)) => file.name = string(b)?,
+            (2, Value::Bytes(b)) => file.package = string(b)?,
+            (3, Value::Bytes(b)) => file.dependency.push(string(b)?),
+            (4, Value::Bytes(b)) => file.message_type.push(message_descriptor(b)?),
+            (5, Value::Bytes(b)) => file.enum_type.push(enum_descriptor(b)?),
+            (6, Value::Bytes(b)) => file.service.push(service_descriptor(b)?),
+            (7, Value::Bytes(b)) => file.extension.push(field_descriptor(b)?),
+            (10, Value::Varint(v)) => file.public_dependency.push(int32(v)),
+            (11, Value::Varint(v)) => file.weak_dependency.push(int32(v)),
+            (12, Value::Bytes(b)) => file.syntax = string(b)?,
+            (14, Value::Varint(v)) => {
+                file.edition = match int32(v) {
+                    1000 => Some(String::from("2023")),
+                    1001 => Some(String::from("2024")),
+                    _ => None,
+                };
+            }
+            _ => {}
+        }
+    }
+    Ok(file)
+}
+
+fn message_descriptor(bytes: &[u8]) -> Result<MessageDescriptor, String> {
+    let mut message = MessageDescriptor::default();
+    for (field, value) in Reader::new(bytes) {
+        match (field, value?) {
+            (1, Value::Bytes(b)) => message.name = string(b)?,
+            (2, Value::Bytes(b)) => message.field.push(field_descriptor(b)?),
+            (3, Value::Bytes(b)) => message.nested_type.push(message_descriptor(b)?),
+            (4, Value::Bytes(b)) => message.enum_type.push(enum_descriptor(b)?),
+            (5, Value::Bytes(b)) => message.extension_range.push(range(b)?),
+            (6, Value::Bytes(b)) => message.extension.push(field_descriptor(b)?),
+            (7, Value::Bytes(b)) => {
+                for (option, value) in Reader::new(b) {
+                    if let (7, Value::Varint(v)) = (option, value?) {
+                        message.map_entry = v != 0;
+                    }
+                }
+            }
+            (8, Value::Bytes(b)) => {
+                let mut name = String::new();
+                for (field, value) in Reader::new(b) {
+                    if let (1, Value::Bytes(b)) = (field, value?) {
+                        name = string(b)?;
+                    }
+                }
+                message.oneof_decl.push(name);
+            }
+            (9, Value::Bytes(b)) => message.reserved_range.push(range(b)?),
+            (10, Value::Bytes(b)) => message.reserved_name.push(string(b)?),
+            _ => {}
+        }
+    }
+    Ok(message)
+}
+
+fn field_descriptor(bytes: &[u8]) -> Result<FieldDescriptor, String> {
+    let mut field = FieldDescriptor::default();
+    for (number, value) in Reader::new(bytes) {
+        match (number, value?) {
+            (1, Value::Bytes(b)) => field.name = string(b)?,
+            (2, Value::Bytes(b)) => field.extendee = Some(string(b)?),
+            (3, Value::Varint(v)) => field.number = int32(v),
+            (4, Value::Varint(v)) => field.label = label(int32(v)),
+            (5, Value::Varint(v)) => field.r#type = field_type(int32(v)),
+            (6, Value::Bytes(b)) => field.type_name = Some(string(b)?),
+            (7, Value::Bytes(b)) => field.default_value = Some(string(b)?),
+            (9, Value::Varint(v)) => field.oneof_index = Some(int32(v)),
+            (10, Value::Bytes(b)) => field.json_name = Some(string(b)?),
+            (17, Value::Varint(v)) => field.proto3_optional = v != 0,
+            _ => {}
+        }
+    }
+    Ok(field)
+}
+
+fn enum_descriptor(bytes: &[u8]) -> Result<EnumDescriptor, String> {
+    let mut enumeration = EnumDescriptor::default();
+    for (field, value) in Reader::new(bytes) {
+        match (field, value?) {
+            (1, Value::Bytes(b)) => enumeration.name = string(b)?,
+            (2, Value::Bytes(b)) => enumeration.value.push(enum_value_descriptor(b)?),
+            (4, Value::Bytes(b)) => enumeration.reserved_range.push(range(b)?),
+            (5, Value::Bytes(b)) => enumeration.reserved_name.push(string(b)?),
+            _ => {}
+        }
+    }
+    Ok(enumeration)
+}
+
+fn enum_value_descriptor(bytes: &[u8]) -> Result<EnumValueDescriptor, String> {
+    let mut enum_value = EnumValueDescriptor::default();
+    for (field, value) in Reader::new(bytes) {
+        match (field, value?) {
+            (1, Value::Bytes(b)) => enum_value.name = string(b)?,
+            (2, Value::Varint(v)) => enum_value.number = int32(v),
+            _ => {}
+        }
+    }
+    Ok(enum_value)
+}
+
+fn service_descriptor(bytes: &[u8]) -> Result<ServiceDescriptor, String> {
+    let mut service = ServiceDescriptor::default();
+    for (field, value) in Reader::new(bytes) {
+        match (field, value?) {
+            (1, Value::Bytes(b)) => service.name = string(b)?,
+            (2, Value::Bytes(b)) => service.method.push(method_descriptor(b)?),
+            _ => {}
+        }
+    }
+    Ok(service)
+}
+
+fn method_descriptor(bytes: &[u8]) -> Result<MethodDescriptor, String> {
+    let mut method = MethodDescriptor::default();
+    for (field, value) in Reader::new(bytes) {
+        match (field, value?) {
+            (1, Value::Bytes(b)) => method.name = string(b)?,
+            (2, Value::Bytes(b)) => method.input_type = string(b)?,
+            (3, Value::Bytes(b)) => method.output_type = string(b)?,
+            (5, Value::Varint(v)) => method.client_streaming = v != 0,
+            (6, Value::Varint(v)) => method.server_streaming = v != 0,
+            _ => {}
+        }
+    }
+    Ok(method)
+}
+
+/// Decodes a reserved or extension range message: `start` and `end`.
+fn range(bytes: &[u8]) -> Result<(i32, i32), String> {
+    let (mut start, mut end) = (0, 0);
+    for (field, value) in Reader::new(bytes) {
+        match (field, value?) {
+            (1, Value::Varint(v)) => start = int32(v),
+            (2, Value::Varint(v)) => end = int32(v),
+            _ => {}
+        }
+    }
+    Ok((start, end))
+}
+
+fn string(bytes: &[u8]) -> Result<String, String> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| String::from("invalid UTF-8 in a string"))
+}
+
+/// Truncates a varint to an `int32`, undoing the sign extension of negative
+/// numbers.
+#[allow(clippy::cast_possible_truncation)]
+fn int32(value: u64) -> i32 {
+    (value as u32).cast_signed()
+}
+
+fn label(number: i32) -> Option<FieldLabel> {
+    Some(match number {
+        1 => FieldLabel::Optional,
+        2 => FieldLabel::Required,
+        3 => FieldLabel::Repeated,
+        _ => return None,
+    })
+}
+
+fn field_type(number: i32) -> Option<FieldType> {
+    Some(match number {
+        1 => FieldType::Double,
+        2 => FieldType::Float,
+        3 => FieldType::Int64,
+        4 => FieldType::Uint64,
+        5 => FieldType::Int32,
+        6 => FieldType::Fixed64,
+        7 => FieldType::Fixed32,
+        8 => FieldType::Bool,
+        9 => FieldType::String,
+        11 => FieldType::Message,
+        12 => FieldType::Bytes,
+        13 => FieldType::Uint32,
+        14 => FieldType::Enum,
+        15 => FieldType::Sfixed32,
+        16 => FieldType::Sfixed64,
+        17 => FieldType::Sint32,
+        18 => FieldType::Sint64,
+        _ => return None,
+    })
+}
+
+/// The value of a field, for the wire types descriptors use.
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    /// A fixed-size value, which descriptors never contain.
+    Fixed,
+}
+
+/// Iterates over the fields of an encoded message.
+struct Reader<'a> {
+    buf: &'a [u8],
+    failed: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf, failed: false }
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self
+                .buf
+                .split_first()
+                .ok_or_else(|| String::from("truncated varint"))?;
+            self.buf = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(String::from("varint too long"))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.buf.len() < len {
+            return Err(String::from("truncated message"));
+        }
+        let (value, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(value)
+    }
+
+    fn field(&mut self) -> Result<(u64, Value<'a>), String> {
+        let key = self.varint()?;
+        let value = match key & 0x7 {
+            0 => Value::Varint(self.varint()?),
+            1 => {
+                self.take(8)?;
+                Value::Fixed
+            }
+            2 => {
+                let len = usize::try_from(self.varint()?)
+                    .map_err(|_| String::from("length too large"))?;
+                Value::Bytes(self.take(len)?)
+            }
+            5 => {
+                self.take(4)?;
+                Value::Fixed
+            }
+            wire_type => return Err(format!("unsupported wire type {wire_type}")),
+        };
+        Ok((key >> 3, value))
+    }
+}
+
+impl<'a> Iterator for Reader<'a> {
+    type Item = (u64, Result<Value<'a>, String>);
+
+    /// Yields the number and value of the next field. After an error, which
+    /// is yielded with field number 0, iteration stops.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() || self.failed {
+            return None;
+        }
+        Some(match self.field() {
+            Ok((field, value)) => (field, Ok(value)),
+            Err(e) => {
+                self.failed = true;
+                (0, Err(e))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::decode_file_descriptor_set;
+    use crate::descriptor::encode_file_descriptor_set;
+    use crate::state::ProtoLanguageState;
+
+    #[test]
+    fn test_decode_file_descriptor_set() {
+        let dir = std::env::current_dir()
+            .unwrap()
+            .join("src/state/input/semantic");
+        let uri = async_lsp::lsp_types::Url::from_file_path(dir.join("valid.proto")).unwrap();
+        let content = std::fs::read_to_string(dir.join("valid.proto")).unwrap();
+        let files = ProtoLanguageState::new()
+            .descriptor_set(&uri, &content, &[dir])
+            .unwrap();
+
+        let bytes = encode_file_descriptor_set(&files);
+        let decoded = decode_file_descriptor_set(&bytes).unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&files).unwrap()
+        );
+        let names: Vec<&str> = decoded.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["common.proto", "hidden.proto", "via.proto", "valid.proto"]
+        );
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        assert!(decode_file_descriptor_set(&[0x0a, 0x05, 0x0a]).is_err());
+        assert!(decode_file_descriptor_set(&[0x0a, 0x02, 0x0a, 0xff]).is_err());
+        assert!(decode_file_descriptor_set(&[]).unwrap().is_empty());
+    }
+}
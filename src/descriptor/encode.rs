@@ -0,0 +1,433 @@
+//! Serializes descriptors in the protobuf wire format, as the
+//! `google.protobuf.FileDescriptorSet` protoc writes with
+//! `--descriptor_set_out`.
+//!
+//! Standard options are encoded in their `*Options` message. Custom options
+//! are left out: their types live in the files that define them, not in
+//! `descriptor.proto`.
+
+use crate::model::OptionEntry;
+
+use super::compiler::unquote;
+use super::{
+    EnumDescriptor, EnumValueDescriptor, FieldDescriptor, FileDescriptor, MessageDescriptor,
+    MethodDescriptor, ServiceDescriptor,
+};
+
+/// How the value of a standard option is encoded.
+enum OptionType {
+    String,
+    Bool,
+    /// An enum, with the number of each of its values.
+    Enum(&'static [(&'static str, i32)]),
+}
+
+/// Standard options of each `*Options` message, with their field numbers.
+type KnownOptions = &'static [(&'static str, u32, OptionType)];
+
+const FILE_OPTIONS: KnownOptions = &[
+    ("java_package", 1, OptionType::String),
+    ("java_outer_classname", 8, OptionType::String),
+    (
+        "optimize_for",
+        9,
+        OptionType::Enum(&[("SPEED", 1), ("CODE_SIZE", 2), ("LITE_RUNTIME", 3)]),
+    ),
+    ("java_multiple_files", 10, OptionType::Bool),
+    ("go_package", 11, OptionType::String),
+    ("cc_generic_services", 16, OptionType::Bool),
+    ("java_generic_services", 17, OptionType::Bool),
+    ("py_generic_services", 18, OptionType::Bool),
+    ("java_generate_equals_and_hash", 20, OptionType::Bool),
+    ("deprecated", 23, OptionType::Bool),
+    ("java_string_check_utf8", 27, OptionType::Bool),
+    ("cc_enable_arenas", 31, OptionType::Bool),
+    ("objc_class_prefix", 36, OptionType::String),
+    ("csharp_namespace", 37, OptionType::String),
+    ("swift_prefix", 39, OptionType::String),
+    ("php_class_prefix", 40, OptionType::String),
+    ("php_namespace", 41, OptionType::String),
+    ("php_metadata_namespace", 44, OptionType::String),
+    ("ruby_package", 45, OptionType::String),
+];
+const MESSAGE_OPTIONS: KnownOptions = &[
+    ("message_set_wire_format", 1, OptionType::Bool),
+    ("no_standard_descriptor_accessor", 2, OptionType::Bool),
+    ("deprecated", 3, OptionType::Bool),
+];
+const FIELD_OPTIONS: KnownOptions = &[
+    (
+        "ctype",
+        1,
+        OptionType::Enum(&[("STRING", 0), ("CORD", 1), ("STRING_PIECE", 2)]),
+    ),
+    ("packed", 2, OptionType::Bool),
+    ("deprecated", 3, OptionType::Bool),
+    ("lazy", 5, OptionType::Bool),
+    (
+        "jstype",
+        6,
+        OptionType::Enum(&[("JS_NORMAL", 0), ("JS_STRING", 1), ("JS_NUMBER", 2)]),
+    ),
+    ("weak", 10, OptionType::Bool),
+    ("unverified_lazy", 15, OptionType::Bool),
+    ("debug_redact", 16, OptionType::Bool),
+];
+const ENUM_OPTIONS: KnownOptions = &[
+    ("allow_alias", 2, OptionType::Bool),
+    ("deprecated", 3, OptionType::Bool),
+];
+const ENUM_VALUE_OPTIONS: KnownOptions = &[
+    ("deprecated", 1, OptionType::Bool),
+    ("debug_redact", 3, OptionType::Bool),
+];
+const SERVICE_OPTIONS: KnownOptions = &[("deprecated", 33, OptionType::Bool)];
+const METHOD_OPTIONS: KnownOptions = &[
+    ("deprecated", 33, OptionType::Bool),
+    (
+        "idempotency_level",
+        34,
+        OptionType::Enum(&[
+            ("IDEMPOTENCY_UNKNOWN", 0),
+            ("NO_SIDE_EFFECTS", 1),
+            ("IDEMPOTENT", 2),
+        ]),
+    ),
+];
+
+/// `MessageOptions.map_entry`.
+const MAP_ENTRY_OPTION: u32 = 7;
+
+/// Encodes `files` as a `google.protobuf.FileDescriptorSet`.
+pub fn encode_file_descriptor_set(files: &[FileDescriptor]) -> Vec<u8> {
+    let mut writer = Writer::default();
+    for file in files {
+        writer.message(1, |w| file_descriptor(w, file));
+    }
+    writer.buf
+}
+
+fn file_descriptor(w: &mut Writer, file: &FileDescriptor) {
+    w.string(1, &file.name);
+    if !file.package.is_empty() {
+        w.string(2, &file.package);
+    }
+    for dependency in &file.dependency {
+        w.string(3, dependency);
+    }
+    for message in &file.message_type {
+        w.message(4, |w| message_descriptor(w, message));
+    }
+    for enumeration in &file.enum_type {
+        w.message(5, |w| enum_descriptor(w, enumeration));
+    }
+    for service in &file.service {
+        w.message(6, |w| service_descriptor(w, service));
+    }
+    for extension in &file.extension {
+        w.message(7, |w| field_descriptor(w, extension));
+    }
+    options(w, 8, &file.options, FILE_OPTIONS, false);
+    for index in &file.public_dependency {
+        w.int32(10, *index);
+    }
+    for index in &file.weak_dependency {
+        w.int32(11, *index);
+    }
+    // protoc leaves the syntax of proto2 files unset.
+    if file.syntax != "proto2" {
+        w.string(12, &file.syntax);
+    }
+    if let Some(edition) = file.edition.as_deref().and_then(edition_number) {
+        w.int32(14, edition);
+    }
+}
+
+fn message_descriptor(w: &mut Writer, message: &MessageDescriptor) {
+    w.string(1, &message.name);
+    for field in &message.field {
+        w.message(2, |w| field_descriptor(w, field));
+    }
+    for nested in &message.nested_type {
+        w.message(3, |w| message_descriptor(w, nested));
+    }
+    for enumeration in &message.enum_type {
+        w.message(4, |w| enum_descriptor(w, enumeration));
+    }
+    for (start, end) in &message.extension_range {
+        w.message(5, |w| {
+            w.int32(1, *start);
+            w.int32(2, *end);
+        });
+    }
+    for extension in &message.extension {
+        w.message(6, |w| field_descriptor(w, extension));
+    }
+    options(w, 7, &message.options, MESSAGE_OPTIONS, message.map_entry);
+    for oneof in &message.oneof_decl {
+        w.message(8, |w| w.string(1, oneof));
+    }
+    for (start, end) in &message.reserved_range {
+        w.message(9, |w| {
+            w.int32(1, *start);
+            w.int32(2, *end);
+        });
+    }
+    for name in &message.reserved_name {
+        w.string(10, name);
+    }
+}
+
+fn field_descriptor(w: &mut Writer, field: &FieldDescriptor) {
+    w.string(1, &field.name);
+    if let Some(extendee) = &field.extendee {
+        w.string(2, extendee);
+    }
+    w.int32(3, field.number);
+    if let Some(label) = field.label {
+        w.int32(4, label as i32);
+    }
+    if let Some(r#type) = field.r#type {
+        w.int32(5, r#type as i32);
+    }
+    if let Some(type_name) = &field.type_name {
+        w.string(6, type_name);
+    }
+    if let Some(default_value) = &field.default_value {
+        w.string(7, default_value);
+    }
+    options(w, 8, &field.options, FIELD_OPTIONS, false);
+    if let Some(index) = field.oneof_index {
+        w.int32(9, index);
+    }
+    if let Some(json_name) = &field.json_name {
+        w.string(10, json_name);
+    }
+    if field.proto3_optional {
+        w.bool(17, true);
+    }
+}
+
+fn enum_descriptor(w: &mut Writer, enumeration: &EnumDescriptor) {
+    w.string(1, &enumeration.name);
+    for value in &enumeration.value {
+        w.message(2, |w| enum_value_descriptor(w, value));
+    }
+    options(w, 3, &enumeration.options, ENUM_OPTIONS, false);
+    for (start, end) in &enumeration.reserved_range {
+        w.message(4, |w| {
+            w.int32(1, *start);
+            w.int32(2, *end);
+        });
+    }
+    for name in &enumeration.reserved_name {
+        w.string(5, name);
+    }
+}
+
+fn enum_value_descriptor(w: &mut Writer, value: &EnumValueDescriptor) {
+    w.string(1, &value.name);
+    w.int32(2, value.number);
+    options(w, 3, &value.options, ENUM_VALUE_OPTIONS, false);
+}
+
+fn service_descriptor(w: &mut Writer, service: &ServiceDescriptor) {
+    w.string(1, &service.name);
+    for method in &service.method {
+        w.message(2, |w| method_descriptor(w, method));
+    }
+    options(w, 3, &service.options, SERVICE_OPTIONS, false);
+}
+
+fn method_descriptor(w: &mut Writer, method: &MethodDescriptor) {
+    w.string(1, &method.name);
+    w.string(2, &method.input_type);
+    w.string(3, &method.output_type);
+    options(w, 4, &method.options, METHOD_OPTIONS, false);
+    if method.client_streaming {
+        w.bool(5, true);
+    }
+    if method.server_streaming {
+        w.bool(6, true);
+    }
+}
+
+/// Writes the standard options among `options` as the `*Options` message in
+/// `field`, if any is set. `map_entry` sets `MessageOptions.map_entry`.
+fn options(
+    w: &mut Writer,
+    field: u32,
+    options: &[OptionEntry],
+    known: KnownOptions,
+    map_entry: bool,
+) {
+    let mut inner = Writer::default();
+    for option in options {
+        let Some((_, number, r#type)) = known.iter().find(|(name, ..)| *name == option.name) else {
+            continue;
+        };
+        let value = option.value.trim();
+        match r#type {
+            OptionType::String => inner.string(*number, &unquote(value)),
+            OptionType::Bool => inner.bool(*number, value == "true"),
+            OptionType::Enum(values) => {
+                if let Some((_, value)) = values.iter().find(|(name, _)| *name == value) {
+                    inner.int32(*number, *value);
+                }
+            }
+        }
+    }
+    if map_entry {
+        inner.bool(MAP_ENTRY_OPTION, true);
+    }
+    if !inner.buf.is_empty() {
+        w.bytes(field, &inner.buf);
+    }
+}
+
+/// Returns the `google.protobuf.Edition` number of an edition year.
+fn edition_number(edition: &str) -> Option<i32> {
+    match edition.trim_matches('"') {
+        "2023" => Some(1000),
+        "2024" => Some(1001),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    fn varint(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.buf.push(byte);
+                return;
+            }
+            self.buf.push(byte | 0x80);
+        }
+    }
+
+    /// Writes the key of `field`, with the varint (0) or length-delimited (2)
+    /// wire type.
+    fn key(&mut self, field: u32, wire_type: u8) {
+        self.varint(u64::from(field) << 3 | u64::from(wire_type));
+    }
+
+    fn int32(&mut self, field: u32, value: i32) {
+        self.key(field, 0);
+        // Negative numbers are sign-extended to ten bytes.
+        self.varint(i64::from(value).cast_unsigned());
+    }
+
+    fn bool(&mut self, field: u32, value: bool) {
+        self.key(field, 0);
+        self.varint(u64::from(value));
+    }
+
+    fn bytes(&mut self, field: u32, value: &[u8]) {
+        self.key(field, 2);
+        self.varint(value.len() as u64);
+        self.buf.extend_from_slice(value);
+    }
+
+    fn string(&mut self, field: u32, value: &str) {
+        self.bytes(field, value.as_bytes());
+    }
+
+    fn message(&mut self, field: u32, write: impl FnOnce(&mut Writer)) {
+        let mut inner = Writer::default();
+        write(&mut inner);
+        self.bytes(field, &inner.buf);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_lsp::lsp_types::Range;
+
+    use super::*;
+    use crate::descriptor::{FieldLabel, FieldType};
+
+    #[test]
+    fn test_encode_file_descriptor_set() {
+        let file = FileDescriptor {
+            name: "a.proto".to_string(),
+            package: "p".to_string(),
+            message_type: vec![MessageDescriptor {
+                name: "M".to_string(),
+                field: vec![FieldDescriptor {
+                    name: "f".to_string(),
+                    number: 1,
+                    label: Some(FieldLabel::Optional),
+                    r#type: Some(FieldType::Int32),
+                    json_name: Some("f".to_string()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            syntax: "proto3".to_string(),
+            ..Default::default()
+        };
+
+        let field = [
+            0x0a, 0x01, b'f', // name
+            0x18, 0x01, // number
+            0x20, 0x01, // label
+            0x28, 0x05, // type
+            0x52, 0x01, b'f', // json_name
+        ];
+        let mut message = vec![0x0a, 0x01, b'M', 0x12, u8::try_from(field.len()).unwrap()];
+        message.extend(field);
+        let mut expected_file = vec![
+            0x0a,
+            0x07,
+            b'a',
+            b'.',
+            b'p',
+            b'r',
+            b'o',
+            b't',
+            b'o', // name
+            0x12,
+            0x01,
+            b'p', // package
+            0x22,
+            u8::try_from(message.len()).unwrap(),
+        ];
+        expected_file.extend(message);
+        expected_file.extend([0x62, 0x06]);
+        expected_file.extend(b"proto3");
+        let mut expected = vec![0x0a, u8::try_from(expected_file.len()).unwrap()];
+        expected.extend(expected_file);
+
+        assert_eq!(encode_file_descriptor_set(&[file]), expected);
+    }
+
+    #[test]
+    fn test_encode_negative_and_options() {
+        let value = EnumValueDescriptor {
+            name: "N".to_string(),
+            number: -1,
+            options: vec![OptionEntry {
+                name: "deprecated".to_string(),
+                value: "true".to_string(),
+                range: Range::default(),
+                value_range: Range::default(),
+            }],
+        };
+        let mut writer = Writer::default();
+        enum_value_descriptor(&mut writer, &value);
+
+        let mut expected = vec![0x0a, 0x01, b'N', 0x10];
+        expected.extend([0xff; 9]);
+        expected.push(0x01);
+        // EnumValueOptions { deprecated: true }
+        expected.extend([0x1a, 0x02, 0x08, 0x01]);
+        assert_eq!(writer.buf, expected);
+    }
+}
@@ -0,0 +1,188 @@
+//! Descriptors built by protols itself, mirroring the `*DescriptorProto`
+//! messages of `google/protobuf/descriptor.proto`.
+//!
+//! [`compile`] turns a parsed document into a [`FileDescriptor`], resolving
+//! every name against the files it imports through a [`SymbolTable`], and
+//! reports the errors protoc would along the way. This gives semantic
+//! diagnostics without any external binary.
+
+pub use breaking::{Breakage, BreakingChange, breaking_changes};
+pub use compiler::compile;
+pub use decode::decode_file_descriptor_set;
+pub use encode::encode_file_descriptor_set;
+pub use symbols::{Origin, SymbolTable};
+pub use tree::{IMPLEMENTATION_RESERVED, declared_extendees, declared_reserved_names};
+
+use serde::Serialize;
+
+use crate::model::OptionEntry;
+
+mod breaking;
+mod compiler;
+mod decode;
+mod encode;
+mod symbols;
+mod tree;
+
+/// Equivalent of `google.protobuf.FileDescriptorProto`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FileDescriptor {
+    /// Path of the file relative to the include path it was found in.
+    pub name: String,
+    pub package: String,
+    /// Paths of the imported files, in declaration order.
+    pub dependency: Vec<String>,
+    /// Indexes into `dependency` of the `import public` statements.
+    pub public_dependency: Vec<i32>,
+    /// Indexes into `dependency` of the `import weak` statements.
+    pub weak_dependency: Vec<i32>,
+    pub message_type: Vec<MessageDescriptor>,
+    pub enum_type: Vec<EnumDescriptor>,
+    pub service: Vec<ServiceDescriptor>,
+    pub extension: Vec<FieldDescriptor>,
+    #[serde(skip)]
+    pub options: Vec<OptionEntry>,
+    /// `proto2`, `proto3` or `editions`.
+    pub syntax: String,
+    /// The edition year, for files using editions.
+    pub edition: Option<String>,
+}
+
+/// Equivalent of `google.protobuf.DescriptorProto`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MessageDescriptor {
+    pub name: String,
+    pub field: Vec<FieldDescriptor>,
+    pub extension: Vec<FieldDescriptor>,
+    pub nested_type: Vec<MessageDescriptor>,
+    pub enum_type: Vec<EnumDescriptor>,
+    /// Extension number ranges, end exclusive.
+    pub extension_range: Vec<(i32, i32)>,
+    pub oneof_decl: Vec<String>,
+    #[serde(skip)]
+    pub options: Vec<OptionEntry>,
+    /// Reserved field number ranges, end exclusive.
+    pub reserved_range: Vec<(i32, i32)>,
+    pub reserved_name: Vec<String>,
+    /// Whether the message is the entry type synthesized for a `map` field.
+    pub map_entry: bool,
+}
+
+/// Equivalent of `google.protobuf.FieldDescriptorProto.Label`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum FieldLabel {
+    Optional = 1,
+    Required = 2,
+    Repeated = 3,
+}
+
+/// Equivalent of `google.protobuf.FieldDescriptorProto.Type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum FieldType {
+    Double = 1,
+    Float = 2,
+    Int64 = 3,
+    Uint64 = 4,
+    Int32 = 5,
+    Fixed64 = 6,
+    Fixed32 = 7,
+    Bool = 8,
+    String = 9,
+    Message = 11,
+    Bytes = 12,
+    Uint32 = 13,
+    Enum = 14,
+    Sfixed32 = 15,
+    Sfixed64 = 16,
+    Sint32 = 17,
+    Sint64 = 18,
+}
+
+impl FieldType {
+    /// Returns the type of a scalar type name, `None` for message and enum
+    /// types.
+    pub fn from_scalar(name: &str) -> Option<Self> {
+        Some(match name {
+            "double" => Self::Double,
+            "float" => Self::Float,
+            "int64" => Self::Int64,
+            "uint64" => Self::Uint64,
+            "int32" => Self::Int32,
+            "fixed64" => Self::Fixed64,
+            "fixed32" => Self::Fixed32,
+            "bool" => Self::Bool,
+            "string" => Self::String,
+            "bytes" => Self::Bytes,
+            "uint32" => Self::Uint32,
+            "sfixed32" => Self::Sfixed32,
+            "sfixed64" => Self::Sfixed64,
+            "sint32" => Self::Sint32,
+            "sint64" => Self::Sint64,
+            _ => return None,
+        })
+    }
+}
+
+/// Equivalent of `google.protobuf.FieldDescriptorProto`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FieldDescriptor {
+    pub name: String,
+    pub number: i32,
+    pub label: Option<FieldLabel>,
+    #[serde(rename = "type")]
+    pub r#type: Option<FieldType>,
+    /// Fully qualified name of the message or enum type, with a leading dot.
+    pub type_name: Option<String>,
+    /// Fully qualified name of the extended message, with a leading dot.
+    pub extendee: Option<String>,
+    pub default_value: Option<String>,
+    pub oneof_index: Option<i32>,
+    pub json_name: Option<String>,
+    #[serde(skip)]
+    pub options: Vec<OptionEntry>,
+    pub proto3_optional: bool,
+}
+
+/// Equivalent of `google.protobuf.EnumDescriptorProto`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EnumDescriptor {
+    pub name: String,
+    pub value: Vec<EnumValueDescriptor>,
+    #[serde(skip)]
+    pub options: Vec<OptionEntry>,
+    /// Reserved value ranges, end inclusive.
+    pub reserved_range: Vec<(i32, i32)>,
+    pub reserved_name: Vec<String>,
+}
+
+/// Equivalent of `google.protobuf.EnumValueDescriptorProto`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EnumValueDescriptor {
+    pub name: String,
+    pub number: i32,
+    #[serde(skip)]
+    pub options: Vec<OptionEntry>,
+}
+
+/// Equivalent of `google.protobuf.ServiceDescriptorProto`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ServiceDescriptor {
+    pub name: String,
+    pub method: Vec<MethodDescriptor>,
+    #[serde(skip)]
+    pub options: Vec<OptionEntry>,
+}
+
+/// Equivalent of `google.protobuf.MethodDescriptorProto`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MethodDescriptor {
+    pub name: String,
+    /// Fully qualified name of the request message, with a leading dot.
+    pub input_type: String,
+    /// Fully qualified name of the response message, with a leading dot.
+    pub output_type: String,
+    #[serde(skip)]
+    pub options: Vec<OptionEntry>,
+    pub client_streaming: bool,
+    pub server_streaming: bool,
+}
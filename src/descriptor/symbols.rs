@@ -0,0 +1,315 @@
+//! Symbols defined by a set of files, looked up with the scoping rules of
+//! protoc.
+
+use std::collections::HashMap;
+
+use async_lsp::lsp_types::{Range, Url};
+
+use crate::document::ProtoDocument;
+use crate::model::ElementKind;
+
+use super::tree::{self, MAX_FIELD_NUMBER, NumberRange};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Package,
+    Message,
+    Enum,
+    EnumValue,
+    Field,
+    Oneof,
+    Service,
+    Method,
+}
+
+impl SymbolKind {
+    /// Whether the symbol may contain other symbols.
+    fn is_aggregate(self) -> bool {
+        matches!(
+            self,
+            Self::Package | Self::Message | Self::Enum | Self::Service
+        )
+    }
+
+    pub fn is_type(self) -> bool {
+        matches!(self, Self::Message | Self::Enum)
+    }
+}
+
+/// How a file relates to the file being compiled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Origin {
+    /// The file being compiled.
+    Current,
+    /// Imported directly, or re-exported by an `import public` of such a
+    /// file: its symbols can be used.
+    Imported,
+    /// Imported indirectly: loaded, but its symbols cannot be used.
+    Transitive,
+    /// Any other indexed file, only used to suggest a missing import.
+    Workspace,
+}
+
+impl Origin {
+    fn is_visible(self) -> bool {
+        self <= Self::Imported
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    /// The file defining the symbol.
+    pub uri: Url,
+    /// The range of the name of the definition, in its file.
+    pub range: Range,
+    /// For messages, the numbers their `extensions` statements declare.
+    pub(super) extension_ranges: Vec<NumberRange>,
+}
+
+/// The outcome of looking a name up.
+pub enum Lookup<'a> {
+    Found {
+        fqn: String,
+        symbol: &'a Symbol,
+    },
+    /// The name is only defined in a file that is not imported.
+    NotImported {
+        file: String,
+    },
+    /// The first component of the name resolved in a scope that does not
+    /// define the rest of it.
+    Shadowed {
+        fqn: String,
+    },
+    NotFound,
+}
+
+/// The symbols of every indexed file, updated one file at a time as files
+/// change so that compiling a file does not walk the whole workspace.
+#[derive(Default)]
+pub struct SymbolTable {
+    symbols: HashMap<String, Vec<Symbol>>,
+    /// The names defined by each file.
+    files: HashMap<Url, Vec<String>>,
+}
+
+impl SymbolTable {
+    /// Replaces the definitions of `document` with the ones it has now.
+    pub fn update_file(&mut self, document: &ProtoDocument, content: &str) {
+        self.remove_file(&document.uri);
+
+        let mut names = Vec::new();
+        let mut add = |fqn: String, kind, range, extension_ranges| {
+            self.symbols.entry(fqn.clone()).or_default().push(Symbol {
+                kind,
+                uri: document.uri.clone(),
+                range,
+                extension_ranges,
+            });
+            names.push(fqn);
+        };
+
+        if !document.package.is_empty() {
+            let mut package = String::new();
+            for part in document.package.split('.') {
+                if !package.is_empty() {
+                    package.push('.');
+                }
+                package.push_str(part);
+                add(
+                    package.clone(),
+                    SymbolKind::Package,
+                    Range::default(),
+                    vec![],
+                );
+            }
+        }
+
+        for element in &document.elements {
+            let Some(fqn) = element.kind.fqn() else {
+                continue;
+            };
+            let range = element.meta.selection_range;
+            let (fqn, kind) = match &element.kind {
+                ElementKind::Message { .. } => (fqn.to_string(), SymbolKind::Message),
+                ElementKind::Enum { .. } => (fqn.to_string(), SymbolKind::Enum),
+                // Enum values are siblings of their enum, as in C++.
+                ElementKind::EnumValue { .. } => (enum_value_fqn(fqn), SymbolKind::EnumValue),
+                ElementKind::Field { .. }
+                | ElementKind::MapField { .. }
+                | ElementKind::OneofField { .. } => (fqn.to_string(), SymbolKind::Field),
+                ElementKind::Oneof { .. } => (fqn.to_string(), SymbolKind::Oneof),
+                ElementKind::Service { .. } => (fqn.to_string(), SymbolKind::Service),
+                ElementKind::Rpc { .. } => (fqn.to_string(), SymbolKind::Method),
+                ElementKind::Import { .. } => continue,
+            };
+            let extension_ranges = if kind == SymbolKind::Message {
+                tree::node_at(&document.tree, element.meta.range, &["message"])
+                    .and_then(tree::body)
+                    .map(|body| tree::number_ranges(body, content, "extensions", MAX_FIELD_NUMBER))
+                    .unwrap_or_default()
+            } else {
+                vec![]
+            };
+            add(fqn, kind, range, extension_ranges);
+        }
+
+        self.files.insert(document.uri.clone(), names);
+    }
+
+    /// Drops the definitions of the file at `uri`.
+    pub fn remove_file(&mut self, uri: &Url) {
+        for name in self.files.remove(uri).unwrap_or_default() {
+            if let Some(symbols) = self.symbols.get_mut(&name) {
+                symbols.retain(|s| &s.uri != uri);
+                if symbols.is_empty() {
+                    self.symbols.remove(&name);
+                }
+            }
+        }
+    }
+
+    /// Sees the table from a file being compiled. `origins` tells how the
+    /// files it loads relate to it, any other file being
+    /// [`Origin::Workspace`], and `file_name` names a file in messages.
+    pub fn view<'a>(
+        &'a self,
+        origins: HashMap<Url, Origin>,
+        file_name: &'a dyn Fn(&Url) -> String,
+    ) -> SymbolView<'a> {
+        SymbolView {
+            table: self,
+            origins,
+            file_name,
+        }
+    }
+}
+
+/// The symbols a document can see, and those of the files it could import.
+pub struct SymbolView<'a> {
+    table: &'a SymbolTable,
+    origins: HashMap<Url, Origin>,
+    file_name: &'a dyn Fn(&Url) -> String,
+}
+
+impl SymbolView<'_> {
+    pub fn origin(&self, symbol: &Symbol) -> Origin {
+        self.origins
+            .get(&symbol.uri)
+            .copied()
+            .unwrap_or(Origin::Workspace)
+    }
+
+    /// The name of the file defining `symbol`, relative to its include path.
+    pub fn file(&self, symbol: &Symbol) -> String {
+        (self.file_name)(&symbol.uri)
+    }
+
+    /// Returns every definition of `fqn` in the current file and the files it
+    /// loads, the closest first.
+    pub fn definitions(&self, fqn: &str) -> impl Iterator<Item = &Symbol> {
+        self.sorted(fqn)
+            .into_iter()
+            .filter(move |s| self.origin(s) < Origin::Workspace)
+    }
+
+    /// Looks `name` up from `scope` the way protoc does: each enclosing scope
+    /// is tried from the innermost one, and once the first component of a
+    /// qualified name is found the rest must be defined inside it. With
+    /// `types_only`, symbols that are not messages or enums are skipped.
+    pub fn lookup(&self, scope: &str, name: &str, types_only: bool) -> Lookup<'_> {
+        if let Some(fqn) = name.strip_prefix('.') {
+            return match self.find(fqn) {
+                Ok(symbol) => Lookup::Found {
+                    fqn: fqn.to_string(),
+                    symbol,
+                },
+                Err(hidden) => self.not_found(hidden),
+            };
+        }
+
+        let first = name.split('.').next().unwrap_or(name);
+        let mut scope = scope.to_string();
+        let mut hidden = None;
+        loop {
+            match self.find(&join(&scope, first)) {
+                Ok(symbol) if first.len() < name.len() => {
+                    if symbol.kind.is_aggregate() {
+                        let fqn = join(&scope, name);
+                        return match self.find(&fqn) {
+                            Ok(symbol) => Lookup::Found { fqn, symbol },
+                            Err(Some(symbol)) => self.not_found(Some(symbol)),
+                            // Resolved from the root, nothing shadows the name.
+                            Err(None) if scope.is_empty() => Lookup::NotFound,
+                            Err(None) => Lookup::Shadowed { fqn },
+                        };
+                    }
+                }
+                Ok(symbol) if !types_only || symbol.kind.is_type() => {
+                    return Lookup::Found {
+                        fqn: join(&scope, name),
+                        symbol,
+                    };
+                }
+                Ok(_) => {}
+                Err(symbol) => hidden = hidden.or(symbol),
+            }
+
+            if scope.is_empty() {
+                return self.not_found(hidden);
+            }
+            scope = scope
+                .rsplit_once('.')
+                .map(|(parent, _)| parent.to_string())
+                .unwrap_or_default();
+        }
+    }
+
+    /// Finds the visible definition of `fqn`, or else the first definition in
+    /// a file that is not imported.
+    fn find(&self, fqn: &str) -> Result<&Symbol, Option<&Symbol>> {
+        match self.sorted(fqn).into_iter().next() {
+            Some(symbol) if self.origin(symbol).is_visible() => Ok(symbol),
+            hidden => Err(hidden),
+        }
+    }
+
+    fn not_found(&self, hidden: Option<&Symbol>) -> Lookup<'_> {
+        match hidden {
+            Some(symbol) => Lookup::NotImported {
+                file: self.file(symbol),
+            },
+            None => Lookup::NotFound,
+        }
+    }
+
+    /// The definitions of `fqn`, the closest to the current file first.
+    fn sorted(&self, fqn: &str) -> Vec<&Symbol> {
+        let mut symbols: Vec<&Symbol> = self.table.symbols.get(fqn).into_iter().flatten().collect();
+        symbols.sort_by(|a, b| {
+            (self.origin(a), a.uri.as_str()).cmp(&(self.origin(b), b.uri.as_str()))
+        });
+        symbols
+    }
+}
+
+fn join(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{scope}.{name}")
+    }
+}
+
+/// Returns the name under which an enum value is defined: in the scope of its
+/// enum rather than inside it.
+pub(super) fn enum_value_fqn(fqn: &str) -> String {
+    match fqn.rsplit_once('.') {
+        Some((enum_fqn, name)) => match enum_fqn.rsplit_once('.') {
+            Some((scope, _)) => format!("{scope}.{name}"),
+            None => name.to_string(),
+        },
+        None => fqn.to_string(),
+    }
+}
@@ -0,0 +1,201 @@
+//! Facts the metamodel does not keep, read back from the syntax tree: exact
+//! numbers, reserved and extension ranges, reserved names, extended types and
+//! import modifiers.
+
+use std::ops::RangeInclusive;
+
+use async_lsp::lsp_types::Range;
+use tree_sitter::{Node, Point, Tree};
+
+use crate::utils::{parse_int_lit, to_lsp_range};
+
+/// Highest field number.
+pub(super) const MAX_FIELD_NUMBER: i64 = 536_870_911;
+
+/// Field numbers reserved for the protobuf implementation.
+pub const IMPLEMENTATION_RESERVED: RangeInclusive<i64> = 19000..=19999;
+
+/// A range of numbers declared by `reserved` or `extensions`, end inclusive.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct NumberRange {
+    pub start: i64,
+    pub end: i64,
+    pub range: Range,
+}
+
+impl NumberRange {
+    pub fn contains(&self, number: i64) -> bool {
+        (self.start..=self.end).contains(&number)
+    }
+}
+
+/// Finds the node of one of `kinds` spanning exactly `range`.
+pub(super) fn node_at<'t>(tree: &'t Tree, range: Range, kinds: &[&str]) -> Option<Node<'t>> {
+    let point = |p: async_lsp::lsp_types::Position| Point {
+        row: p.line as usize,
+        column: p.character as usize,
+    };
+    let mut node = tree
+        .root_node()
+        .descendant_for_point_range(point(range.start), point(range.end));
+    while let Some(n) = node {
+        if kinds.contains(&n.kind()) && to_lsp_range(n) == range {
+            return Some(n);
+        }
+        node = n.parent();
+    }
+    None
+}
+
+/// Returns the body of a `message`, `enum` or `extend` node.
+pub(super) fn body(node: Node) -> Option<Node> {
+    node.children(&mut node.walk())
+        .find(|c| matches!(c.kind(), "message_body" | "enum_body"))
+}
+
+/// Reads the number of a field or enum value node and its range.
+pub(super) fn number(node: Node, content: &str) -> Option<(i64, Range)> {
+    let negative = node.children(&mut node.walk()).any(|c| c.kind() == "-");
+    let literal = node
+        .children(&mut node.walk())
+        .find(|c| matches!(c.kind(), "field_number" | "int_lit"))?;
+    let value = parse_int_lit(&content[literal.byte_range()])?;
+    let mut range = to_lsp_range(literal);
+    if negative && let Some(minus) = literal.prev_sibling() {
+        range.start = to_lsp_range(minus).start;
+    }
+    Some((if negative { -value } else { value }, range))
+}
+
+/// Collects the number ranges of the `kind` statements (`reserved` or
+/// `extensions`) of `body`, `max` standing for `max_value`.
+pub(super) fn number_ranges(
+    body: Node,
+    content: &str,
+    kind: &str,
+    max_value: i64,
+) -> Vec<NumberRange> {
+    let mut out = Vec::new();
+    for statement in body.children(&mut body.walk()).filter(|n| n.kind() == kind) {
+        for ranges in statement
+            .children(&mut statement.walk())
+            .filter(|n| n.kind() == "ranges")
+        {
+            for range in ranges
+                .children(&mut ranges.walk())
+                .filter(|n| n.kind() == "range")
+            {
+                let bounds: Vec<Node> = range
+                    .children(&mut range.walk())
+                    .filter(|n| matches!(n.kind(), "int_lit" | "max"))
+                    .collect();
+                let Some(start) = bounds.first().and_then(|n| signed(*n, content)) else {
+                    continue;
+                };
+                let end = match bounds.last() {
+                    Some(n) if n.kind() == "max" => max_value,
+                    Some(n) if n.id() != bounds[0].id() => signed(*n, content).unwrap_or(start),
+                    _ => start,
+                };
+                out.push(NumberRange {
+                    start,
+                    end,
+                    range: to_lsp_range(range),
+                });
+            }
+        }
+    }
+    out
+}
+
+/// Reads an integer literal, applying the `-` sign preceding it.
+fn signed(node: Node, content: &str) -> Option<i64> {
+    let value = parse_int_lit(&content[node.byte_range()])?;
+    let negative = node.prev_sibling().is_some_and(|p| p.kind() == "-");
+    Some(if negative { -value } else { value })
+}
+
+/// Collects the names listed by the `reserved` statements of `body`.
+pub(super) fn reserved_names(body: Node, content: &str) -> Vec<(String, Range)> {
+    let mut out = Vec::new();
+    for statement in body
+        .children(&mut body.walk())
+        .filter(|n| n.kind() == "reserved")
+    {
+        for names in statement
+            .children(&mut statement.walk())
+            .filter(|n| n.kind() == "reserved_field_names")
+        {
+            for name in names.children(&mut names.walk()).filter(Node::is_named) {
+                let text = content[name.byte_range()].trim_matches(['"', '\'']);
+                out.push((text.to_string(), to_lsp_range(name)));
+            }
+        }
+    }
+    out
+}
+
+/// Returns the name of the type extended by the `extend` block around a
+/// field node, with its range, or `None` if the field is not an extension.
+pub(super) fn extendee(field: Node, content: &str) -> Option<(String, Range)> {
+    let extend = field.parent().and_then(|body| body.parent())?;
+    if extend.kind() != "extend" {
+        return None;
+    }
+    let name = extend
+        .children(&mut extend.walk())
+        .find(|c| c.kind() == "full_ident")?;
+    Some((content[name.byte_range()].to_string(), to_lsp_range(name)))
+}
+
+/// Lists the `extend` blocks of `tree`, empty ones included: the extended
+/// type as written with its range, and the range of the message declaring
+/// the block, or `None` at the top level.
+pub fn declared_extendees(tree: &Tree, content: &str) -> Vec<(String, Range, Option<Range>)> {
+    let mut out = Vec::new();
+    let mut pending = vec![tree.root_node()];
+    while let Some(node) = pending.pop() {
+        if node.kind() == "extend"
+            && let Some(name) = node
+                .children(&mut node.walk())
+                .find(|c| c.kind() == "full_ident")
+        {
+            let mut message = node.parent();
+            while let Some(m) = message.filter(|m| m.kind() != "message") {
+                message = m.parent();
+            }
+            out.push((
+                content[name.byte_range()].to_string(),
+                to_lsp_range(name),
+                message.map(to_lsp_range),
+            ));
+        }
+        pending.extend(node.children(&mut node.walk()));
+    }
+    out.sort_by_key(|(_, range, _)| range.start);
+    out
+}
+
+/// Returns the names reserved by the `message` or `enum` spanning `range`.
+pub fn declared_reserved_names(tree: &Tree, range: Range, content: &str) -> Vec<String> {
+    node_at(tree, range, &["message", "enum"])
+        .and_then(body)
+        .map(|body| {
+            reserved_names(body, content)
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns `public` or `weak` for an import node declared with one.
+pub(super) fn import_modifier(import: Node) -> Option<&'static str> {
+    import
+        .children(&mut import.walk())
+        .find_map(|c| match c.kind() {
+            "public" => Some("public"),
+            "weak" => Some("weak"),
+            _ => None,
+        })
+}
@@ -0,0 +1,151 @@
+//! Structural folding ranges computed from the raw Tree-sitter tree.
+//!
+//! Comments and option literals are not part of the metamodel, so folding
+//! walks the syntax tree directly, the same way parse diagnostics do.
+
+use async_lsp::lsp_types::{FoldingRange, FoldingRangeKind};
+use tree_sitter::Node;
+
+use crate::utils::to_lsp_range;
+
+use super::parser::ProtoDocument;
+
+/// Node kinds whose braced or bracketed body can be folded.
+const FOLDABLE_BLOCKS: &[&str] = &[
+    "message",
+    "enum",
+    "service",
+    "oneof",
+    "extend",
+    "rpc",
+    "block_lit",
+    "field_options",
+];
+
+impl ProtoDocument {
+    /// Collects the folding ranges of the document: braced definitions
+    /// (messages, enums, services, oneofs, extends and rpcs with a body),
+    /// option literals and multi-line field option lists, runs of comments,
+    /// and groups of consecutive imports.
+    ///
+    /// Block folds stop on the line before the closing delimiter so it stays
+    /// visible when folded.
+    pub fn folding_ranges(&self) -> Vec<FoldingRange> {
+        let root = self.tree.root_node();
+        let mut ranges = Vec::new();
+
+        collect_block_folds(root, &mut ranges);
+        collect_comment_folds(root, &mut ranges);
+        collect_import_folds(root, &mut ranges);
+
+        ranges.sort_by_key(|r| (r.start_line, r.end_line));
+        ranges
+    }
+}
+
+fn collect_block_folds(n: Node, out: &mut Vec<FoldingRange>) {
+    if FOLDABLE_BLOCKS.contains(&n.kind()) {
+        // Field options exclude their surrounding brackets, so fold from the
+        // `[` to the `]` siblings instead.
+        let (first, last) = if n.kind() == "field_options" {
+            (n.prev_sibling().unwrap_or(n), n.next_sibling().unwrap_or(n))
+        } else {
+            (n, n)
+        };
+        let start_line = to_lsp_range(first).start.line;
+        let end_line = to_lsp_range(last).end.line.saturating_sub(1);
+        if end_line > start_line {
+            out.push(fold(start_line, end_line, FoldingRangeKind::Region));
+        }
+    }
+
+    let mut cursor = n.walk();
+    for child in n.children(&mut cursor) {
+        collect_block_folds(child, out);
+    }
+}
+
+/// Folds every run of comments on adjacent lines spanning at least two lines,
+/// as well as single multi-line block comments.
+fn collect_comment_folds(n: Node, out: &mut Vec<FoldingRange>) {
+    let mut run: Option<(u32, u32)> = None;
+
+    let mut cursor = n.walk();
+    for child in n.children(&mut cursor) {
+        if child.kind() != "comment" {
+            flush(&mut run, FoldingRangeKind::Comment, out);
+            collect_comment_folds(child, out);
+            continue;
+        }
+
+        let range = to_lsp_range(child);
+        let (start, end) = (range.start.line, range.end.line);
+        run = match run {
+            Some((run_start, run_end)) if start == run_end + 1 => Some((run_start, end)),
+            _ => {
+                flush(&mut run, FoldingRangeKind::Comment, out);
+                Some((start, end))
+            }
+        };
+    }
+    flush(&mut run, FoldingRangeKind::Comment, out);
+}
+
+/// Folds every group of at least two top-level imports that are not separated
+/// by any other statement.
+fn collect_import_folds(root: Node, out: &mut Vec<FoldingRange>) {
+    let mut run: Option<(u32, u32)> = None;
+
+    let mut cursor = root.walk();
+    for child in root.children(&mut cursor) {
+        match child.kind() {
+            "import" => {
+                let range = to_lsp_range(child);
+                let (start, end) = (range.start.line, range.end.line);
+                run = Some(run.map_or((start, end), |(run_start, _)| (run_start, end)));
+            }
+            // Comments between imports do not break the group.
+            "comment" => {}
+            _ => flush(&mut run, FoldingRangeKind::Imports, out),
+        }
+    }
+    flush(&mut run, FoldingRangeKind::Imports, out);
+}
+
+fn flush(run: &mut Option<(u32, u32)>, kind: FoldingRangeKind, out: &mut Vec<FoldingRange>) {
+    if let Some((start, end)) = run.take()
+        && end > start
+    {
+        out.push(fold(start, end, kind));
+    }
+}
+
+fn fold(start_line: u32, end_line: u32, kind: FoldingRangeKind) -> FoldingRange {
+    FoldingRange {
+        start_line,
+        start_character: None,
+        end_line,
+        end_character: None,
+        kind: Some(kind),
+        collapsed_text: None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_lsp::lsp_types::Url;
+    use insta::assert_yaml_snapshot;
+
+    use crate::document::parser::ProtoParser;
+    use crate::utils::compile_test_query;
+
+    #[test]
+    fn test_folding_ranges() {
+        let url: Url = "file://foo/bar.proto".parse().unwrap();
+        let contents = include_str!("input/test_folding.proto");
+        let query = &compile_test_query();
+
+        let parsed = ProtoParser::new().parse(url, contents, query).unwrap();
+        assert_yaml_snapshot!(parsed.folding_ranges());
+    }
+}
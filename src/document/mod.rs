@@ -2,8 +2,11 @@ mod parser;
 
 mod diagnostics;
 mod docsymbol;
+mod folding;
 mod hover;
+mod on_type;
 mod rename;
+mod selection;
 mod syntax;
 
 pub use parser::{ProtoDocument, ProtoParser};
@@ -0,0 +1,387 @@
+//! Edits applied while typing: reindentation and field numbering.
+//!
+//! The line being typed is usually not valid yet, so indentation is computed
+//! from the raw text and the syntax tree is only used to find the enclosing
+//! message or enum.
+
+use async_lsp::lsp_types::{FormattingOptions, Position, Range, TextEdit};
+use tree_sitter::{Node, Point};
+
+use crate::descriptor::IMPLEMENTATION_RESERVED;
+use crate::utils::{byte_offset, is_identifier, parse_int_lit};
+
+use super::parser::ProtoDocument;
+
+/// Words that start a statement and therefore never a field type.
+const KEYWORDS: &[&str] = &[
+    "syntax",
+    "edition",
+    "package",
+    "import",
+    "option",
+    "message",
+    "enum",
+    "service",
+    "rpc",
+    "oneof",
+    "extend",
+    "reserved",
+    "extensions",
+];
+
+const LABELS: &[&str] = &["optional", "required", "repeated"];
+
+impl ProtoDocument {
+    /// Computes the edits for `ch` just typed before `position` in `content`.
+    ///
+    /// `}` and `;` reindent their line to its brace depth. `=` typed after a
+    /// field or enum value name inserts the next free number, and `;` ending
+    /// such a declaration without a number inserts ` = <number>` before it.
+    pub fn on_type_formatting(
+        &self,
+        content: &str,
+        position: Position,
+        ch: &str,
+        options: &FormattingOptions,
+    ) -> Vec<TextEdit> {
+        let Some((line_start, line)) = line_at(content, position.line) else {
+            return vec![];
+        };
+        let cursor = byte_offset(line, Position::new(0, position.character));
+
+        let mut edits = Vec::new();
+        if matches!(ch, "}" | ";")
+            && let Some(edit) = reindent(&content[..line_start], line, position.line, options)
+        {
+            edits.push(edit);
+        }
+        if matches!(ch, "=" | ";")
+            && let Some(edit) = self.number_edit(content, line, position, cursor, ch)
+        {
+            edits.push(edit);
+        }
+        edits
+    }
+
+    fn number_edit(
+        &self,
+        content: &str,
+        line: &str,
+        position: Position,
+        cursor: usize,
+        ch: &str,
+    ) -> Option<TextEdit> {
+        let before = line[..cursor].strip_suffix(ch)?.trim();
+        let after = line[cursor..].trim();
+
+        let point = Point {
+            row: position.line as usize,
+            column: cursor.saturating_sub(1),
+        };
+        let body = enclosing_body(self.tree.root_node(), point)?;
+        let is_enum = body.kind() == "enum_body";
+        if !is_declaration(before, is_enum) {
+            return None;
+        }
+
+        let number = next_number(body, content, is_enum);
+        match ch {
+            "=" if after.is_empty() => Some(insert_at(position, format!(" {number}"))),
+            ";" => {
+                let semicolon = Position {
+                    line: position.line,
+                    character: position.character.saturating_sub(1),
+                };
+                Some(insert_at(semicolon, format!(" = {number}")))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn insert_at(position: Position, new_text: String) -> TextEdit {
+    TextEdit {
+        range: Range {
+            start: position,
+            end: position,
+        },
+        new_text,
+    }
+}
+
+/// Returns the byte offset at which line `line` starts and its text, without
+/// line terminator.
+fn line_at(content: &str, line: u32) -> Option<(usize, &str)> {
+    let mut start = 0;
+    for (i, text) in content.split_inclusive('\n').enumerate() {
+        if i == line as usize {
+            return Some((start, text.trim_end_matches(['\n', '\r'])));
+        }
+        start += text.len();
+    }
+    // The cursor may sit on an empty last line.
+    (content.len() == start && content.split_inclusive('\n').count() == line as usize)
+        .then_some((start, ""))
+}
+
+fn reindent(
+    preceding: &str,
+    line: &str,
+    line_number: u32,
+    options: &FormattingOptions,
+) -> Option<TextEdit> {
+    let depth = brace_depth(preceding)?;
+    let text = line.trim_start();
+    let depth = if text.starts_with('}') {
+        depth.saturating_sub(1)
+    } else {
+        depth
+    };
+
+    let unit = if options.insert_spaces {
+        " ".repeat(options.tab_size as usize)
+    } else {
+        "\t".to_string()
+    };
+    let indent = unit.repeat(depth);
+    let current = &line[..line.len() - text.len()];
+    if current == indent {
+        return None;
+    }
+
+    Some(TextEdit {
+        range: Range {
+            start: Position {
+                line: line_number,
+                character: 0,
+            },
+            end: Position {
+                line: line_number,
+                character: u32::try_from(current.encode_utf16().count()).ok()?,
+            },
+        },
+        new_text: indent,
+    })
+}
+
+/// Counts the braces left open at the end of `text`, skipping strings and
+/// comments. Returns `None` if `text` ends inside a block comment or string.
+fn brace_depth(text: &str) -> Option<usize> {
+    let mut depth: usize = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            '/' if chars.peek() == Some(&'/') => {
+                chars.by_ref().find(|&c| c == '\n');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut closed = false;
+                while let Some(c) = chars.next() {
+                    if c == '*' && chars.peek() == Some(&'/') {
+                        chars.next();
+                        closed = true;
+                        break;
+                    }
+                }
+                if !closed {
+                    return None;
+                }
+            }
+            '"' | '\'' => {
+                let mut closed = false;
+                while let Some(s) = chars.next() {
+                    match s {
+                        '\\' => {
+                            chars.next();
+                        }
+                        s if s == c => {
+                            closed = true;
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                if !closed {
+                    return None;
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(depth)
+}
+
+/// Finds the message or enum body around `point`. Fields of a oneof are
+/// numbered within their message.
+fn enclosing_body(root: Node, point: Point) -> Option<Node> {
+    let mut node = root.descendant_for_point_range(point, point);
+    while let Some(n) = node {
+        match n.kind() {
+            "message_body" | "enum_body" => return Some(n),
+            "oneof" => return n.parent().filter(|p| p.kind() == "message_body"),
+            "service" | "rpc" | "block_lit" | "field_options" | "source_file" => return None,
+            _ => node = n.parent(),
+        }
+    }
+    None
+}
+
+/// Whether `text` is a field (`[label] type name`, `map<K, V> name`) or, in an
+/// enum, a value name, waiting for its number.
+fn is_declaration(text: &str, is_enum: bool) -> bool {
+    if is_enum {
+        return is_identifier(text) && !KEYWORDS.contains(&text);
+    }
+
+    if let Some(map) = text.strip_prefix("map")
+        && map.trim_start().starts_with('<')
+    {
+        return map
+            .split_once('>')
+            .is_some_and(|(_, name)| is_identifier(name.trim()));
+    }
+
+    let mut words: Vec<&str> = text.split_whitespace().collect();
+    if words.first().is_some_and(|w| LABELS.contains(w)) {
+        words.remove(0);
+    }
+    match words.as_slice() {
+        [ty, name] => {
+            !KEYWORDS.contains(ty)
+                && is_identifier(ty.trim_start_matches('.').replace('.', "_").as_str())
+                && is_identifier(name)
+        }
+        _ => false,
+    }
+}
+
+/// Returns the number following the highest one used in `body`, skipping
+/// reserved and extension ranges.
+fn next_number(body: Node, content: &str, is_enum: bool) -> i64 {
+    let mut used: Vec<i64> = Vec::new();
+    let mut reserved: Vec<(i64, i64)> = Vec::new();
+    collect_numbers(body, content, &mut used, &mut reserved);
+    if !is_enum {
+        reserved.push((
+            *IMPLEMENTATION_RESERVED.start(),
+            *IMPLEMENTATION_RESERVED.end(),
+        ));
+    }
+
+    let mut next = used.iter().max().map_or(i64::from(!is_enum), |max| max + 1);
+    while let Some(&(_, end)) = reserved
+        .iter()
+        .find(|(start, end)| (*start..=*end).contains(&next))
+    {
+        next = end.saturating_add(1);
+    }
+    next
+}
+
+fn collect_numbers(node: Node, content: &str, used: &mut Vec<i64>, reserved: &mut Vec<(i64, i64)>) {
+    for child in node.children(&mut node.walk()) {
+        match child.kind() {
+            "field" | "map_field" | "oneof_field" => {
+                if let Some(number) = child
+                    .children(&mut child.walk())
+                    .find(|n| n.kind() == "field_number")
+                    .and_then(|n| parse_int_lit(&content[n.byte_range()]))
+                {
+                    used.push(number);
+                }
+            }
+            "enum_field" => {
+                let negative = child.children(&mut child.walk()).any(|n| n.kind() == "-");
+                if let Some(number) = child
+                    .children(&mut child.walk())
+                    .find(|n| n.kind() == "int_lit")
+                    .and_then(|n| parse_int_lit(&content[n.byte_range()]))
+                {
+                    used.push(if negative { -number } else { number });
+                }
+            }
+            "oneof" | "ERROR" => collect_numbers(child, content, used, reserved),
+            "reserved" | "extensions" => {
+                for ranges in child
+                    .children(&mut child.walk())
+                    .filter(|n| n.kind() == "ranges")
+                {
+                    for range in ranges
+                        .children(&mut ranges.walk())
+                        .filter(|n| n.kind() == "range")
+                    {
+                        let bounds: Vec<Node> = range.children(&mut range.walk()).collect();
+                        let Some(start) = bounds
+                            .first()
+                            .and_then(|n| parse_int_lit(&content[n.byte_range()]))
+                        else {
+                            continue;
+                        };
+                        let end = match bounds.last() {
+                            Some(n) if n.kind() == "max" => i64::MAX,
+                            Some(n) => parse_int_lit(&content[n.byte_range()]).unwrap_or(start),
+                            None => start,
+                        };
+                        reserved.push((start, end));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_lsp::lsp_types::{FormattingOptions, Position, Url};
+    use insta::assert_yaml_snapshot;
+
+    use crate::document::parser::ProtoParser;
+    use crate::utils::compile_test_query;
+
+    fn on_type(
+        contents: &str,
+        line: u32,
+        character: u32,
+        ch: &str,
+    ) -> Vec<async_lsp::lsp_types::TextEdit> {
+        let url: Url = "file://foo/bar.proto".parse().unwrap();
+        let query = &compile_test_query();
+        let parsed = ProtoParser::new().parse(url, contents, query).unwrap();
+        let options = FormattingOptions {
+            tab_size: 2,
+            insert_spaces: true,
+            ..Default::default()
+        };
+        parsed.on_type_formatting(contents, Position { line, character }, ch, &options)
+    }
+
+    #[test]
+    fn test_reindent() {
+        let contents = include_str!("input/test_on_type.proto");
+
+        // Closing brace typed at the wrong depth.
+        assert_yaml_snapshot!(on_type(contents, 9, 7, "}"));
+        // Statement typed without indentation.
+        assert_yaml_snapshot!(on_type(contents, 5, 17, ";"));
+        // Already indented.
+        assert!(on_type(contents, 4, 16, ";").is_empty());
+    }
+
+    #[test]
+    fn test_next_field_number() {
+        let contents = include_str!("input/test_on_type.proto");
+
+        // After `=`, skipping the reserved range.
+        assert_yaml_snapshot!(on_type(contents, 6, 17, "="));
+        // A `;` closing a field without number.
+        assert_yaml_snapshot!(on_type(contents, 7, 14, ";"));
+        // Enum values are numbered from their enum.
+        assert_yaml_snapshot!(on_type(contents, 14, 10, "="));
+        // Not a declaration.
+        assert!(on_type(contents, 16, 21, "=").is_empty());
+    }
+}
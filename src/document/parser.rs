@@ -3,7 +3,9 @@ use std::sync::Arc;
 use async_lsp::lsp_types::{Range, Url};
 use tree_sitter::{Parser, Query, Tree};
 
-use crate::model::{ElementKind, ModelElement, SpatialEntry, build_meta_model};
+use crate::model::{
+    ElementKind, MetaModel, ModelElement, OptionEntry, SpatialEntry, SyntaxKind, build_meta_model,
+};
 pub struct ProtoParser {
     parser: tree_sitter::Parser,
 }
@@ -12,6 +14,8 @@ pub struct ProtoParser {
 pub struct ProtoDocument {
     pub uri: Url,
     pub package: String,
+    pub syntax: SyntaxKind,
+    pub options: Vec<OptionEntry>,
     pub elements: Vec<ModelElement>,
     pub spatial_index: Vec<SpatialEntry>,
     pub tree: Arc<Tree>,
@@ -45,7 +49,12 @@ impl ProtoDocument {
     ) -> Option<Self> {
         let tree = ts_parser.parse(source, None)?;
 
-        let (package, elements) = build_meta_model(tree.root_node(), source, query);
+        let MetaModel {
+            package,
+            syntax,
+            options,
+            elements,
+        } = build_meta_model(tree.root_node(), source, query);
 
         let mut spatial_index = Vec::with_capacity(elements.len() * 2);
 
@@ -58,6 +67,8 @@ impl ProtoDocument {
         Some(Self {
             uri,
             package,
+            syntax,
+            options,
             elements,
             spatial_index,
             tree: Arc::new(tree),
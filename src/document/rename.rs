@@ -2,13 +2,18 @@ use async_lsp::lsp_types::{Position, Range};
 
 use crate::{
     model::{ElementKind, SpatialEntry, TypeReference},
-    utils::is_position_inside_range,
+    utils::{is_position_inside_range, to_lsp_range},
 };
 
 use super::parser::ProtoDocument;
 
 impl ProtoDocument {
     pub fn can_rename(&self, pos: Position) -> Option<Range> {
+        if let Some(range) = self.package_name_range()
+            && is_position_inside_range(pos, range)
+        {
+            return Some(range);
+        }
         let SpatialEntry { element_id, .. } = self.find_entry_at_position(pos)?;
         let element = self.elements.get(*element_id)?;
         if matches!(element.kind, ElementKind::Import { .. }) {
@@ -22,6 +27,19 @@ impl ProtoDocument {
         type_ref_segment_range(type_ref, pos)
     }
 
+    /// Returns the range of the package name of the `package` statement, if
+    /// the document declares one.
+    pub fn package_name_range(&self) -> Option<Range> {
+        let root = self.tree.root_node();
+        let package = root
+            .children(&mut root.walk())
+            .find(|n| n.kind() == "package")?;
+        let name = package
+            .children(&mut package.walk())
+            .find(|n| n.kind() == "full_ident")?;
+        Some(to_lsp_range(name))
+    }
+
     /// If the given position is on the rpc name of an rpc declaration, returns
     /// the rpc's name along with its declared request and response type texts.
     /// Used to drive the rpc/request/response chained rename.
@@ -120,7 +138,7 @@ fn type_ref_segment_range(type_ref: &TypeReference, position: Position) -> Optio
 
 #[cfg(test)]
 mod test {
-    use async_lsp::lsp_types::{Position, Url};
+    use async_lsp::lsp_types::{Position, Range, Url};
     use insta::assert_yaml_snapshot;
 
     use crate::document::parser::ProtoParser;
@@ -156,6 +174,24 @@ mod test {
         assert_yaml_snapshot!(document.can_rename(pos_non_rename));
         assert_yaml_snapshot!(document.can_rename(pos_inner_type));
         assert_yaml_snapshot!(document.can_rename(pos_outer_type));
+
+        // The package name is renamed as a whole.
+        let package = Range {
+            start: Position {
+                line: 2,
+                character: 8,
+            },
+            end: Position {
+                line: 2,
+                character: 18,
+            },
+        };
+        assert_eq!(document.package_name_range(), Some(package));
+        let pos_package = Position {
+            line: 2,
+            character: 12,
+        };
+        assert_eq!(document.can_rename(pos_package), Some(package));
     }
 
     #[test]
@@ -0,0 +1,112 @@
+//! Smart selection expansion over the syntax tree and the metamodel.
+
+use async_lsp::lsp_types::{Position, Range, SelectionRange};
+use tree_sitter::Point;
+
+use crate::utils::{is_position_inside_range, to_lsp_range};
+
+use super::parser::ProtoDocument;
+
+impl ProtoDocument {
+    /// Builds the chain of ranges an "expand selection" request walks through
+    /// from `position`: the token under the cursor, the full (possibly
+    /// qualified) type reference, every enclosing element from the innermost
+    /// field or value up to its top-level definition, and finally the whole
+    /// file.
+    ///
+    /// Each range strictly contains the previous one; duplicates are dropped.
+    pub fn selection_range(&self, position: Position) -> SelectionRange {
+        let root = self.tree.root_node();
+        let mut ranges: Vec<Range> = Vec::new();
+
+        let point = Point {
+            row: position.line as usize,
+            column: position.character as usize,
+        };
+        if let Some(token) = root.descendant_for_point_range(point, point)
+            && token.id() != root.id()
+        {
+            ranges.push(to_lsp_range(token));
+        }
+
+        let innermost = self
+            .elements
+            .iter()
+            .rfind(|e| is_position_inside_range(position, e.meta.range));
+
+        if let Some(type_ref) = innermost.and_then(|e| e.type_reference_at(position)) {
+            ranges.push(type_ref.range);
+        }
+
+        let mut current = innermost;
+        while let Some(element) = current {
+            ranges.push(element.meta.range);
+            current = element.parent_id.and_then(|id| self.elements.get(id));
+        }
+
+        ranges.push(to_lsp_range(root));
+
+        let mut chain: Vec<Range> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            if let Some(last) = chain.last()
+                && (range == *last
+                    || !is_position_inside_range(last.start, range)
+                    || !is_position_inside_range(last.end, range))
+            {
+                continue;
+            }
+            chain.push(range);
+        }
+
+        chain
+            .into_iter()
+            .rev()
+            .fold(None, |parent, range| {
+                Some(SelectionRange {
+                    range,
+                    parent: parent.map(Box::new),
+                })
+            })
+            .unwrap_or(SelectionRange {
+                range: Range {
+                    start: position,
+                    end: position,
+                },
+                parent: None,
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_lsp::lsp_types::{Position, Url};
+    use insta::assert_yaml_snapshot;
+
+    use crate::document::parser::ProtoParser;
+    use crate::utils::compile_test_query;
+
+    #[test]
+    fn test_selection_range() {
+        let url: Url = "file://foo/bar.proto".parse().unwrap();
+        let contents = include_str!("input/test_folding.proto");
+        let query = &compile_test_query();
+
+        let parsed = ProtoParser::new().parse(url, contents, query).unwrap();
+
+        // Field name inside a nested message.
+        assert_yaml_snapshot!(parsed.selection_range(Position {
+            line: 22,
+            character: 12
+        }));
+        // Field type inside a oneof.
+        assert_yaml_snapshot!(parsed.selection_range(Position {
+            line: 26,
+            character: 5
+        }));
+        // Rpc request type.
+        assert_yaml_snapshot!(parsed.selection_range(Position {
+            line: 41,
+            character: 15
+        }));
+    }
+}
@@ -0,0 +1,47 @@
+//! Formatter following the conventions of `buf format`.
+//!
+//! It shares the printer of the native formatter so that both agree on
+//! everything `buf format` does not have an opinion on.
+
+use async_lsp::lsp_types::{Range, TextEdit};
+
+use super::ProtoFormatter;
+use super::native::{Style, format_edits};
+
+/// Formats documents the way `buf format` does, so that format-on-save and
+/// `buf format --diff` in CI agree.
+///
+/// Compared to the native style, `=` signs are not aligned and fields with
+/// several options list each one on its own line.
+pub struct BufFormatter;
+
+impl ProtoFormatter for BufFormatter {
+    fn format_document(&self, _filename: &str, content: &str) -> Option<Vec<TextEdit>> {
+        format_edits(content, &Style::BUF, None)
+    }
+
+    fn format_document_range(
+        &self,
+        r: &Range,
+        _filename: &str,
+        content: &str,
+    ) -> Option<Vec<TextEdit>> {
+        format_edits(content, &Style::BUF, Some(r))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use insta::assert_snapshot;
+
+    use crate::formatter::native::{Style, format};
+
+    #[test]
+    fn test_format_document() {
+        let c = include_str!("input/buf.proto");
+        let formatted = format(c, &Style::BUF, true).unwrap();
+        assert_snapshot!(formatted);
+
+        assert_eq!(format(&formatted, &Style::BUF, true).unwrap(), formatted);
+    }
+}
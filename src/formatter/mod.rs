@@ -1,8 +1,14 @@
+mod buf;
 mod clang;
+mod native;
 
 use async_lsp::lsp_types::{Range, TextEdit};
 
+use crate::utils::byte_offset;
+
+pub use buf::BufFormatter;
 pub use clang::ClangFormatter;
+pub use native::NativeFormatter;
 
 pub trait ProtoFormatter: Sized {
     fn format_document(&self, filename: &str, content: &str) -> Option<Vec<TextEdit>>;
@@ -13,3 +19,76 @@ pub trait ProtoFormatter: Sized {
         content: &str,
     ) -> Option<Vec<TextEdit>>;
 }
+
+/// Formatter selected for a workspace through `[config.formatter]`.
+pub enum Formatter {
+    Clang(ClangFormatter),
+    Native(NativeFormatter),
+    Buf(BufFormatter),
+}
+
+impl ProtoFormatter for Formatter {
+    fn format_document(&self, filename: &str, content: &str) -> Option<Vec<TextEdit>> {
+        match self {
+            Formatter::Clang(f) => f.format_document(filename, content),
+            Formatter::Native(f) => f.format_document(filename, content),
+            Formatter::Buf(f) => f.format_document(filename, content),
+        }
+    }
+
+    fn format_document_range(
+        &self,
+        r: &Range,
+        filename: &str,
+        content: &str,
+    ) -> Option<Vec<TextEdit>> {
+        match self {
+            Formatter::Clang(f) => f.format_document_range(r, filename, content),
+            Formatter::Native(f) => f.format_document_range(r, filename, content),
+            Formatter::Buf(f) => f.format_document_range(r, filename, content),
+        }
+    }
+}
+
+/// Applies formatting `edits`, which must not overlap, to `content`.
+pub fn apply_edits(content: &str, edits: &[TextEdit]) -> String {
+    let mut edits: Vec<&TextEdit> = edits.iter().collect();
+    edits.sort_by_key(|edit| edit.range.start);
+
+    let mut out = String::with_capacity(content.len());
+    let mut last = 0;
+    for edit in edits {
+        let start = byte_offset(content, edit.range.start).max(last);
+        out.push_str(&content[last..start]);
+        out.push_str(&edit.new_text);
+        last = byte_offset(content, edit.range.end).max(start);
+    }
+    out.push_str(&content[last..]);
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use async_lsp::lsp_types::{Position, Range, TextEdit};
+
+    use super::apply_edits;
+
+    #[test]
+    fn test_apply_edits() {
+        let content = "message Ц  {\nint32 a=1;\n}";
+        let edit = |start: (u32, u32), end: (u32, u32), text: &str| TextEdit {
+            range: Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1)),
+            new_text: text.to_string(),
+        };
+        let edits = [
+            edit((2, 1), (2, 1), "\n"),
+            edit((0, 9), (0, 10), ""),
+            edit((1, 0), (2, 0), "  int32 a = 1;\n"),
+        ];
+        assert_eq!(
+            apply_edits(content, &edits),
+            "message Ц {\n  int32 a = 1;\n}\n"
+        );
+        assert_eq!(apply_edits(content, &[]), content);
+    }
+}
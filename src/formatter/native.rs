@@ -0,0 +1,680 @@
+//! Built-in formatter pretty-printing the Tree-sitter syntax tree.
+//!
+//! Unlike clang-format it needs no external binary and understands protobuf
+//! constructs: option literals are laid out one entry per line instead of
+//! being treated as C++ initializer lists.
+
+use async_lsp::lsp_types::{Position, Range, TextEdit};
+use similar::{Algorithm, DiffTag, TextDiff};
+use tree_sitter::{Node, Parser};
+
+use super::ProtoFormatter;
+
+const INDENT: &str = "  ";
+
+/// Statements whose `=` sign and number are aligned with their neighbours.
+const ALIGNED: &[&str] = &["field", "map_field", "oneof_field", "enum_field"];
+
+/// Top-level definitions always separated from their neighbours by a blank
+/// line.
+const DEFINITIONS: &[&str] = &["message", "enum", "service", "extend"];
+
+/// Nodes printed as a single token.
+const ATOMIC: &[&str] = &[
+    "string",
+    "full_ident",
+    "message_or_enum_type",
+    "int_lit",
+    "float_lit",
+    "identifier",
+    "bool",
+    "block_lit",
+    "key_type",
+    "field_options",
+];
+
+/// Formats documents without any external tool.
+///
+/// Blocks are indented by two spaces with the opening brace on the
+/// declaration line, consecutive fields and enum values have their `=` signs
+/// aligned, top-level imports are sorted, and blank lines are collapsed so
+/// that at most one separates two statements. Comments are kept where they
+/// are; a statement with a comment in the middle of it is left untouched.
+pub struct NativeFormatter;
+
+impl ProtoFormatter for NativeFormatter {
+    fn format_document(&self, _filename: &str, content: &str) -> Option<Vec<TextEdit>> {
+        format_edits(content, &Style::NATIVE, None)
+    }
+
+    fn format_document_range(
+        &self,
+        r: &Range,
+        _filename: &str,
+        content: &str,
+    ) -> Option<Vec<TextEdit>> {
+        format_edits(content, &Style::NATIVE, Some(r))
+    }
+}
+
+/// Layout choices on which the supported styles differ.
+pub(super) struct Style {
+    /// Align the `=` signs of consecutive fields and enum values.
+    align: bool,
+    /// Put each option of a field with several options on its own line.
+    expand_field_options: bool,
+    /// Always separate keys from message values in option literals by a
+    /// colon, instead of keeping the one from the source.
+    literal_colons: bool,
+}
+
+impl Style {
+    pub(super) const NATIVE: Style = Style {
+        align: true,
+        expand_field_options: false,
+        literal_colons: false,
+    };
+
+    pub(super) const BUF: Style = Style {
+        align: false,
+        expand_field_options: true,
+        literal_colons: true,
+    };
+}
+
+/// Formats `content` in `style`, or returns `None` if it does not parse.
+pub(super) fn format(content: &str, style: &Style, sort_imports: bool) -> Option<String> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&tree_sitter_proto::LANGUAGE.into())
+        .ok()?;
+    let tree = parser.parse(content, None)?;
+    let root = tree.root_node();
+    if root.has_error() {
+        return None;
+    }
+
+    let printer = Printer {
+        source: content,
+        style,
+        sort_imports,
+    };
+    Some(printer.file(root))
+}
+
+/// Edits formatting the whole of `content`, or only the lines of `range`.
+pub(super) fn format_edits(
+    content: &str,
+    style: &Style,
+    range: Option<&Range>,
+) -> Option<Vec<TextEdit>> {
+    let Some(r) = range else {
+        let formatted = format(content, style, true)?;
+        return Some(line_edits(content, &formatted, None));
+    };
+
+    // Reordered imports turn into separate deletions and insertions, which
+    // would duplicate lines if only some of them were applied.
+    let formatted = format(content, style, false)?;
+    let lines = (r.start.line as usize, r.end.line as usize);
+    Some(line_edits(content, &formatted, Some(lines)))
+}
+
+/// Diffs `original` against `formatted` line by line and turns every changed
+/// hunk into an edit. With `lines`, only hunks touching that inclusive line
+/// range are kept.
+fn line_edits(original: &str, formatted: &str, lines: Option<(usize, usize)>) -> Vec<TextEdit> {
+    let diff = TextDiff::configure()
+        .algorithm(Algorithm::Patience)
+        .diff_lines(original, formatted);
+    let old = diff.old_slices();
+    let new = diff.new_slices();
+
+    diff.ops()
+        .iter()
+        .filter(|op| op.tag() != DiffTag::Equal)
+        .filter(|op| {
+            lines.is_none_or(|(start, end)| {
+                let changed = op.old_range();
+                let last = changed.end.max(changed.start + 1) - 1;
+                changed.start <= end && last >= start
+            })
+        })
+        .filter_map(|op| {
+            let changed = op.old_range();
+            let start = Position {
+                line: u32::try_from(changed.start).ok()?,
+                character: 0,
+            };
+            // The last line has no newline to end the range on.
+            let end = match old.get(changed.end.wrapping_sub(1)) {
+                Some(last) if changed.end == old.len() && !last.ends_with('\n') => Position {
+                    line: u32::try_from(changed.end - 1).ok()?,
+                    character: u32::try_from(last.encode_utf16().count()).ok()?,
+                },
+                _ => Position {
+                    line: u32::try_from(changed.end).ok()?,
+                    character: 0,
+                },
+            };
+            Some(TextEdit {
+                range: Range { start, end },
+                new_text: new[op.new_range()].concat(),
+            })
+        })
+        .collect()
+}
+
+/// A statement together with the comments attached to it.
+struct Unit<'t> {
+    /// Comments on the lines right above the statement.
+    comments: Vec<Node<'t>>,
+    /// `None` for comments separated by a blank line from what follows.
+    statement: Option<Node<'t>>,
+    /// Comment on the same line as the end of the statement.
+    trailing: Option<Node<'t>>,
+    blank_before: bool,
+}
+
+struct Printer<'a> {
+    source: &'a str,
+    style: &'a Style,
+    sort_imports: bool,
+}
+
+impl Printer<'_> {
+    fn file(&self, root: Node) -> String {
+        let children: Vec<Node> = root.children(&mut root.walk()).collect();
+        let (_, mut units) = units(&children, None);
+
+        if self.sort_imports {
+            self.sort_imports(&mut units);
+        }
+        for i in 1..units.len() {
+            if let (Some(prev), Some(cur)) = (units[i - 1].statement, units[i].statement) {
+                units[i].blank_before |= DEFINITIONS.contains(&prev.kind())
+                    || DEFINITIONS.contains(&cur.kind())
+                    || prev.kind() != cur.kind();
+            }
+        }
+
+        let mut out = String::new();
+        self.units(&units, 0, &mut out);
+        out
+    }
+
+    /// Sorts each group of consecutive imports by path and removes the blank
+    /// lines inside it.
+    fn sort_imports(&self, units: &mut [Unit]) {
+        let is_import = |u: &Unit| u.statement.is_some_and(|n| n.kind() == "import");
+
+        let mut start = 0;
+        while start < units.len() {
+            if !is_import(&units[start]) {
+                start += 1;
+                continue;
+            }
+            let end = units[start..]
+                .iter()
+                .position(|u| !is_import(u))
+                .map_or(units.len(), |len| start + len);
+
+            let blank_before = units[start].blank_before;
+            units[start..end].sort_by_cached_key(|u| {
+                u.statement
+                    .and_then(|n| n.child_by_field_name("path"))
+                    .map(|path| self.text(path).trim_matches(['"', '\'']).to_string())
+            });
+            for unit in &mut units[start..end] {
+                unit.blank_before = false;
+            }
+            units[start].blank_before = blank_before;
+
+            start = end;
+        }
+    }
+
+    fn units(&self, units: &[Unit], depth: usize, out: &mut String) {
+        let indent = INDENT.repeat(depth);
+        let parts: Vec<Option<(String, String)>> = units
+            .iter()
+            .map(|u| u.statement.and_then(|n| self.aligned_parts(n, depth)))
+            .collect();
+
+        // Width of the part before `=` for each run of aligned statements.
+        let mut widths = vec![0; units.len()];
+        let mut start = 0;
+        while start < units.len() {
+            let end = (start + 1..units.len())
+                .find(|&i| parts[i].is_none() || units[i].blank_before)
+                .unwrap_or(units.len());
+            let width = parts[start..end]
+                .iter()
+                .flatten()
+                .map(|(head, _)| head.chars().count())
+                .max()
+                .unwrap_or_default();
+            widths[start..end].fill(width);
+            start = end;
+        }
+
+        for (i, unit) in units.iter().enumerate() {
+            if i > 0 && unit.blank_before {
+                out.push('\n');
+            }
+            for comment in &unit.comments {
+                out.push_str(&indent);
+                out.push_str(&self.verbatim(*comment));
+                out.push('\n');
+            }
+            let Some(statement) = unit.statement else {
+                continue;
+            };
+
+            match &parts[i] {
+                Some((head, tail)) => {
+                    let padding = widths[i] - head.chars().count();
+                    out.push_str(&indent);
+                    out.push_str(head);
+                    out.push_str(&" ".repeat(padding + 1));
+                    out.push_str(tail);
+                }
+                None => self.statement(statement, depth, out),
+            }
+            if let Some(comment) = unit.trailing {
+                out.push(' ');
+                out.push_str(&self.verbatim(comment));
+            }
+            out.push('\n');
+        }
+    }
+
+    fn statement(&self, node: Node, depth: usize, out: &mut String) {
+        let parts = body_parts(node);
+        let open = parts.iter().position(|n| is_token(*n, "{"));
+        let close = parts.iter().rposition(|n| is_token(*n, "}"));
+
+        let indent = INDENT.repeat(depth);
+        let (Some(open), Some(close)) = (open, close) else {
+            out.push_str(&indent);
+            if has_comment(node) {
+                out.push_str(&self.verbatim(node));
+            } else {
+                out.push_str(&self.inline(&tokens(&parts), depth));
+            }
+            // The grammar leaves the `;` of oneof fields as a separate
+            // statement, which is dropped.
+            if node.kind() == "oneof_field" {
+                out.push(';');
+            }
+            return;
+        };
+
+        let header = &parts[..open];
+        if header.iter().any(|n| has_comment(*n)) {
+            out.push_str(&indent);
+            out.push_str(&self.verbatim(node));
+            return;
+        }
+
+        out.push_str(&indent);
+        out.push_str(&self.inline(&tokens(header), depth));
+        out.push_str(" {");
+
+        let (comment, units) = units(
+            &parts[open + 1..close],
+            Some(parts[open].end_position().row),
+        );
+        if let Some(comment) = comment {
+            out.push(' ');
+            out.push_str(&self.verbatim(comment));
+        }
+        if units.is_empty() {
+            if comment.is_some() {
+                out.push('\n');
+                out.push_str(&indent);
+            }
+            out.push('}');
+            return;
+        }
+
+        out.push('\n');
+        self.units(&units, depth + 1, out);
+        out.push_str(&indent);
+        out.push('}');
+    }
+
+    /// Splits a field or enum value around its `=` sign so that consecutive
+    /// ones can be aligned.
+    fn aligned_parts(&self, node: Node, depth: usize) -> Option<(String, String)> {
+        if !self.style.align || !ALIGNED.contains(&node.kind()) || has_comment(node) {
+            return None;
+        }
+
+        let tokens = tokens(&[node]);
+        let eq = tokens
+            .iter()
+            .position(|t| is_token(*t, "=") && t.parent() == Some(node))?;
+
+        let head = self.inline(&tokens[..eq], depth);
+        let mut tail = self.inline(&tokens[eq..], depth);
+        if node.kind() == "oneof_field" {
+            tail.push(';');
+        }
+        Some((head, tail))
+    }
+
+    /// Joins tokens on a single line, except for option literals which span
+    /// several lines.
+    fn inline(&self, tokens: &[Node], depth: usize) -> String {
+        let mut out = String::new();
+        let mut prev: Option<Node> = None;
+        for &token in tokens {
+            if prev.is_some_and(|p| spaced(p, token)) {
+                out.push(' ');
+            }
+            if token.kind() == "block_lit" {
+                out.push_str(&self.block_lit(token, depth));
+            } else if token.kind() == "field_options" {
+                out.push_str(&self.field_options(token, depth));
+            } else {
+                out.push_str(&self.token(token));
+            }
+            prev = Some(token);
+        }
+        out
+    }
+
+    /// Prints the options between the brackets of a field.
+    fn field_options(&self, node: Node, depth: usize) -> String {
+        let options: Vec<String> = node
+            .children(&mut node.walk())
+            .filter(|n| n.kind() == "field_option")
+            .map(|n| self.inline(&tokens(&[n]), depth + 1))
+            .collect();
+
+        if !self.style.expand_field_options || options.len() < 2 {
+            return options.join(", ");
+        }
+
+        let indent = INDENT.repeat(depth);
+        let mut out = String::from("\n");
+        for (i, option) in options.iter().enumerate() {
+            out.push_str(&indent);
+            out.push_str(INDENT);
+            out.push_str(option);
+            if i + 1 < options.len() {
+                out.push(',');
+            }
+            out.push('\n');
+        }
+        out.push_str(&indent);
+        out
+    }
+
+    /// Lays out an option literal with one `key: value` entry per line.
+    fn block_lit(&self, node: Node, depth: usize) -> String {
+        let children: Vec<Node> = node.children(&mut node.walk()).collect();
+        let mut entries: Vec<String> = Vec::new();
+        let mut key: Option<String> = None;
+        let mut colon = false;
+
+        let mut i = 0;
+        while i < children.len() {
+            let child = children[i];
+            i += 1;
+
+            let value = match child.kind() {
+                "{" | "}" | "," | ";" if !child.is_named() => continue,
+                ":" if !child.is_named() => {
+                    colon = true;
+                    continue;
+                }
+                "[" if !child.is_named() => {
+                    let end = children[i..]
+                        .iter()
+                        .position(|n| is_token(*n, "]"))
+                        .map_or(children.len(), |len| i + len);
+                    let inner = &children[i..end];
+                    i = end + 1;
+
+                    if key.is_none() {
+                        let name = inner.iter().map(|n| self.token(*n)).collect::<String>();
+                        key = Some(format!("[{name}]"));
+                        continue;
+                    }
+                    let items: Vec<String> = inner
+                        .iter()
+                        .filter(|n| n.kind() == "constant")
+                        .map(|n| self.inline(&tokens(&[*n]), depth + 1))
+                        .collect();
+                    format!("[{}]", items.join(", "))
+                }
+                _ if key.is_none() => {
+                    key = Some(self.token(child));
+                    continue;
+                }
+                _ => self.inline(&tokens(&[child]), depth + 1),
+            };
+
+            let key = key.take().unwrap_or_default();
+            if value.starts_with('{') && !colon && !self.style.literal_colons {
+                entries.push(format!("{key} {value}"));
+            } else {
+                entries.push(format!("{key}: {value}"));
+            }
+            colon = false;
+        }
+
+        if entries.is_empty() {
+            return "{}".to_string();
+        }
+
+        let indent = INDENT.repeat(depth);
+        let mut out = String::from("{\n");
+        for entry in entries {
+            out.push_str(&indent);
+            out.push_str(INDENT);
+            out.push_str(&entry);
+            out.push('\n');
+        }
+        out.push_str(&indent);
+        out.push('}');
+        out
+    }
+
+    fn token(&self, node: Node) -> String {
+        let text = self.text(node);
+        if node.kind() == "string" && node.is_named() {
+            text.to_string()
+        } else {
+            text.split_whitespace().collect()
+        }
+    }
+
+    /// Source text of `node` with normalized line endings and no trailing
+    /// whitespace.
+    fn verbatim(&self, node: Node) -> String {
+        self.text(node).replace("\r\n", "\n").trim_end().to_string()
+    }
+
+    fn text(&self, node: Node) -> &str {
+        &self.source[node.byte_range()]
+    }
+}
+
+/// Groups the statements and comments of a block into units. `open_row` is
+/// the row of the opening brace; a comment on that same row is returned
+/// separately so that it stays on the declaration line.
+fn units<'t>(nodes: &[Node<'t>], open_row: Option<usize>) -> (Option<Node<'t>>, Vec<Unit<'t>>) {
+    let mut header_comment = None;
+    let mut units: Vec<Unit> = Vec::new();
+    let mut comments: Vec<Node> = Vec::new();
+    let mut blank_before = false;
+    let mut last_row = open_row;
+    // Whether the previous item can take a comment on its last line.
+    let mut attachable = open_row.is_some();
+
+    for &node in nodes {
+        let row = node.start_position().row;
+        let blank = last_row.is_some_and(|last| row > last + 1);
+
+        match node.kind() {
+            "empty_statement" => {}
+            "comment" if attachable && last_row == Some(row) => {
+                match units.last_mut() {
+                    Some(unit) => unit.trailing = Some(node),
+                    None => header_comment = Some(node),
+                }
+                attachable = false;
+            }
+            kind => {
+                if blank && !comments.is_empty() {
+                    units.push(Unit {
+                        comments: std::mem::take(&mut comments),
+                        statement: None,
+                        trailing: None,
+                        blank_before,
+                    });
+                }
+                if comments.is_empty() {
+                    blank_before = blank;
+                }
+
+                if kind == "comment" {
+                    comments.push(node);
+                    attachable = false;
+                } else {
+                    units.push(Unit {
+                        comments: std::mem::take(&mut comments),
+                        statement: Some(node),
+                        trailing: None,
+                        blank_before,
+                    });
+                    attachable = true;
+                }
+            }
+        }
+        last_row = Some(node.end_position().row);
+    }
+
+    if !comments.is_empty() {
+        units.push(Unit {
+            comments,
+            statement: None,
+            trailing: None,
+            blank_before,
+        });
+    }
+    (header_comment, units)
+}
+
+/// Children of a statement, with message and enum bodies flattened so that
+/// their braces sit next to the declaration header.
+fn body_parts(node: Node) -> Vec<Node> {
+    let mut parts = Vec::new();
+    for child in node.children(&mut node.walk()) {
+        if matches!(child.kind(), "message_body" | "enum_body") {
+            parts.extend(child.children(&mut child.walk()));
+        } else {
+            parts.push(child);
+        }
+    }
+    parts
+}
+
+fn tokens<'t>(nodes: &[Node<'t>]) -> Vec<Node<'t>> {
+    fn collect<'t>(node: Node<'t>, out: &mut Vec<Node<'t>>) {
+        if node.child_count() == 0 || ATOMIC.contains(&node.kind()) {
+            out.push(node);
+            return;
+        }
+        for child in node.children(&mut node.walk()) {
+            collect(child, out);
+        }
+    }
+
+    let mut out = Vec::new();
+    for node in nodes {
+        collect(*node, &mut out);
+    }
+    out
+}
+
+fn is_token(node: Node, text: &str) -> bool {
+    !node.is_named() && node.kind() == text
+}
+
+fn has_comment(node: Node) -> bool {
+    node.kind() == "comment" || node.children(&mut node.walk()).any(has_comment)
+}
+
+/// Whether a space separates two consecutive tokens.
+fn spaced(prev: Node, next: Node) -> bool {
+    if [";", ",", ")", "]", ">", ".", "<"]
+        .iter()
+        .any(|t| is_token(next, t))
+    {
+        return false;
+    }
+    if ["(", "[", "<", ".", "-", "+"]
+        .iter()
+        .any(|t| is_token(prev, t))
+    {
+        return false;
+    }
+    // `rpc Foo(Request)`
+    !(is_token(next, "(") && prev.parent().is_some_and(|p| p.kind() == "rpc_name"))
+}
+
+#[cfg(test)]
+mod test {
+    use async_lsp::lsp_types::{Position, Range};
+    use insta::{assert_snapshot, assert_yaml_snapshot};
+
+    use super::{NativeFormatter, Style, format};
+    use crate::formatter::ProtoFormatter;
+
+    #[test]
+    fn test_format_document() {
+        let c = include_str!("input/native.proto");
+        let formatted = format(c, &Style::NATIVE, true).unwrap();
+        assert_snapshot!(formatted);
+
+        // Formatting is idempotent.
+        assert_eq!(format(&formatted, &Style::NATIVE, true).unwrap(), formatted);
+        assert!(
+            NativeFormatter
+                .format_document("native.proto", &formatted)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_format_document_range() {
+        let c = include_str!("input/native.proto");
+        let range = Range {
+            start: Position {
+                line: 10,
+                character: 0,
+            },
+            end: Position {
+                line: 13,
+                character: 0,
+            },
+        };
+        assert_yaml_snapshot!(NativeFormatter.format_document_range(&range, "native.proto", c));
+    }
+
+    #[test]
+    fn test_format_invalid_document() {
+        let c = "syntax = \"proto3\";\nmessage Foo {\n  string name = \n}\n";
+        assert!(format(c, &Style::NATIVE, true).is_none());
+        assert!(
+            NativeFormatter
+                .format_document("invalid.proto", c)
+                .is_none()
+        );
+    }
+}
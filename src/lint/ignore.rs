@@ -0,0 +1,72 @@
+//! Comments turning lint rules off for a definition, such as
+//! `// protols:ignore FIELD_LOWER_SNAKE_CASE`.
+//!
+//! `buf:lint:ignore` is understood as well, so that schemas already linted by
+//! `buf` need no new comments.
+
+use async_lsp::lsp_types::Range;
+
+use crate::document::ProtoDocument;
+use crate::model::CommentBlock;
+use crate::utils::{clean_proto_comment, to_lsp_range};
+
+/// The prefixes of the comment lines naming the rules to ignore.
+const DIRECTIVES: &[&str] = &["protols:ignore", "buf:lint:ignore"];
+
+/// Whether `rule` is ignored where it is broken at `range` in `document`,
+/// whose content is `source`: by a leading comment of the definition found
+/// there, as `buf` does. Outside definitions, such as on the package
+/// statement, the leading comments of the statement found there count.
+pub(super) fn is_ignored(document: &ProtoDocument, source: &str, range: Range, rule: &str) -> bool {
+    let innermost = document
+        .elements
+        .iter()
+        .rfind(|element| element.contains_position(range.start));
+    if let Some(element) = innermost {
+        return element
+            .meta
+            .documentation
+            .iter()
+            .any(|block: &CommentBlock| ignores(&block.text, rule));
+    }
+
+    let root = document.tree.root_node();
+    let Some(statement) = root
+        .children(&mut root.walk())
+        .find(|n| n.kind() != "comment" && to_lsp_range(*n) == range)
+    else {
+        return false;
+    };
+    // Comments on the lines right above the statement.
+    let mut comments = Vec::new();
+    let mut expected = range.start.line;
+    let mut previous = statement.prev_sibling();
+    while let Some(node) = previous.filter(|n| n.kind() == "comment") {
+        let node_range = to_lsp_range(node);
+        if node_range.end.line + 1 != expected {
+            break;
+        }
+        expected = node_range.start.line;
+        comments.push(node);
+        previous = node.prev_sibling();
+    }
+
+    comments.iter().any(|node| {
+        node.utf8_text(source.as_bytes())
+            .is_ok_and(|text| ignores(&clean_proto_comment(text), rule))
+    })
+}
+
+/// Whether a line of `comment` is a directive naming `rule`.
+fn ignores(comment: &str, rule: &str) -> bool {
+    comment.lines().any(|line| {
+        let line = line.trim();
+        DIRECTIVES.iter().any(|directive| {
+            line.strip_prefix(directive).is_some_and(|rules| {
+                rules
+                    .split(|c: char| c.is_whitespace() || c == ',')
+                    .any(|id| id == rule)
+            })
+        })
+    })
+}
@@ -0,0 +1,333 @@
+//! Lint rules checking that schemas follow the usual protobuf style, reported
+//! as warnings next to the other diagnostics.
+//!
+//! Rules are identified by the ids `buf lint` uses for the same checks, and
+//! are turned on or off one by one in the `[config.lint]` section of
+//! `protols.toml`.
+
+mod ignore;
+mod rules;
+
+use async_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Range};
+
+use crate::config::LintConfig;
+use crate::document::ProtoDocument;
+
+use self::ignore::is_ignored;
+pub(crate) use self::rules::{
+    is_pascal_case, to_lower_snake_case, to_pascal_case, to_upper_snake_case,
+};
+
+/// A single lint rule.
+struct Rule {
+    /// The id the rule is configured and reported with.
+    id: &'static str,
+    /// Whether the rule runs when the configuration does not mention it.
+    default: bool,
+    /// Returns the places of the document breaking the rule.
+    check: fn(&Context) -> Vec<Violation>,
+}
+
+/// What rules look at.
+struct Context<'a> {
+    document: &'a ProtoDocument,
+    /// The path of the document relative to its include path, if it is
+    /// under one.
+    file_name: Option<&'a str>,
+    /// The paths and packages of the other files of the directory of the
+    /// document.
+    siblings: &'a [(String, String)],
+}
+
+/// A place breaking a rule.
+struct Violation {
+    range: Range,
+    message: String,
+}
+
+/// Every rule, in the order they are run.
+const RULES: &[Rule] = &[
+    Rule {
+        id: "PACKAGE_DIRECTORY_MATCH",
+        default: true,
+        check: rules::package_directory_match,
+    },
+    Rule {
+        id: "PACKAGE_SAME_DIRECTORY",
+        default: true,
+        check: rules::package_same_directory,
+    },
+    Rule {
+        id: "MESSAGE_PASCAL_CASE",
+        default: true,
+        check: rules::message_pascal_case,
+    },
+    Rule {
+        id: "FIELD_LOWER_SNAKE_CASE",
+        default: true,
+        check: rules::field_lower_snake_case,
+    },
+    Rule {
+        id: "ONEOF_LOWER_SNAKE_CASE",
+        default: true,
+        check: rules::oneof_lower_snake_case,
+    },
+    Rule {
+        id: "ENUM_PASCAL_CASE",
+        default: true,
+        check: rules::enum_pascal_case,
+    },
+    Rule {
+        id: "ENUM_VALUE_UPPER_SNAKE_CASE",
+        default: true,
+        check: rules::enum_value_upper_snake_case,
+    },
+    Rule {
+        id: "ENUM_VALUE_PREFIX",
+        default: true,
+        check: rules::enum_value_prefix,
+    },
+    Rule {
+        id: "ENUM_ZERO_VALUE_SUFFIX",
+        default: true,
+        check: rules::enum_zero_value_suffix,
+    },
+    Rule {
+        id: "SERVICE_PASCAL_CASE",
+        default: true,
+        check: rules::service_pascal_case,
+    },
+    Rule {
+        id: "RPC_PASCAL_CASE",
+        default: true,
+        check: rules::rpc_pascal_case,
+    },
+    Rule {
+        id: "RPC_REQUEST_STANDARD_NAME",
+        default: true,
+        check: rules::rpc_request_standard_name,
+    },
+    Rule {
+        id: "RPC_RESPONSE_STANDARD_NAME",
+        default: true,
+        check: rules::rpc_response_standard_name,
+    },
+    Rule {
+        id: "COMMENT_MESSAGE",
+        default: false,
+        check: rules::comment_message,
+    },
+    Rule {
+        id: "COMMENT_FIELD",
+        default: false,
+        check: rules::comment_field,
+    },
+    Rule {
+        id: "COMMENT_ONEOF",
+        default: false,
+        check: rules::comment_oneof,
+    },
+    Rule {
+        id: "COMMENT_ENUM",
+        default: false,
+        check: rules::comment_enum,
+    },
+    Rule {
+        id: "COMMENT_ENUM_VALUE",
+        default: false,
+        check: rules::comment_enum_value,
+    },
+    Rule {
+        id: "COMMENT_SERVICE",
+        default: false,
+        check: rules::comment_service,
+    },
+    Rule {
+        id: "COMMENT_RPC",
+        default: false,
+        check: rules::comment_rpc,
+    },
+];
+
+/// Whether `config` turns on the rule `id`.
+pub(crate) fn is_rule_enabled(config: &LintConfig, id: &str) -> bool {
+    RULES
+        .iter()
+        .any(|rule| rule.id == id && config.is_enabled(rule.id, rule.default))
+}
+
+/// Runs the rules `config` turns on over `document`, whose content is
+/// `source` and whose path relative to its include path is `file_name`.
+/// `siblings` lists the path and package of the other files in its directory.
+/// What breaks the rules is reported as warnings with the rule id as code,
+/// unless a comment ignores the rule there. Nothing is reported while the
+/// document has syntax errors.
+pub fn lint(
+    document: &ProtoDocument,
+    source: &str,
+    file_name: Option<&str>,
+    siblings: &[(String, String)],
+    config: &LintConfig,
+) -> Vec<Diagnostic> {
+    if !config.enabled || document.tree.root_node().has_error() {
+        return vec![];
+    }
+    let context = Context {
+        document,
+        file_name,
+        siblings,
+    };
+
+    let mut diagnostics = Vec::new();
+    for rule in RULES
+        .iter()
+        .filter(|rule| config.is_enabled(rule.id, rule.default))
+    {
+        diagnostics.extend(
+            (rule.check)(&context)
+                .into_iter()
+                .filter(|violation| !is_ignored(document, source, violation.range, rule.id))
+                .map(|violation| Diagnostic {
+                    range: violation.range,
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    code: Some(NumberOrString::String(rule.id.to_string())),
+                    source: Some(String::from("protols")),
+                    message: violation.message,
+                    ..Default::default()
+                }),
+        );
+    }
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.range.start, diagnostic.range.end));
+    diagnostics
+}
+
+#[cfg(test)]
+mod test {
+    use async_lsp::lsp_types::{NumberOrString, Url};
+    use insta::assert_yaml_snapshot;
+
+    use crate::config::LintConfig;
+    use crate::document::ProtoParser;
+    use crate::utils::compile_test_query;
+
+    use super::{RULES, lint};
+
+    fn config(rules: &[(&str, bool)]) -> LintConfig {
+        LintConfig {
+            enabled: true,
+            rules: rules
+                .iter()
+                .map(|(id, on)| ((*id).to_string(), *on))
+                .collect(),
+        }
+    }
+
+    fn codes(diagnostics: &[async_lsp::lsp_types::Diagnostic]) -> Vec<String> {
+        diagnostics
+            .iter()
+            .filter_map(|d| match &d.code {
+                Some(NumberOrString::String(code)) => Some(code.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lint() {
+        let url: Url = "file://foo/bar.proto".parse().unwrap();
+        let contents = include_str!("input/style.proto");
+        let document = ProtoParser::new()
+            .parse(url, contents, &compile_test_query())
+            .unwrap();
+
+        let all: Vec<(&str, bool)> = RULES.iter().map(|rule| (rule.id, true)).collect();
+        assert_yaml_snapshot!(lint(
+            &document,
+            contents,
+            Some("style.proto"),
+            &[],
+            &config(&all)
+        ));
+
+        // Comments are not required by default, and a rule can be turned off.
+        let diagnostics = lint(
+            &document,
+            contents,
+            Some("shop/v1/style.proto"),
+            &[],
+            &config(&[("ENUM_VALUE_PREFIX", false)]),
+        );
+        let codes = codes(&diagnostics);
+        assert!(!codes.iter().any(|code| code.starts_with("COMMENT_")));
+        assert!(!codes.contains(&String::from("ENUM_VALUE_PREFIX")));
+        assert!(!codes.contains(&String::from("PACKAGE_DIRECTORY_MATCH")));
+        assert!(codes.contains(&String::from("ENUM_ZERO_VALUE_SUFFIX")));
+
+        assert!(lint(&document, contents, None, &[], &LintConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_lint_siblings() {
+        let url: Url = "file://foo/bar.proto".parse().unwrap();
+        let contents = include_str!("input/clean.proto");
+        let document = ProtoParser::new()
+            .parse(url, contents, &compile_test_query())
+            .unwrap();
+        let siblings = [
+            (String::from("shop/v1/same.proto"), String::from("shop.v1")),
+            (String::from("shop/v1/other.proto"), String::from("shop.v2")),
+        ];
+
+        let diagnostics = lint(
+            &document,
+            contents,
+            Some("shop/v1/clean.proto"),
+            &siblings,
+            &config(&[]),
+        );
+        assert_eq!(codes(&diagnostics), ["PACKAGE_SAME_DIRECTORY"]);
+        assert_eq!(
+            diagnostics[0].message,
+            "Files in the same directory should have the same package, but \"shop/v1/other.proto\" has package \"shop.v2\"."
+        );
+    }
+
+    #[test]
+    fn test_lint_ignore() {
+        let url: Url = "file://foo/bar.proto".parse().unwrap();
+        let contents = include_str!("input/ignore.proto");
+        let document = ProtoParser::new()
+            .parse(url, contents, &compile_test_query())
+            .unwrap();
+
+        let all: Vec<(&str, bool)> = RULES.iter().map(|rule| (rule.id, true)).collect();
+        assert_yaml_snapshot!(lint(
+            &document,
+            contents,
+            Some("ignore.proto"),
+            &[],
+            &config(&all)
+        ));
+    }
+
+    #[test]
+    fn test_lint_clean() {
+        let url: Url = "file://foo/bar.proto".parse().unwrap();
+        let contents = include_str!("input/clean.proto");
+        let document = ProtoParser::new()
+            .parse(url, contents, &compile_test_query())
+            .unwrap();
+
+        let all: Vec<(&str, bool)> = RULES.iter().map(|rule| (rule.id, true)).collect();
+        assert!(
+            lint(
+                &document,
+                contents,
+                Some("shop/v1/clean.proto"),
+                &[],
+                &config(&all)
+            )
+            .is_empty()
+        );
+    }
+}
@@ -0,0 +1,424 @@
+//! The checks of the lint rules.
+
+use async_lsp::lsp_types::Range;
+
+use crate::document::ProtoDocument;
+use crate::model::{ElementKind, ModelElement, TypeReference};
+use crate::utils::{to_lsp_range, trailing_segment};
+
+use super::{Context, Violation};
+
+pub(super) fn package_directory_match(context: &Context) -> Vec<Violation> {
+    let document = context.document;
+    let Some(file_name) = context.file_name else {
+        return vec![];
+    };
+    if document.package.is_empty() {
+        return vec![];
+    }
+    let expected = document.package.replace('.', "/");
+    let directory = file_name.rsplit_once('/').map_or("", |(dir, _)| dir);
+    if directory == expected {
+        return vec![];
+    }
+
+    let Some(range) = package_range(document) else {
+        return vec![];
+    };
+    let directory = if directory.is_empty() { "." } else { directory };
+    vec![Violation {
+        range,
+        message: format!(
+            "Files with package \"{}\" must be within a directory \"{expected}\" relative to their include path, but this file is in \"{directory}\".",
+            document.package
+        ),
+    }]
+}
+
+pub(super) fn package_same_directory(context: &Context) -> Vec<Violation> {
+    let document = context.document;
+    if document.package.is_empty() {
+        return vec![];
+    }
+    let Some((name, package)) = context
+        .siblings
+        .iter()
+        .filter(|(_, package)| !package.is_empty() && *package != document.package)
+        .min()
+    else {
+        return vec![];
+    };
+    let Some(range) = package_range(document) else {
+        return vec![];
+    };
+    vec![Violation {
+        range,
+        message: format!(
+            "Files in the same directory should have the same package, but \"{name}\" has package \"{package}\"."
+        ),
+    }]
+}
+
+pub(super) fn message_pascal_case(context: &Context) -> Vec<Violation> {
+    pascal_case(context, "Message", |kind| {
+        matches!(kind, ElementKind::Message { .. })
+    })
+}
+
+pub(super) fn enum_pascal_case(context: &Context) -> Vec<Violation> {
+    pascal_case(context, "Enum", |kind| {
+        matches!(kind, ElementKind::Enum { .. })
+    })
+}
+
+pub(super) fn service_pascal_case(context: &Context) -> Vec<Violation> {
+    pascal_case(context, "Service", |kind| {
+        matches!(kind, ElementKind::Service { .. })
+    })
+}
+
+pub(super) fn rpc_pascal_case(context: &Context) -> Vec<Violation> {
+    pascal_case(context, "RPC", |kind| {
+        matches!(kind, ElementKind::Rpc { .. })
+    })
+}
+
+pub(super) fn field_lower_snake_case(context: &Context) -> Vec<Violation> {
+    lower_snake_case(context, "Field", |kind| {
+        matches!(
+            kind,
+            ElementKind::Field { .. }
+                | ElementKind::MapField { .. }
+                | ElementKind::OneofField { .. }
+        )
+    })
+}
+
+pub(super) fn oneof_lower_snake_case(context: &Context) -> Vec<Violation> {
+    lower_snake_case(context, "Oneof", |kind| {
+        matches!(kind, ElementKind::Oneof { .. })
+    })
+}
+
+pub(super) fn enum_value_upper_snake_case(context: &Context) -> Vec<Violation> {
+    elements(context, |kind| {
+        matches!(kind, ElementKind::EnumValue { .. })
+    })
+    .filter(|value| !is_upper_snake_case(&value.meta.name))
+    .map(|value| Violation {
+        range: value.meta.selection_range,
+        message: format!(
+            "Enum value name \"{}\" should be UPPER_SNAKE_CASE, such as \"{}\".",
+            value.meta.name,
+            to_upper_snake_case(&value.meta.name)
+        ),
+    })
+    .collect()
+}
+
+pub(super) fn enum_value_prefix(context: &Context) -> Vec<Violation> {
+    elements(context, |kind| {
+        matches!(kind, ElementKind::EnumValue { .. })
+    })
+    .filter_map(|value| {
+        let parent = context.document.elements.get(value.parent_id?)?;
+        let prefix = format!("{}_", to_upper_snake_case(&parent.meta.name));
+        (!value.meta.name.starts_with(&prefix)).then(|| Violation {
+            range: value.meta.selection_range,
+            message: format!(
+                "Enum value name \"{}\" should be prefixed with \"{prefix}\".",
+                value.meta.name
+            ),
+        })
+    })
+    .collect()
+}
+
+pub(super) fn enum_zero_value_suffix(context: &Context) -> Vec<Violation> {
+    elements(context, |kind| {
+        matches!(kind, ElementKind::EnumValue { number: 0, .. })
+    })
+    .filter(|value| !value.meta.name.ends_with("_UNSPECIFIED"))
+    .map(|value| Violation {
+        range: value.meta.selection_range,
+        message: format!(
+            "Enum zero value name \"{}\" should be suffixed with \"_UNSPECIFIED\".",
+            value.meta.name
+        ),
+    })
+    .collect()
+}
+
+pub(super) fn rpc_request_standard_name(context: &Context) -> Vec<Violation> {
+    rpc_standard_name(context, "request", "Request", |kind| match kind {
+        ElementKind::Rpc {
+            request_type_ref, ..
+        } => Some(request_type_ref),
+        _ => None,
+    })
+}
+
+pub(super) fn rpc_response_standard_name(context: &Context) -> Vec<Violation> {
+    rpc_standard_name(context, "response", "Response", |kind| match kind {
+        ElementKind::Rpc {
+            response_type_ref, ..
+        } => Some(response_type_ref),
+        _ => None,
+    })
+}
+
+pub(super) fn comment_message(context: &Context) -> Vec<Violation> {
+    comment(context, "Message", |kind| {
+        matches!(kind, ElementKind::Message { .. })
+    })
+}
+
+pub(super) fn comment_field(context: &Context) -> Vec<Violation> {
+    comment(context, "Field", |kind| {
+        matches!(
+            kind,
+            ElementKind::Field { .. }
+                | ElementKind::MapField { .. }
+                | ElementKind::OneofField { .. }
+        )
+    })
+}
+
+pub(super) fn comment_oneof(context: &Context) -> Vec<Violation> {
+    comment(context, "Oneof", |kind| {
+        matches!(kind, ElementKind::Oneof { .. })
+    })
+}
+
+pub(super) fn comment_enum(context: &Context) -> Vec<Violation> {
+    comment(context, "Enum", |kind| {
+        matches!(kind, ElementKind::Enum { .. })
+    })
+}
+
+pub(super) fn comment_enum_value(context: &Context) -> Vec<Violation> {
+    comment(context, "Enum value", |kind| {
+        matches!(kind, ElementKind::EnumValue { .. })
+    })
+}
+
+pub(super) fn comment_service(context: &Context) -> Vec<Violation> {
+    comment(context, "Service", |kind| {
+        matches!(kind, ElementKind::Service { .. })
+    })
+}
+
+pub(super) fn comment_rpc(context: &Context) -> Vec<Violation> {
+    comment(context, "RPC", |kind| {
+        matches!(kind, ElementKind::Rpc { .. })
+    })
+}
+
+/// The range of the package statement of `document`.
+fn package_range(document: &ProtoDocument) -> Option<Range> {
+    let root = document.tree.root_node();
+    root.children(&mut root.walk())
+        .find(|n| n.kind() == "package")
+        .map(to_lsp_range)
+}
+
+/// The elements of the document whose kind matches `filter`.
+fn elements<'a>(
+    context: &'a Context,
+    filter: fn(&ElementKind) -> bool,
+) -> impl Iterator<Item = &'a ModelElement> {
+    context
+        .document
+        .elements
+        .iter()
+        .filter(move |element| filter(&element.kind))
+}
+
+fn pascal_case(context: &Context, label: &str, filter: fn(&ElementKind) -> bool) -> Vec<Violation> {
+    elements(context, filter)
+        .filter(|element| !is_pascal_case(&element.meta.name))
+        .map(|element| Violation {
+            range: element.meta.selection_range,
+            message: format!(
+                "{label} name \"{}\" should be PascalCase, such as \"{}\".",
+                element.meta.name,
+                to_pascal_case(&element.meta.name)
+            ),
+        })
+        .collect()
+}
+
+fn lower_snake_case(
+    context: &Context,
+    label: &str,
+    filter: fn(&ElementKind) -> bool,
+) -> Vec<Violation> {
+    elements(context, filter)
+        .filter(|element| to_lower_snake_case(&element.meta.name) != element.meta.name)
+        .map(|element| Violation {
+            range: element.meta.selection_range,
+            message: format!(
+                "{label} name \"{}\" should be lower_snake_case, such as \"{}\".",
+                element.meta.name,
+                to_lower_snake_case(&element.meta.name)
+            ),
+        })
+        .collect()
+}
+
+/// Requires the `side` type of every rpc, which `type_ref` returns, to be
+/// named `<Rpc><suffix>` or `<Service><Rpc><suffix>`.
+fn rpc_standard_name(
+    context: &Context,
+    side: &str,
+    suffix: &str,
+    type_ref: fn(&ElementKind) -> Option<&TypeReference>,
+) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for rpc in &context.document.elements {
+        let Some(type_ref) = type_ref(&rpc.kind) else {
+            continue;
+        };
+        let service = rpc
+            .parent_id
+            .and_then(|id| context.document.elements.get(id))
+            .map(|service| service.meta.name.as_str())
+            .unwrap_or_default();
+
+        let name = trailing_segment(&type_ref.name);
+        let rpc_name = if is_pascal_case(&rpc.meta.name) {
+            rpc.meta.name.clone()
+        } else {
+            to_pascal_case(&rpc.meta.name)
+        };
+        let short = format!("{rpc_name}{suffix}");
+        let long = format!("{service}{short}");
+        if name != short && name != long {
+            violations.push(Violation {
+                range: type_ref.range,
+                message: format!(
+                    "RPC {side} type \"{name}\" should be named \"{short}\" or \"{long}\"."
+                ),
+            });
+        }
+    }
+    violations
+}
+
+fn comment(context: &Context, label: &str, filter: fn(&ElementKind) -> bool) -> Vec<Violation> {
+    elements(context, filter)
+        .filter(|element| {
+            element
+                .meta
+                .documentation
+                .iter()
+                .all(|block| block.text.trim().is_empty())
+        })
+        .map(|element| Violation {
+            range: element.meta.selection_range,
+            message: format!(
+                "{label} \"{}\" should have a non-empty comment for documentation.",
+                element.meta.name
+            ),
+        })
+        .collect()
+}
+
+pub(crate) fn is_pascal_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Whether `name` only has upper case letters, digits and underscores, and
+/// starts with a letter. Digits do not split words, so `STATUS_4XX` and
+/// `TYPE_A1B` are `UPPER_SNAKE_CASE`.
+fn is_upper_snake_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Splits `name` into its lower case words, at underscores and where the
+/// case changes, such as `HTTPServer2Name` into `http`, `server2` and `name`.
+fn words(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        if c.is_uppercase() && !word.is_empty() {
+            let previous = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if previous.is_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_uppercase() && next_lower)
+            {
+                words.push(std::mem::take(&mut word));
+            }
+        }
+        word.extend(c.to_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+pub(crate) fn to_lower_snake_case(name: &str) -> String {
+    words(name).join("_")
+}
+
+pub(crate) fn to_upper_snake_case(name: &str) -> String {
+    if is_upper_snake_case(name) {
+        return name.to_owned();
+    }
+    to_lower_snake_case(name).to_uppercase()
+}
+
+pub(crate) fn to_pascal_case(name: &str) -> String {
+    words(name)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or_else(String::new, |first| {
+                first.to_uppercase().chain(chars).collect()
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        is_pascal_case, is_upper_snake_case, to_lower_snake_case, to_pascal_case,
+        to_upper_snake_case,
+    };
+
+    #[test]
+    fn test_case_conversions() {
+        assert_eq!(to_lower_snake_case("fooBar"), "foo_bar");
+        assert_eq!(to_lower_snake_case("HTTPServer2Name"), "http_server2_name");
+        assert_eq!(to_lower_snake_case("foo__bar_"), "foo_bar");
+        assert_eq!(to_lower_snake_case("foo_bar_2"), "foo_bar_2");
+        assert_eq!(to_upper_snake_case("OrderStatus"), "ORDER_STATUS");
+        assert_eq!(to_upper_snake_case("STATUS_OK"), "STATUS_OK");
+        assert_eq!(to_upper_snake_case("STATUS_4XX"), "STATUS_4XX");
+        assert_eq!(to_upper_snake_case("TYPE_A1B"), "TYPE_A1B");
+        assert_eq!(to_upper_snake_case("status4xx"), "STATUS4XX");
+        assert!(is_upper_snake_case("STATUS_4XX"));
+        assert!(is_upper_snake_case("TYPE_A1B"));
+        assert!(!is_upper_snake_case("4XX_STATUS"));
+        assert!(!is_upper_snake_case("Status_OK"));
+        assert_eq!(to_pascal_case("order_item"), "OrderItem");
+        assert_eq!(to_pascal_case("orderItem"), "OrderItem");
+        assert!(is_pascal_case("HTTPServer"));
+        assert!(!is_pascal_case("Order_Item"));
+        assert!(!is_pascal_case("orderItem"));
+    }
+}
@@ -1,30 +1,60 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::ops::ControlFlow;
+use std::time::Duration;
 use std::{fs::read_to_string, path::PathBuf};
 use tracing::{error, info, warn};
 
+use async_lsp::lsp_types::notification::{DidChangeWatchedFiles, Notification};
 use async_lsp::lsp_types::{
-    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
-    CreateFilesParams, DeleteFilesParams, DidChangeTextDocumentParams, DidOpenTextDocumentParams,
-    DidSaveTextDocumentParams, DocumentFormattingParams, DocumentRangeFormattingParams,
-    DocumentSymbolParams, DocumentSymbolResponse, Documentation, FileOperationFilter,
-    FileOperationPattern, FileOperationPatternKind, FileOperationRegistrationOptions,
-    GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverParams, HoverProviderCapability,
-    InitializeParams, InitializeResult, Location, MarkupContent, MarkupKind, OneOf,
-    PrepareRenameResponse, ReferenceParams, RenameFilesParams, RenameOptions, RenameParams,
-    ServerCapabilities, ServerInfo, SetTraceParams, TextDocumentPositionParams,
-    TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Url, WorkDoneProgressOptions,
+    AnnotatedTextEdit, CallHierarchyIncomingCall, CallHierarchyIncomingCallsParams,
+    CallHierarchyItem, CallHierarchyOutgoingCall, CallHierarchyOutgoingCallsParams,
+    CallHierarchyPrepareParams, CallHierarchyServerCapability, ChangeAnnotation, CodeAction,
+    CodeActionKind, CodeActionOptions, CodeActionOrCommand, CodeActionParams,
+    CodeActionProviderCapability, CodeActionResponse, CompletionItem, CompletionItemKind,
+    CompletionOptions, CompletionParams, CompletionResponse, CreateFilesParams,
+    DeclarationCapability, DeleteFilesParams, Diagnostic, DidChangeTextDocumentParams,
+    DidChangeWatchedFilesParams, DidChangeWatchedFilesRegistrationOptions,
+    DidOpenTextDocumentParams, DidSaveTextDocumentParams, DocumentChanges,
+    DocumentFormattingParams, DocumentHighlight, DocumentHighlightParams,
+    DocumentOnTypeFormattingOptions, DocumentOnTypeFormattingParams, DocumentRangeFormattingParams,
+    DocumentSymbolParams, DocumentSymbolResponse, Documentation, ExecuteCommandOptions,
+    ExecuteCommandParams, FileOperationFilter, FileOperationPattern, FileOperationPatternKind,
+    FileOperationRegistrationOptions, FileSystemWatcher, FoldingRange, FoldingRangeParams,
+    FoldingRangeProviderCapability, GlobPattern, GotoDefinitionParams, GotoDefinitionResponse,
+    Hover, HoverParams, HoverProviderCapability, InitializeParams, InitializeResult,
+    InitializedParams, InlayHint, InlayHintParams, Location, MarkupContent, MarkupKind,
+    NumberOrString, OneOf, OptionalVersionedTextDocumentIdentifier, Position,
+    PrepareRenameResponse, PublishDiagnosticsParams, ReferenceParams, Registration,
+    RegistrationParams, RenameFilesParams, RenameOptions, RenameParams, SelectionRange,
+    SelectionRangeParams, SelectionRangeProviderCapability, ServerCapabilities, ServerInfo,
+    SetTraceParams, TextDocumentEdit, TextDocumentPositionParams, TextDocumentSyncCapability,
+    TextDocumentSyncKind, TextEdit, TypeDefinitionProviderCapability, Url, WorkDoneProgressOptions,
     WorkspaceEdit, WorkspaceFileOperationsServerCapabilities, WorkspaceFoldersServerCapabilities,
     WorkspaceServerCapabilities, WorkspaceSymbolParams, WorkspaceSymbolResponse,
 };
-use async_lsp::{Error, LanguageClient, ResponseError};
+use async_lsp::{Error, ErrorCode, LanguageClient, ResponseError};
 use futures::future::BoxFuture;
 use serde_json::Value;
 
+use crate::baseline::BaselineLoaded;
+use crate::config::{Config, DiagnosticsEngine};
+use crate::descriptor::encode_file_descriptor_set;
 use crate::formatter::ProtoFormatter;
+use crate::protoc::{ProtocDiagnostic, ProtocFinished, ProtocRun};
 use crate::server::ProtoLanguageServer;
+use crate::state::{RenameGroups, is_external_document};
+use crate::utils::is_position_inside_range;
 use crate::{docs, log};
 
+/// Change annotation of the edits renaming the symbol the user renamed.
+const PRIMARY_RENAME_ANNOTATION: &str = "primaryRename";
+
+/// Change annotation of the edits of the rpc/request/response chain.
+const CONVENTION_CHAIN_ANNOTATION: &str = "conventionChain";
+
+/// Command writing the `FileDescriptorSet` of a file and of its imports.
+pub(super) const EXPORT_DESCRIPTOR_SET_COMMAND: &str = "protols.exportDescriptorSet";
+
 impl ProtoLanguageServer {
     pub(super) fn initialize(
         &mut self,
@@ -65,6 +95,20 @@ impl ProtoLanguageServer {
             filters: file_operation_filers.clone(),
         };
 
+        // Moving a directory moves the proto files inside it.
+        let mut rename_filters = file_operation_filers.clone();
+        rename_filters.push(FileOperationFilter {
+            scheme: Some(String::from("file")),
+            pattern: FileOperationPattern {
+                glob: String::from("**"),
+                matches: Some(FileOperationPatternKind::Folder),
+                ..Default::default()
+            },
+        });
+        let rename_registration_option = FileOperationRegistrationOptions {
+            filters: rename_filters,
+        };
+
         let mut workspace_capabilities = None;
 
         if let Some(folders) = params.workspace_folders {
@@ -80,7 +124,8 @@ impl ProtoLanguageServer {
 
                 file_operations: Some(WorkspaceFileOperationsServerCapabilities {
                     did_create: Some(file_registration_option.clone()),
-                    did_rename: Some(file_registration_option.clone()),
+                    did_rename: Some(rename_registration_option.clone()),
+                    will_rename: Some(rename_registration_option),
                     did_delete: Some(file_registration_option.clone()),
                     ..Default::default()
                 }),
@@ -90,6 +135,22 @@ impl ProtoLanguageServer {
             self.configs.no_workspace_mode();
         }
 
+        self.change_annotations = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.workspace_edit.as_ref())
+            .is_some_and(|edit| {
+                edit.document_changes == Some(true) && edit.change_annotation_support.is_some()
+            });
+        self.watched_files = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.did_change_watched_files)
+            .and_then(|watched| watched.dynamic_registration)
+            .unwrap_or_default();
+
         let mut rename_provider: OneOf<bool, RenameOptions> = OneOf::Left(true);
 
         if params
@@ -120,7 +181,31 @@ impl ProtoLanguageServer {
                 rename_provider: Some(rename_provider),
                 document_formatting_provider: Some(OneOf::Left(true)),
                 document_range_formatting_provider: Some(OneOf::Left(true)),
+                document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions {
+                    first_trigger_character: String::from("}"),
+                    more_trigger_character: Some(vec![String::from(";"), String::from("=")]),
+                }),
+                code_action_provider: Some(CodeActionProviderCapability::Options(
+                    CodeActionOptions {
+                        code_action_kinds: Some(vec![
+                            CodeActionKind::QUICKFIX,
+                            CodeActionKind::SOURCE_ORGANIZE_IMPORTS,
+                        ]),
+                        ..Default::default()
+                    },
+                )),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: vec![EXPORT_DESCRIPTOR_SET_COMMAND.to_string()],
+                    ..Default::default()
+                }),
                 references_provider: Some(OneOf::Left(true)),
+                inlay_hint_provider: Some(OneOf::Left(true)),
+                type_definition_provider: Some(TypeDefinitionProviderCapability::Simple(true)),
+                declaration_provider: Some(DeclarationCapability::Simple(true)),
+                folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+                selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
+                document_highlight_provider: Some(OneOf::Left(true)),
+                call_hierarchy_provider: Some(CallHierarchyServerCapability::Simple(true)),
 
                 ..ServerCapabilities::default()
             },
@@ -238,10 +323,29 @@ impl ProtoLanguageServer {
             error!(uri=%uri, "failed to get document");
             return Box::pin(async move { Ok(None) });
         };
+        let Some(range) = document.can_rename(pos) else {
+            return Box::pin(async move { Ok(None) });
+        };
 
-        let response = document.can_rename(pos).map(PrepareRenameResponse::Range);
+        // Symbols of vendored or out-of-workspace files are never renamed, as
+        // their declaration could not be edited.
+        let declaration = if self.package_at(&uri, pos).is_some() {
+            Some(uri.clone())
+        } else {
+            self.state
+                .resolve_target_fqn(&uri, pos)
+                .and_then(|fqn| self.state.declaration_for_fqn(&fqn))
+                .map(|(decl_uri, _)| decl_uri)
+        };
+        let result = match declaration {
+            Some(decl_uri) if !self.is_renamable(&uri, &decl_uri) => Err(ResponseError::new(
+                ErrorCode::REQUEST_FAILED,
+                format!("Cannot rename a symbol declared outside of the workspace, in {decl_uri}."),
+            )),
+            _ => Ok(Some(PrepareRenameResponse::Range(range))),
+        };
 
-        Box::pin(async move { Ok(response) })
+        Box::pin(async move { result })
     }
 
     pub(super) fn rename(
@@ -254,67 +358,161 @@ impl ProtoLanguageServer {
 
         let ipath = self.configs.get_include_paths(&uri).unwrap_or_default();
 
+        // Renaming the package statement renames the whole package: every file
+        // declaring it and every reference qualified with it.
+        let groups = if let Some(package) = self.package_at(&uri, pos) {
+            self.state
+                .validate_package_rename(&package, &new_name)
+                .map(|()| {
+                    let edits = self.state.rename_package(&package, &new_name);
+                    ((edits.into_iter().collect(), HashMap::new()), None)
+                })
+                .map_err(|message| ResponseError::new(ErrorCode::INVALID_PARAMS, message))
+        } else {
+            self.rename_symbol(&uri, pos, &new_name, &ipath)
+        };
+        let ((primary, chain), warning) = match groups {
+            Ok(groups) => groups,
+            Err(e) => return Box::pin(async move { Err(e) }),
+        };
+
+        // Only produce edits inside the current workspace. Anything outside it
+        // (e.g. vendored dependency files) is dropped, so renaming never leaks
+        // into external code.
+        let keep = |edits: HashMap<Url, Vec<TextEdit>>| -> HashMap<_, _> {
+            edits
+                .into_iter()
+                .filter(|(edit_uri, _)| self.is_renamable(&uri, edit_uri))
+                .collect()
+        };
+        let (primary, chain) = (keep(primary), keep(chain));
+
+        // Renames reaching other files, chained renames and renames changing
+        // the JSON encoding are previewed before being applied, by clients
+        // supporting it.
+        let spans_files = primary.keys().chain(chain.keys()).any(|u| *u != uri);
+        let response = if primary.is_empty() && chain.is_empty() {
+            None
+        } else if self.change_annotations && (spans_files || !chain.is_empty() || warning.is_some())
+        {
+            Some(annotated_rename_edit(
+                &new_name,
+                primary,
+                chain,
+                warning.as_deref(),
+            ))
+        } else {
+            let mut all_edits = primary;
+            for (u, e) in chain {
+                all_edits.entry(u).or_default().extend(e);
+            }
+            Some(WorkspaceEdit {
+                changes: Some(all_edits),
+                ..Default::default()
+            })
+        };
+
+        Box::pin(async move { Ok(response) })
+    }
+
+    /// The package of `uri` when `pos` is on the name of its `package`
+    /// statement.
+    fn package_at(&self, uri: &Url, pos: Position) -> Option<String> {
+        let document = self.state.get_document(uri)?;
+        document
+            .package_name_range()
+            .filter(|range| is_position_inside_range(pos, *range))
+            .map(|_| document.package.clone())
+    }
+
+    /// Whether a rename started in `origin` may edit `uri`: it must be in the
+    /// workspace of `origin`, and not a vendored well-known file.
+    fn is_renamable(&self, origin: &Url, uri: &Url) -> bool {
+        let workspace_root = self
+            .configs
+            .get_workspace_for_uri(origin)
+            .and_then(|workspace| workspace.to_file_path().ok());
+        !is_external_document(uri)
+            && workspace_root
+                .as_ref()
+                .is_none_or(|root| uri.to_file_path().is_ok_and(|p| p.starts_with(root)))
+    }
+
+    /// The edits renaming the symbol at `pos` in `uri` to `new_name`, from its
+    /// declaration, returned as the primary edits and, separately, the edits
+    /// of the rpc/request/response chain, with a warning when the rename
+    /// changes the JSON encoding. Returns the reason the symbol cannot be
+    /// renamed otherwise.
+    fn rename_symbol(
+        &mut self,
+        uri: &Url,
+        pos: Position,
+        new_name: &str,
+        ipath: &[PathBuf],
+    ) -> Result<(RenameGroups, Option<String>), ResponseError> {
         // Resolve the symbol under the cursor directly from the metamodel,
         // using its position (like hover / go-to-definition). This handles both
         // declaration sites and reference sites (pivoting to the referenced
         // declaration) without any string-based identifier reconstruction.
-        let Some(target_fqn) = self.state.resolve_target_fqn(&uri, pos) else {
+        let Some(target_fqn) = self.state.resolve_target_fqn(uri, pos) else {
             error!(uri=%uri, "failed to resolve target fqn for rename");
-            return Box::pin(async move { Ok(None) });
+            return Err(ResponseError::new(
+                ErrorCode::REQUEST_FAILED,
+                "No symbol to rename at this position.",
+            ));
         };
         let Some((decl_uri, decl_pos)) = self.state.declaration_for_fqn(&target_fqn) else {
             error!(fqn=%target_fqn, "failed to locate declaration for rename");
-            return Box::pin(async move { Ok(None) });
+            return Err(ResponseError::new(
+                ErrorCode::REQUEST_FAILED,
+                format!("Cannot find the declaration of \"{target_fqn}\"."),
+            ));
         };
+        if !self.is_renamable(uri, &decl_uri) {
+            return Err(ResponseError::new(
+                ErrorCode::REQUEST_FAILED,
+                format!("Cannot rename a symbol declared outside of the workspace, in {decl_uri}."),
+            ));
+        }
+        // Naming conventions are enforced as the workspace's `[config.lint]`
+        // rules are; without a config they are not.
+        let lint = self
+            .configs
+            .get_config_for_uri(uri)
+            .map(|c| c.config.lint.clone())
+            .unwrap_or_default();
+        self.state
+            .validate_rename(&target_fqn, new_name, &lint)
+            .map_err(|message| ResponseError::new(ErrorCode::INVALID_PARAMS, message))?;
 
         // The rpc/request/response chain rename is opt-in via the workspace's
         // `[config.rename]` settings; without a config it stays off.
         let chain_rpc_request_response = self
             .configs
-            .get_config_for_uri(&uri)
+            .get_config_for_uri(uri)
             .is_some_and(|c| c.config.rename.chain_rpc_request_response);
 
         let ops = self.state.compute_rename_ops(
             &decl_uri,
             decl_pos,
-            &new_name,
-            &ipath,
+            new_name,
+            ipath,
             chain_rpc_request_response,
         );
-        let Some(all_edits) = self.state.apply_rename_ops(&ops) else {
+        self.state
+            .validate_chain_renames(&ops, &lint)
+            .map_err(|message| ResponseError::new(ErrorCode::INVALID_PARAMS, message))?;
+        let Some(groups) = self.state.apply_rename_ops_grouped(&ops) else {
             error!(uri=%decl_uri, "failed to apply primary rename");
-            return Box::pin(async move { Ok(None) });
+            return Err(ResponseError::new(
+                ErrorCode::REQUEST_FAILED,
+                format!("Cannot rename \"{target_fqn}\"."),
+            ));
         };
 
-        // Only produce edits inside the current workspace. Anything outside it
-        // (e.g. vendored dependency files) is dropped, so renaming never leaks
-        // into external code.
-        let workspace_root = self
-            .configs
-            .get_workspace_for_uri(&uri)
-            .and_then(|workspace| workspace.to_file_path().ok());
-        let all_edits: HashMap<_, _> = all_edits
-            .into_iter()
-            .filter(|(edit_uri, _)| {
-                workspace_root.as_ref().is_none_or(|root| {
-                    edit_uri
-                        .to_file_path()
-                        .ok()
-                        .is_some_and(|p| p.starts_with(root))
-                })
-            })
-            .collect();
-
-        let response = if all_edits.is_empty() {
-            None
-        } else {
-            Some(WorkspaceEdit {
-                changes: Some(all_edits),
-                ..Default::default()
-            })
-        };
-
-        Box::pin(async move { Ok(response) })
+        // Renaming fields and enum values is safe on the wire but not in JSON.
+        let warning = self.state.rename_json_warning(&target_fqn, new_name);
+        Ok((groups, warning))
     }
 
     pub(super) fn references(
@@ -362,6 +560,109 @@ impl ProtoLanguageServer {
         Box::pin(async move { Ok(response) })
     }
 
+    pub(super) fn inlay_hint(
+        &mut self,
+        params: InlayHintParams,
+    ) -> BoxFuture<'static, Result<Option<Vec<InlayHint>>, ResponseError>> {
+        let uri = params.text_document.uri;
+        let hints = self.state.inlay_hints(&uri, params.range);
+
+        Box::pin(async move { Ok(Some(hints)) })
+    }
+
+    pub(super) fn type_definition(
+        &mut self,
+        param: GotoDefinitionParams,
+    ) -> BoxFuture<'static, Result<Option<GotoDefinitionResponse>, ResponseError>> {
+        let uri = param.text_document_position_params.text_document.uri;
+        let pos = param.text_document_position_params.position;
+
+        let locations = self.state.type_definition(&uri, pos);
+
+        let response = match locations.len() {
+            0 => None,
+            1 => Some(GotoDefinitionResponse::Scalar(locations[0].clone())),
+            2.. => Some(GotoDefinitionResponse::Array(locations)),
+        };
+
+        Box::pin(async move { Ok(response) })
+    }
+
+    pub(super) fn declaration(
+        &mut self,
+        param: GotoDefinitionParams,
+    ) -> BoxFuture<'static, Result<Option<GotoDefinitionResponse>, ResponseError>> {
+        let uri = param.text_document_position_params.text_document.uri;
+        let pos = param.text_document_position_params.position;
+
+        let ipath = self.configs.get_include_paths(&uri).unwrap_or_default();
+        let locations = self.state.declaration(&uri, pos, &ipath);
+
+        let response = match locations.len() {
+            0 => None,
+            1 => Some(GotoDefinitionResponse::Scalar(locations[0].clone())),
+            2.. => Some(GotoDefinitionResponse::Array(locations)),
+        };
+
+        Box::pin(async move { Ok(response) })
+    }
+
+    pub(super) fn document_highlight(
+        &mut self,
+        params: DocumentHighlightParams,
+    ) -> BoxFuture<'static, Result<Option<Vec<DocumentHighlight>>, ResponseError>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let pos = params.text_document_position_params.position;
+
+        let highlights = self.state.document_highlights(&uri, pos);
+
+        Box::pin(async move {
+            if highlights.is_empty() {
+                Ok(None)
+            } else {
+                Ok(Some(highlights))
+            }
+        })
+    }
+
+    pub(super) fn prepare_call_hierarchy(
+        &mut self,
+        params: CallHierarchyPrepareParams,
+    ) -> BoxFuture<'static, Result<Option<Vec<CallHierarchyItem>>, ResponseError>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let pos = params.text_document_position_params.position;
+
+        let items = self.state.prepare_call_hierarchy(&uri, pos);
+
+        Box::pin(async move {
+            if items.is_empty() {
+                Ok(None)
+            } else {
+                Ok(Some(items))
+            }
+        })
+    }
+
+    #[allow(clippy::needless_pass_by_value)]
+    pub(super) fn incoming_calls(
+        &mut self,
+        params: CallHierarchyIncomingCallsParams,
+    ) -> BoxFuture<'static, Result<Option<Vec<CallHierarchyIncomingCall>>, ResponseError>> {
+        let calls = self.state.incoming_calls(&params.item);
+
+        Box::pin(async move { Ok(Some(calls)) })
+    }
+
+    #[allow(clippy::needless_pass_by_value)]
+    pub(super) fn outgoing_calls(
+        &mut self,
+        params: CallHierarchyOutgoingCallsParams,
+    ) -> BoxFuture<'static, Result<Option<Vec<CallHierarchyOutgoingCall>>, ResponseError>> {
+        let calls = self.state.outgoing_calls(&params.item);
+
+        Box::pin(async move { Ok(Some(calls)) })
+    }
+
     pub(super) fn document_symbol(
         &self,
         params: DocumentSymbolParams,
@@ -379,6 +680,42 @@ impl ProtoLanguageServer {
         Box::pin(async move { Ok(Some(response)) })
     }
 
+    pub(super) fn folding_range(
+        &self,
+        params: FoldingRangeParams,
+    ) -> BoxFuture<'static, Result<Option<Vec<FoldingRange>>, ResponseError>> {
+        let uri = params.text_document.uri;
+
+        let Some(document) = self.state.get_document(&uri) else {
+            error!(uri=%uri, "failed to get document");
+            return Box::pin(async move { Ok(None) });
+        };
+
+        let ranges = document.folding_ranges();
+
+        Box::pin(async move { Ok(Some(ranges)) })
+    }
+
+    pub(super) fn selection_range(
+        &self,
+        params: SelectionRangeParams,
+    ) -> BoxFuture<'static, Result<Option<Vec<SelectionRange>>, ResponseError>> {
+        let uri = params.text_document.uri;
+
+        let Some(document) = self.state.get_document(&uri) else {
+            error!(uri=%uri, "failed to get document");
+            return Box::pin(async move { Ok(None) });
+        };
+
+        let ranges = params
+            .positions
+            .into_iter()
+            .map(|position| document.selection_range(position))
+            .collect();
+
+        Box::pin(async move { Ok(Some(ranges)) })
+    }
+
     #[allow(clippy::needless_pass_by_value)]
     pub(super) fn workspace_symbol(
         &mut self,
@@ -429,28 +766,200 @@ impl ProtoLanguageServer {
         Box::pin(async move { Ok(response) })
     }
 
-    pub(super) fn did_save(
-        &mut self,
-        params: DidSaveTextDocumentParams,
-    ) -> ControlFlow<async_lsp::Result<()>> {
-        let uri = params.text_document.uri;
+    pub(super) fn on_type_formatting(
+        &self,
+        params: DocumentOnTypeFormattingParams,
+    ) -> BoxFuture<'static, Result<Option<Vec<TextEdit>>, ResponseError>> {
+        let uri = params.text_document_position.text_document.uri;
+        let pos = params.text_document_position.position;
+
+        let Some(document) = self.state.get_document(&uri) else {
+            error!(uri=%uri, "failed to get document");
+            return Box::pin(async move { Ok(None) });
+        };
+
         let content = self.state.get_content(&uri);
+        let edits = document.on_type_formatting(&content, pos, &params.ch, &params.options);
 
-        let Some(ipath) = self.configs.get_include_paths(&uri) else {
-            return ControlFlow::Continue(());
+        Box::pin(async move {
+            if edits.is_empty() {
+                Ok(None)
+            } else {
+                Ok(Some(edits))
+            }
+        })
+    }
+
+    pub(super) fn code_action(
+        &self,
+        params: CodeActionParams,
+    ) -> BoxFuture<'static, Result<Option<CodeActionResponse>, ResponseError>> {
+        let uri = params.text_document.uri;
+        let requested = |kind: &CodeActionKind| {
+            params
+                .context
+                .only
+                .as_ref()
+                .is_none_or(|only| only.iter().any(|k| kind.as_str().starts_with(k.as_str())))
         };
 
-        let Some(pconf) = self.configs.get_config_for_uri(&uri) else {
+        let mut actions = Vec::new();
+        let misplaced = params.context.diagnostics.iter().find(|diagnostic| {
+            diagnostic.code
+                == Some(NumberOrString::String(String::from(
+                    "PACKAGE_DIRECTORY_MATCH",
+                )))
+        });
+        if requested(&CodeActionKind::QUICKFIX)
+            && let Some(diagnostic) = misplaced
+        {
+            let include_paths = |uri: &Url| self.configs.get_include_paths(uri);
+            if let Some((name, edit)) = self.state.move_to_package_directory(&uri, &include_paths) {
+                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: format!("Move file to \"{name}\""),
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    diagnostics: Some(vec![diagnostic.clone()]),
+                    edit: Some(edit),
+                    is_preferred: Some(true),
+                    ..Default::default()
+                }));
+            }
+        }
+        if requested(&CodeActionKind::SOURCE_ORGANIZE_IMPORTS) {
+            let ipath = self.configs.get_include_paths(&uri).unwrap_or_default();
+            let edits = self.state.organize_imports(&uri, &ipath);
+            if !edits.is_empty() {
+                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: String::from("Organize imports"),
+                    kind: Some(CodeActionKind::SOURCE_ORGANIZE_IMPORTS),
+                    edit: Some(WorkspaceEdit {
+                        changes: Some(HashMap::from([(uri, edits)])),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }));
+            }
+        }
+
+        Box::pin(async move { Ok(Some(actions)) })
+    }
+
+    /// Runs `protols.exportDescriptorSet`, whose arguments are the URI of a
+    /// file and the path to write its descriptor set to.
+    pub(super) fn execute_command(
+        &mut self,
+        params: ExecuteCommandParams,
+    ) -> BoxFuture<'static, Result<Option<Value>, ResponseError>> {
+        let ExecuteCommandParams {
+            command, arguments, ..
+        } = params;
+        let result = match command.as_str() {
+            EXPORT_DESCRIPTOR_SET_COMMAND => self.export_descriptor_set(&arguments),
+            command => Err(ResponseError::new(
+                ErrorCode::INVALID_REQUEST,
+                format!("unknown command: {command}"),
+            )),
+        };
+        Box::pin(async move { result.map(|()| None) })
+    }
+
+    fn export_descriptor_set(&mut self, arguments: &[Value]) -> Result<(), ResponseError> {
+        let invalid = || {
+            ResponseError::new(
+                ErrorCode::INVALID_PARAMS,
+                format!("{EXPORT_DESCRIPTOR_SET_COMMAND} expects a file URI and an output path"),
+            )
+        };
+        let [uri, output] = arguments else {
+            return Err(invalid());
+        };
+        let uri = uri
+            .as_str()
+            .and_then(|uri| Url::parse(uri).ok())
+            .ok_or_else(invalid)?;
+        let output = output.as_str().map(PathBuf::from).ok_or_else(invalid)?;
+
+        let ipath = self.configs.get_include_paths(&uri).ok_or_else(|| {
+            ResponseError::new(
+                ErrorCode::INVALID_PARAMS,
+                format!("{uri} is not in a workspace"),
+            )
+        })?;
+        let content = match self.state.get_document(&uri) {
+            Some(_) => self.state.get_content(&uri),
+            None => uri
+                .to_file_path()
+                .ok()
+                .and_then(|path| read_to_string(path).ok())
+                .ok_or_else(|| {
+                    ResponseError::new(ErrorCode::INVALID_PARAMS, format!("cannot read {uri}"))
+                })?,
+        };
+
+        let files = self
+            .state
+            .descriptor_set(&uri, &content, &ipath)
+            .map_err(|errors| ResponseError::new(ErrorCode::REQUEST_FAILED, errors.join("\n")))?;
+        std::fs::write(&output, encode_file_descriptor_set(&files)).map_err(|e| {
+            ResponseError::new(
+                ErrorCode::REQUEST_FAILED,
+                format!("cannot write {}: {e}", output.display()),
+            )
+        })
+    }
+
+    /// Asks the client to report changes to the files of the breaking change
+    /// baselines, which stay loaded until then.
+    pub(super) fn initialized(
+        &mut self,
+        _params: InitializedParams,
+    ) -> ControlFlow<async_lsp::Result<()>> {
+        let baselines = self.configs.breaking_baselines();
+        if !self.watched_files || baselines.is_empty() {
             return ControlFlow::Continue(());
+        }
+
+        // A baseline is either a descriptor set or a directory of files.
+        let watchers = baselines
+            .iter()
+            .flat_map(|path| {
+                let path = path.to_string_lossy();
+                [path.to_string(), format!("{path}/**")]
+            })
+            .map(|glob| FileSystemWatcher {
+                glob_pattern: GlobPattern::String(glob),
+                kind: None,
+            })
+            .collect();
+        let params = RegistrationParams {
+            registrations: vec![Registration {
+                id: String::from("breaking-baselines"),
+                method: String::from(DidChangeWatchedFiles::METHOD),
+                register_options: serde_json::to_value(DidChangeWatchedFilesRegistrationOptions {
+                    watchers,
+                })
+                .ok(),
+            }],
         };
+        let mut client = self.client.clone();
+        tokio::spawn(async move {
+            if let Err(e) = client.register_capability(params).await {
+                error!(error=%e, "failed to watch the breaking change baselines");
+            }
+        });
+        ControlFlow::Continue(())
+    }
 
-        if let Some(diagnostics) =
-            self.state
-                .upsert_file(&uri, &content, &ipath, 8, &pconf.config, true)
-            && let Err(e) = self.client.publish_diagnostics(diagnostics)
-        {
-            error!(error=%e, "failed to publish diagnostics");
+    pub(super) fn did_save(
+        &mut self,
+        params: DidSaveTextDocumentParams,
+    ) -> ControlFlow<async_lsp::Result<()>> {
+        let uri = params.text_document.uri;
+        let content = self.state.get_content(&uri);
+        if let Ok(path) = uri.to_file_path() {
+            self.baselines.invalidate(&path);
         }
+        self.check_file(&uri, &content);
         ControlFlow::Continue(())
     }
 
@@ -460,21 +969,141 @@ impl ProtoLanguageServer {
     ) -> ControlFlow<async_lsp::Result<()>> {
         let uri = params.text_document.uri;
         let content = params.text_document.text;
+        self.check_file(&uri, &content);
+        ControlFlow::Continue(())
+    }
 
-        let Some(ipath) = self.configs.get_include_paths(&uri) else {
-            return ControlFlow::Continue(());
+    /// Parses `uri` with `content`, publishes its diagnostics and runs protoc
+    /// on it.
+    fn check_file(&mut self, uri: &Url, content: &str) {
+        let Some(ipath) = self.configs.get_include_paths(uri) else {
+            return;
         };
 
-        let Some(pconf) = self.configs.get_config_for_uri(&uri) else {
-            return ControlFlow::Continue(());
+        let Some(pconf) = self.configs.get_config_for_uri(uri) else {
+            return;
         };
 
-        if let Some(diagnostics) =
-            self.state
-                .upsert_file(&uri, &content, &ipath, 8, &pconf.config, true)
-            && let Err(e) = self.client.publish_diagnostics(diagnostics)
+        let config = pconf.config.clone();
+        let diagnostics = self
+            .state
+            .upsert_file(uri, content, &ipath, 8, &config, false);
+        self.publish_diagnostics(diagnostics);
+
+        self.run_protoc(uri, &ipath, &config);
+    }
+
+    /// Checks `uri` with protoc in the background when it is the diagnostics
+    /// engine, unless its content and imports did not change since the last
+    /// run. The diagnostics are published when the run ends.
+    fn run_protoc(&mut self, uri: &Url, ipath: &[PathBuf], config: &Config) {
+        if config.diagnostics.engine != DiagnosticsEngine::Protoc {
+            return;
+        }
+        let Ok(file_path) = uri.to_file_path() else {
+            return;
+        };
+        let run = ProtocRun {
+            uri: uri.clone(),
+            protoc_path: config.path.protoc.clone(),
+            file_path: file_path.to_str().unwrap_or_default().to_string(),
+            include_paths: ipath
+                .iter()
+                .map(|p| p.to_str().unwrap_or_default().to_string())
+                .collect(),
+            timeout: Duration::from_millis(config.diagnostics.protoc_timeout_ms),
+            content_hash: self.state.content_hash(uri, ipath),
+        };
+
+        match self.protoc.cached(&run) {
+            Some(messages) => self.publish_protoc_diagnostics(uri, ipath, messages),
+            None => self.protoc.spawn(self.client.clone(), run),
+        }
+    }
+
+    pub(super) fn protoc_finished(
+        &mut self,
+        event: ProtocFinished,
+    ) -> ControlFlow<async_lsp::Result<()>> {
+        let uri = event.run.uri.clone();
+        if let Some(messages) = self.protoc.finish(event)
+            && let Some(ipath) = self.configs.get_include_paths(&uri)
         {
-            error!(error=%e, "failed to publish diagnostics");
+            self.publish_protoc_diagnostics(&uri, &ipath, messages);
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn publish_protoc_diagnostics(
+        &mut self,
+        uri: &Url,
+        ipath: &[PathBuf],
+        messages: Vec<ProtocDiagnostic>,
+    ) {
+        let diagnostics = self.state.publish_protoc_diagnostics(uri, ipath, messages);
+        self.publish_diagnostics(diagnostics);
+    }
+
+    /// Publishes `diagnostics`, adding to each file its lint warnings and its
+    /// breaking changes against the baseline of its workspace, if one is
+    /// configured.
+    fn publish_diagnostics(&mut self, diagnostics: Vec<PublishDiagnosticsParams>) {
+        for mut params in diagnostics {
+            if let (Some(pconf), Some(ipath)) = (
+                self.configs.get_config_for_uri(&params.uri),
+                self.configs.get_include_paths(&params.uri),
+            ) {
+                params.diagnostics.extend(self.state.lint_diagnostics(
+                    &params.uri,
+                    &ipath,
+                    &pconf.config.lint,
+                ));
+            }
+            params
+                .diagnostics
+                .extend(self.breaking_diagnostics(&params.uri));
+            if let Err(e) = self.client.publish_diagnostics(params) {
+                error!(error=%e, "failed to publish diagnostics");
+            }
+        }
+    }
+
+    /// Reports the breaking changes of `uri` against `breaking.against`, none
+    /// while the baseline loads in the background. The baseline is loaded
+    /// again once its files change.
+    fn breaking_diagnostics(&mut self, uri: &Url) -> Vec<Diagnostic> {
+        let (Some(workspace), Some(pconf), Some(ipath)) = (
+            self.configs.get_workspace_for_uri(uri).cloned(),
+            self.configs.get_config_for_uri(uri),
+            self.configs.get_include_paths(uri),
+        ) else {
+            return vec![];
+        };
+        let Some(against) = &pconf.config.breaking.against else {
+            return vec![];
+        };
+        let Some(document) = self.state.get_document(uri) else {
+            return vec![];
+        };
+
+        let path = workspace.to_file_path().unwrap_or_default().join(against);
+        let Some(baseline) =
+            self.baselines
+                .get(&self.client, &workspace, &path, &self.configs, uri)
+        else {
+            return vec![];
+        };
+        self.state.breaking_diagnostics(&document, &ipath, baseline)
+    }
+
+    /// Checks again the files that waited for a baseline to load.
+    pub(super) fn baseline_loaded(
+        &mut self,
+        event: BaselineLoaded,
+    ) -> ControlFlow<async_lsp::Result<()>> {
+        for uri in self.baselines.finish(event) {
+            let content = self.state.get_content(&uri);
+            self.check_file(&uri, &content);
         }
         ControlFlow::Continue(())
     }
@@ -494,13 +1123,10 @@ impl ProtoLanguageServer {
             return ControlFlow::Continue(());
         };
 
-        if let Some(diagnostics) =
-            self.state
-                .upsert_file(&uri, &content, &ipath, 8, &pconf.config, false)
-            && let Err(e) = self.client.publish_diagnostics(diagnostics)
-        {
-            error!(error=%e, "failed to publish diagnostics");
-        }
+        let diagnostics = self
+            .state
+            .upsert_file(&uri, &content, &ipath, 8, &pconf.config, false);
+        self.publish_diagnostics(diagnostics);
         ControlFlow::Continue(())
     }
 
@@ -526,17 +1152,62 @@ impl ProtoLanguageServer {
         params: RenameFilesParams,
     ) -> ControlFlow<async_lsp::Result<()>> {
         for file in params.files {
-            let Ok(new_uri) = Url::from_file_path(&file.new_uri) else {
+            let Ok(new_uri) = Url::parse(&file.new_uri) else {
                 error!(uri = file.new_uri, "failed to parse uri");
                 continue;
             };
 
-            let Ok(old_uri) = Url::from_file_path(&file.old_uri) else {
+            let Ok(old_uri) = Url::parse(&file.old_uri) else {
                 error!(uri = file.old_uri, "failed to parse uri");
                 continue;
             };
 
-            self.state.rename_file(&new_uri, &old_uri);
+            for (old_uri, new_uri) in self.state.moved_files(&old_uri, &new_uri) {
+                self.state.rename_file(&new_uri, &old_uri);
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Rewrites the imports of the proto files about to be renamed or moved,
+    /// directly or with their directory, so that they import the new paths.
+    pub(super) fn will_rename_files(
+        &self,
+        params: RenameFilesParams,
+    ) -> BoxFuture<'static, Result<Option<WorkspaceEdit>, ResponseError>> {
+        let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+        for file in params.files {
+            let (Ok(old_uri), Ok(new_uri)) = (Url::parse(&file.old_uri), Url::parse(&file.new_uri))
+            else {
+                error!(uri = file.old_uri, "failed to parse uri");
+                continue;
+            };
+
+            let include_paths = |uri: &Url| self.configs.get_include_paths(uri);
+            for (from, to) in self.state.moved_files(&old_uri, &new_uri) {
+                for (uri, edits) in self.state.import_edits_for_move(&from, &to, &include_paths) {
+                    changes.entry(uri).or_default().extend(edits);
+                }
+            }
+        }
+
+        let edit = (!changes.is_empty()).then(|| WorkspaceEdit {
+            changes: Some(changes),
+            ..Default::default()
+        });
+        Box::pin(async move { Ok(edit) })
+    }
+
+    /// Forgets the baselines whose files changed, to load them again on the
+    /// next check.
+    pub(super) fn did_change_watched_files(
+        &mut self,
+        params: DidChangeWatchedFilesParams,
+    ) -> ControlFlow<async_lsp::Result<()>> {
+        for change in params.changes {
+            if let Ok(path) = change.uri.to_file_path() {
+                self.baselines.invalidate(&path);
+            }
         }
         ControlFlow::Continue(())
     }
@@ -567,10 +1238,9 @@ impl ProtoLanguageServer {
         if self.shutdown_received {
             info!("Received exit notification after shutdown, exiting with code 0");
             std::process::exit(0);
-        } else {
-            warn!("Received exit notification without shutdown, exiting with code 1");
-            std::process::exit(1);
         }
+        warn!("Received exit notification without shutdown, exiting with code 1");
+        std::process::exit(1);
     }
 }
 
@@ -597,11 +1267,173 @@ fn parse_init_include_paths(init_options: &Value) -> Option<Vec<PathBuf>> {
     }
 }
 
+/// Builds the edit of a rename as document changes annotated as part of the
+/// primary rename or of the convention chain, both needing confirmation, so
+/// that clients show a preview of them. A `warning` about the rename is added
+/// to the description of the primary rename.
+fn annotated_rename_edit(
+    new_name: &str,
+    primary: HashMap<Url, Vec<TextEdit>>,
+    chain: HashMap<Url, Vec<TextEdit>>,
+    warning: Option<&str>,
+) -> WorkspaceEdit {
+    let mut primary_description = String::from("The renamed symbol and its references.");
+    if let Some(warning) = warning {
+        primary_description = format!("{primary_description} {warning}");
+    }
+    let groups = [
+        (
+            PRIMARY_RENAME_ANNOTATION,
+            format!("Rename to \"{new_name}\""),
+            primary_description,
+            primary,
+        ),
+        (
+            CONVENTION_CHAIN_ANNOTATION,
+            String::from("Rename the convention chain"),
+            String::from("The rpc and its <Rpc>Request and <Rpc>Response messages, renamed along."),
+            chain,
+        ),
+    ];
+
+    let mut annotations = HashMap::new();
+    let mut files: BTreeMap<Url, Vec<AnnotatedTextEdit>> = BTreeMap::new();
+    for (id, label, description, edits) in groups {
+        if edits.is_empty() {
+            continue;
+        }
+        annotations.insert(
+            id.to_string(),
+            ChangeAnnotation {
+                label,
+                needs_confirmation: Some(true),
+                description: Some(description),
+            },
+        );
+        for (uri, edits) in edits {
+            files
+                .entry(uri)
+                .or_default()
+                .extend(edits.into_iter().map(|text_edit| AnnotatedTextEdit {
+                    text_edit,
+                    annotation_id: id.to_string(),
+                }));
+        }
+    }
+
+    let edits = files
+        .into_iter()
+        .map(|(uri, mut edits)| {
+            edits.sort_by_key(|e| (e.text_edit.range.start, e.text_edit.range.end));
+            TextDocumentEdit {
+                text_document: OptionalVersionedTextDocumentIdentifier { uri, version: None },
+                edits: edits.into_iter().map(OneOf::Right).collect(),
+            }
+        })
+        .collect();
+    WorkspaceEdit {
+        document_changes: Some(DocumentChanges::Edits(edits)),
+        change_annotations: Some(annotations),
+        ..Default::default()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use async_lsp::lsp_types::Range;
     use serde_json::json;
 
+    #[test]
+    fn test_annotated_rename_edit() {
+        let service: Url = "file://input/service.proto".parse().unwrap();
+        let messages: Url = "file://input/messages.proto".parse().unwrap();
+        let edit = |line: u32, new_text: &str| TextEdit {
+            range: Range {
+                start: Position { line, character: 2 },
+                end: Position { line, character: 9 },
+            },
+            new_text: new_text.to_string(),
+        };
+        let primary = HashMap::from([(service.clone(), vec![edit(7, "FetchBook")])]);
+        let chain = HashMap::from([
+            (service, vec![edit(3, "FetchBookRequest")]),
+            (messages, vec![edit(1, "FetchBookRequest")]),
+        ]);
+
+        let workspace_edit = annotated_rename_edit("FetchBook", primary, chain, None);
+        assert_eq!(
+            serde_json::to_value(workspace_edit).unwrap(),
+            json!({
+                "documentChanges": [
+                    {
+                        "textDocument": { "uri": "file://input/messages.proto", "version": null },
+                        "edits": [{
+                            "range": {
+                                "start": { "line": 1, "character": 2 },
+                                "end": { "line": 1, "character": 9 }
+                            },
+                            "newText": "FetchBookRequest",
+                            "annotationId": "conventionChain"
+                        }]
+                    },
+                    {
+                        "textDocument": { "uri": "file://input/service.proto", "version": null },
+                        "edits": [
+                            {
+                                "range": {
+                                    "start": { "line": 3, "character": 2 },
+                                    "end": { "line": 3, "character": 9 }
+                                },
+                                "newText": "FetchBookRequest",
+                                "annotationId": "conventionChain"
+                            },
+                            {
+                                "range": {
+                                    "start": { "line": 7, "character": 2 },
+                                    "end": { "line": 7, "character": 9 }
+                                },
+                                "newText": "FetchBook",
+                                "annotationId": "primaryRename"
+                            }
+                        ]
+                    }
+                ],
+                "changeAnnotations": {
+                    "primaryRename": {
+                        "label": "Rename to \"FetchBook\"",
+                        "needsConfirmation": true,
+                        "description": "The renamed symbol and its references."
+                    },
+                    "conventionChain": {
+                        "label": "Rename the convention chain",
+                        "needsConfirmation": true,
+                        "description": "The rpc and its <Rpc>Request and <Rpc>Response messages, renamed along."
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_annotated_rename_edit_warning() {
+        let uri: Url = "file://input/book.proto".parse().unwrap();
+        let primary = HashMap::from([(uri, vec![TextEdit::default()])]);
+
+        let workspace_edit =
+            annotated_rename_edit("head_line", primary, HashMap::new(), Some("JSON changes."));
+        assert_eq!(
+            serde_json::to_value(workspace_edit.change_annotations).unwrap(),
+            json!({
+                "primaryRename": {
+                    "label": "Rename to \"head_line\"",
+                    "needsConfirmation": true,
+                    "description": "The renamed symbol and its references. JSON changes."
+                }
+            })
+        );
+    }
+
     #[test]
     fn test_parse_init_include_paths_array() {
         let init_options = json!({
@@ -13,11 +13,15 @@ use tower::ServiceBuilder;
 
 use crate::transport::create_transport;
 
+mod baseline;
 mod cli;
+mod commands;
 mod config;
+mod descriptor;
 mod docs;
 mod document;
 mod formatter;
+mod lint;
 mod log;
 mod lsp;
 mod model;
@@ -32,6 +36,9 @@ const FALLBACK_INCLUDE_PATH: Option<&str> = option_env!("FALLBACK_INCLUDE_PATH")
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<(), transport::TransportError> {
     let cli = Cli::parse();
+    if let Some(command) = &cli.command {
+        std::process::exit(commands::run(command, &cli));
+    }
 
     let (tx, mut rx) = tokio::sync::mpsc::channel(100);
     let (reload_handle, _log_guard) = log::install(tx);
@@ -5,6 +5,7 @@
 /// (e.g., messages, fields, RPCs) and are used by the central dispatcher to
 /// route query matches to their designated handlers.
 pub mod definitions {
+    pub const SYNTAX: &str = "element.syntax";
     pub const PACKAGE: &str = "element.package";
     pub const IMPORT: &str = "element.import";
     pub const MESSAGE: &str = "element.message";
@@ -18,6 +19,7 @@ pub mod definitions {
     pub const ENUM_FIELD: &str = "element.enum_field";
 
     const ALL: &[&str] = &[
+        SYNTAX,
         PACKAGE,
         IMPORT,
         MESSAGE,
@@ -67,4 +69,6 @@ pub mod properties {
     pub const RPC_RESPONSE_STREAM: &str = "rpc.response.stream";
     pub const OPTION_NAME: &str = "option.name";
     pub const OPTION_VALUE: &str = "option.value";
+    pub const OPTION: &str = "option";
+    pub const EDITION_YEAR: &str = "edition.year";
 }
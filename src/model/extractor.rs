@@ -12,7 +12,9 @@ use tree_sitter::{Node, Query, QueryCursor, StreamingIterator};
 
 use super::captures;
 use super::captures::definitions;
-use super::types::{CommentBlock, ElementKind, ElementMeta, ModelElement};
+use super::types::{
+    CommentBlock, ElementKind, ElementMeta, MetaModel, ModelElement, OptionEntry, SyntaxKind,
+};
 
 mod handlers;
 mod query;
@@ -36,20 +38,16 @@ mod query;
 ///
 /// # Returns
 ///
-/// Returns a tuple containing:
-/// 1. A [`String`] representing the extracted package namespace of the file
-///    (defaults to empty if missing).
-/// 2. A [`Vec<ModelElement>`] containing the completely assembled, flat
-///    hierarchical graph registry of all elements.
-pub fn build_meta_model(
-    root_node: Node,
-    source: &[u8],
-    query: &Query,
-) -> (String, Vec<ModelElement>) {
+/// Returns a [`MetaModel`] containing the extracted package namespace
+/// (defaults to empty if missing), the declared syntax revision, the
+/// file-level options and the completely assembled, flat hierarchical graph
+/// registry of all elements.
+pub fn build_meta_model(root_node: Node, source: &[u8], query: &Query) -> MetaModel {
     let mut cursor = QueryCursor::new();
     let mut matches = cursor.matches(query, root_node, source);
     let capture_names = query.capture_names();
     let mut package_name = None;
+    let mut syntax = None;
     let mut parsed_matches: Vec<ParsedMatch> = Vec::with_capacity(128);
 
     while let Some(query_match) = matches.next() {
@@ -66,12 +64,19 @@ pub fn build_meta_model(
             package_name.get_or_insert(name);
         }
 
+        if let Some(ParsedMatch::Syntax { kind }) =
+            element.take_if(|e| matches!(e, ParsedMatch::Syntax { .. }))
+        {
+            syntax.get_or_insert(kind);
+        }
+
         parsed_matches.extend(element);
     }
 
     let mut elements: Vec<ModelElement> = Vec::with_capacity(parsed_matches.len());
     let mut context_stack: Vec<usize> = Vec::new();
     let mut documentation_buffer: Vec<CommentBlock> = Vec::new();
+    let mut file_options: Vec<OptionEntry> = Vec::new();
     let package_name = package_name.unwrap_or_default();
 
     parsed_matches.sort_by_key(|m| m.range().start);
@@ -81,11 +86,17 @@ pub fn build_meta_model(
             &mut elements,
             &mut context_stack,
             &mut documentation_buffer,
+            &mut file_options,
             &package_name,
         );
     }
 
-    (package_name, elements)
+    MetaModel {
+        package: package_name,
+        syntax: syntax.unwrap_or_default(),
+        options: file_options,
+        elements,
+    }
 }
 
 #[inline]
@@ -134,6 +145,14 @@ pub(super) enum ParsedMatch {
     /// file.
     Package { name: String },
 
+    /// A `syntax` or `edition` declaration defining the language revision of
+    /// the file.
+    Syntax { kind: SyntaxKind },
+
+    /// An option assignment attached to the enclosing element, or to the file
+    /// when declared at the root scope.
+    OptionAssignment(OptionEntry),
+
     /// An option marker signaling that the target container or terminal field
     /// is deprecated.
     DeprecationMarker { range: Range },
@@ -159,13 +178,14 @@ impl ParsedMatch {
     /// # Returns
     ///
     /// Returns the exact [`Range`] spanning the token's presence in the file.
-    /// [`ParsedMatch::Package`] defaults to an empty zero-range positioned at
-    /// the absolute start of the document.
+    /// [`ParsedMatch::Package`] and [`ParsedMatch::Syntax`] default to an
+    /// empty zero-range positioned at the absolute start of the document.
     fn range(&self) -> Range {
         match self {
             Self::Comment(c) => c.range,
+            Self::OptionAssignment(option) => option.range,
             Self::DeprecationMarker { range } | Self::Entity { range, .. } => *range,
-            Self::Package { .. } => Range::default(),
+            Self::Package { .. } | Self::Syntax { .. } => Range::default(),
         }
     }
 
@@ -180,12 +200,14 @@ impl ParsedMatch {
         elements: &mut Vec<ModelElement>,
         context_stack: &mut Vec<usize>,
         documentation_buffer: &mut Vec<CommentBlock>,
+        file_options: &mut Vec<OptionEntry>,
         package_name: &str,
     ) {
         match self {
-            Self::Package { .. } => {}
+            Self::Package { .. } | Self::Syntax { .. } => {}
             Self::Comment(comment) => {
-                if is_inline_trailing(elements, comment.range.start.line) {
+                if let Some(id) = inline_trailed(elements, comment.range.start.line) {
+                    elements[id].meta.trailing_comments.push(comment);
                     return;
                 }
 
@@ -206,6 +228,20 @@ impl ParsedMatch {
                     target.kind.set_deprecated(true);
                 }
             }
+            Self::OptionAssignment(option) => {
+                prune_context_stack(context_stack, elements, option.range.start);
+
+                let target_id = elements
+                    .last()
+                    .filter(|e| e.contains_position(option.range.start) && e.kind.is_terminal())
+                    .map(|e| e.id)
+                    .or_else(|| context_stack.last().copied());
+
+                match target_id.and_then(|idx| elements.get_mut(idx)) {
+                    Some(target) => target.meta.options.push(option),
+                    None => file_options.push(option),
+                }
+            }
             Self::Entity {
                 mut kind,
                 range,
@@ -244,6 +280,8 @@ impl ParsedMatch {
                         range,
                         selection_range,
                         documentation,
+                        trailing_comments: Vec::new(),
+                        options: Vec::new(),
                     },
                     kind,
                     children: Vec::new(),
@@ -279,14 +317,16 @@ fn prune_context_stack(
     }
 }
 
-/// Checks if the given comment line rests on the exact same physical line where
-/// any active element or its parent hierarchical container closes.
+/// Returns the id of the innermost active element, or parent hierarchical
+/// container, that opens or closes on the exact same physical line as the
+/// given comment line.
 #[inline]
-fn is_inline_trailing(elements: &[ModelElement], comment_line: u32) -> bool {
+fn inline_trailed(elements: &[ModelElement], comment_line: u32) -> Option<usize> {
     std::iter::successors(elements.last(), |current| {
         current
             .parent_id
             .and_then(|parent_idx| elements.get(parent_idx))
     })
-    .any(|e| e.meta.range.end.line == comment_line)
+    .find(|e| e.meta.range.start.line == comment_line || e.meta.range.end.line == comment_line)
+    .map(|e| e.id)
 }
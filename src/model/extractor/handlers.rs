@@ -9,6 +9,7 @@ mod import;
 mod orphan;
 mod package;
 mod rpc;
+mod syntax;
 
 /// Routes a compiled Tree-sitter query match to its respective dedicated
 /// extraction handler based on the matching root element flavor handle.
@@ -49,6 +50,7 @@ pub fn dispatch_element(
     };
 
     match kind_str {
+        definitions::SYNTAX => syntax::extract_syntax(query_match, capture_names, source),
         definitions::PACKAGE => package::extract_package(query_match, capture_names, source),
         definitions::IMPORT => import::extract_import(query_match, capture_names, source),
         definitions::SERVICE => container::extract_service(query_match, capture_names, source),
@@ -1,8 +1,8 @@
 use std::str::Utf8Error;
 
-use tree_sitter::{QueryCapture, QueryMatch};
+use tree_sitter::{Node, QueryCapture, QueryMatch};
 
-use crate::model::CommentBlock;
+use crate::model::{CommentBlock, OptionEntry};
 use crate::utils::{clean_proto_comment, to_lsp_range};
 
 use super::super::captures::{definitions, properties};
@@ -35,6 +35,9 @@ pub(super) fn extract_orphan(
                 }
             }
             properties::OPTION_NAME | properties::OPTION_VALUE => {}
+            properties::OPTION => {
+                return extract_option_entry(node, source).map(ParsedMatch::OptionAssignment);
+            }
             properties::DEPRECATION_MARKER => {
                 return Some(ParsedMatch::DeprecationMarker {
                     range: to_lsp_range(node),
@@ -59,3 +62,39 @@ pub(super) fn extract_orphan(
 
     None
 }
+
+/// Splits an `option`, `field_option` or `enum_value_option` node into its
+/// written name and the raw text of its assigned constant.
+fn extract_option_entry(node: Node, source: &[u8]) -> Option<OptionEntry> {
+    let mut cursor = node.walk();
+    let constant = node
+        .named_children(&mut cursor)
+        .find(|child| child.kind() == "constant")?;
+
+    let trace_utf8_error = |e: &Utf8Error| {
+        tracing::warn!(
+            "Option skipped: failed to extract valid UTF-8 text at {:?}. Error: {:?}",
+            to_lsp_range(node),
+            e
+        );
+    };
+
+    let raw_text = node.utf8_text(source).inspect_err(trace_utf8_error).ok()?;
+    let value = constant
+        .utf8_text(source)
+        .inspect_err(trace_utf8_error)
+        .ok()?;
+
+    let (name, _) = raw_text.split_once('=')?;
+    let name = match node.kind() {
+        "option" => name.trim().strip_prefix("option")?.trim(),
+        _ => name.trim(),
+    };
+
+    Some(OptionEntry {
+        name: name.to_string(),
+        value: value.to_string(),
+        range: to_lsp_range(node),
+        value_range: to_lsp_range(constant),
+    })
+}
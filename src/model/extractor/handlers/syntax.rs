@@ -0,0 +1,78 @@
+use std::str::Utf8Error;
+
+use tree_sitter::{QueryCapture, QueryMatch};
+
+use crate::model::SyntaxKind;
+use crate::utils::to_lsp_range;
+
+use super::super::captures::{definitions, properties};
+use super::ParsedMatch;
+
+#[inline]
+pub(super) fn extract_syntax(
+    query_match: &QueryMatch,
+    capture_names: &[&str],
+    source: &[u8],
+) -> Option<ParsedMatch> {
+    let mut declaration = None;
+    let mut edition = None;
+
+    for QueryCapture { node, index } in query_match.captures.iter().copied() {
+        let capture_name = capture_names[index as usize];
+
+        let trace_utf8_error = |e: &Utf8Error| {
+            tracing::warn!(
+                "extract_syntax: failed to extract valid UTF-8 text at {:?}. Error: {:?}",
+                to_lsp_range(node),
+                e
+            );
+        };
+
+        match capture_name {
+            definitions::SYNTAX => {
+                declaration = node.utf8_text(source).inspect_err(trace_utf8_error).ok();
+            }
+            properties::EDITION_YEAR => {
+                edition = node
+                    .utf8_text(source)
+                    .map(|raw| raw.trim_matches(['"', '\'']).to_string())
+                    .inspect_err(trace_utf8_error)
+                    .ok();
+            }
+            properties::DOC_COMMENT => {}
+            invalid_kind if definitions::is_match(invalid_kind) => {
+                tracing::error!(
+                    "extract_syntax: received an incompatible element capture '{}' at range {:?}",
+                    invalid_kind,
+                    to_lsp_range(node)
+                );
+            }
+            unknown => {
+                tracing::debug!(
+                    "Unused auxiliary capture '{}' ignored inside extract_syntax at {:?}",
+                    unknown,
+                    to_lsp_range(node)
+                );
+            }
+        }
+    }
+
+    if let Some(edition) = edition {
+        return Some(ParsedMatch::Syntax {
+            kind: SyntaxKind::Editions { edition },
+        });
+    }
+
+    let Some(declaration) = declaration else {
+        tracing::error!("extract_syntax: failed to extract syntax declaration");
+        return None;
+    };
+
+    let kind = if declaration.contains("proto3") {
+        SyntaxKind::Proto3
+    } else {
+        SyntaxKind::Proto2
+    };
+
+    Some(ParsedMatch::Syntax { kind })
+}
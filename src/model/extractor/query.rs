@@ -1,11 +1,11 @@
 use super::super::captures::{
     definitions::{
         ENUM, ENUM_FIELD, FIELD, IMPORT, MAP_FIELD, MESSAGE, ONEOF, ONEOF_FIELD, PACKAGE, RPC,
-        SERVICE,
+        SERVICE, SYNTAX,
     },
     properties::{
-        DEPRECATION_MARKER, DOC_COMMENT, ENUM_VALUE, IMPORT_PATH, NAME, OPTION_NAME, OPTION_VALUE,
-        RPC_REQUEST_STREAM, RPC_RESPONSE_STREAM, TAG,
+        DEPRECATION_MARKER, DOC_COMMENT, EDITION_YEAR, ENUM_VALUE, IMPORT_PATH, NAME, OPTION,
+        OPTION_NAME, OPTION_VALUE, RPC_REQUEST_STREAM, RPC_RESPONSE_STREAM, TAG,
     },
     references::{FIELD_TYPE, MAP_KEY, MAP_VALUE, RPC_REQUEST, RPC_RESPONSE},
 };
@@ -27,6 +27,12 @@ pub fn generate_metamodel_query() -> String {
         r#"
 (comment) @{DOC_COMMENT}
 
+(syntax) @{SYNTAX}
+
+(edition
+    year: (string) @{EDITION_YEAR}
+) @{SYNTAX}
+
 (package
     (full_ident) @{NAME}
 ) @{PACKAGE}
@@ -103,6 +109,12 @@ pub fn generate_metamodel_query() -> String {
     (#eq? @{OPTION_NAME} "deprecated")
     (#eq? @{OPTION_VALUE} "true")
 ) @{DEPRECATION_MARKER}
+
+(option) @{OPTION}
+
+(field_option) @{OPTION}
+
+(enum_value_option) @{OPTION}
 "#
     )
 }
@@ -10,8 +10,11 @@ use std::fmt::Write;
 use async_lsp::lsp_types::{Position, SymbolKind};
 
 use crate::docs;
+use crate::utils::to_json_name;
 
-use super::types::{CardinalityKind, ElementKind, ModelElement, TypeReference};
+use super::types::{
+    CardinalityKind, ElementKind, HttpRule, ModelElement, OptionEntry, RpcShape, TypeReference,
+};
 
 impl From<&ElementKind> for SymbolKind {
     /// Maps an internal [`ElementKind`] variant directly to its closest
@@ -139,6 +142,19 @@ impl ModelElement {
             let _ = writeln!(buffer, "{fqn}");
         }
 
+        self.fill_declaration(buffer)?;
+
+        buffer.push_str(Self::CODE_BLOCK_END);
+
+        Some(())
+    }
+
+    /// Appends the bare single-line protobuf declaration of this element
+    /// (e.g., `repeated string tags = 3;` or `message Book`) to `buffer`,
+    /// without fences, FQN header or deprecation banner.
+    ///
+    /// Returns `None` for imports, which have no declaration form.
+    pub fn fill_declaration(&self, buffer: &mut String) -> Option<()> {
         let element_name = &self.meta.name;
 
         match &self.kind {
@@ -212,8 +228,6 @@ impl ModelElement {
             ElementKind::Import { .. } => return None,
         }
 
-        buffer.push_str(Self::CODE_BLOCK_END);
-
         Some(())
     }
 }
@@ -276,6 +290,29 @@ impl ElementKind {
         }
     }
 
+    /// Classifies an RPC by its streaming modifiers, returning `None` for any
+    /// other element.
+    #[inline]
+    pub const fn rpc_shape(&self) -> Option<RpcShape> {
+        let Self::Rpc {
+            request_stream,
+            response_stream,
+            ..
+        } = self
+        else {
+            return None;
+        };
+
+        Some(
+            match (request_stream.is_some(), response_stream.is_some()) {
+                (false, false) => RpcShape::Unary,
+                (false, true) => RpcShape::ServerStreaming,
+                (true, false) => RpcShape::ClientStreaming,
+                (true, true) => RpcShape::Bidirectional,
+            },
+        )
+    }
+
     /// Determines whether the element represents a leaf node (terminal) in the
     /// hierarchy, meaning it cannot syntactically contain any nested
     /// sub-elements.
@@ -293,6 +330,197 @@ impl ElementKind {
     }
 }
 
+impl ModelElement {
+    /// Returns the last option declared on this element under `name`, if any.
+    ///
+    /// Protobuf compilers reject repeated assignments of the same option, so
+    /// the last one wins when the source is malformed.
+    pub fn option(&self, name: &str) -> Option<&OptionEntry> {
+        self.meta.options.iter().rev().find(|o| o.name == name)
+    }
+
+    /// Returns the name this field is serialized under in the canonical JSON
+    /// mapping: the explicit `json_name` option when present, otherwise the
+    /// lowerCamelCase conversion of the field name.
+    ///
+    /// Returns `None` for elements that are not fields.
+    pub fn json_name(&self) -> Option<String> {
+        if !matches!(
+            self.kind,
+            ElementKind::Field { .. }
+                | ElementKind::MapField { .. }
+                | ElementKind::OneofField { .. }
+        ) {
+            return None;
+        }
+
+        let explicit = self
+            .option("json_name")
+            .and_then(OptionEntry::string_value)
+            .map(ToOwned::to_owned);
+
+        Some(explicit.unwrap_or_else(|| to_json_name(&self.meta.name)))
+    }
+}
+
+impl ModelElement {
+    const HTTP_OPTION: &'static str = "(google.api.http)";
+    const HTTP_VERBS: [&'static str; 5] = ["get", "put", "post", "delete", "patch"];
+
+    /// Extracts the REST mapping of an RPC from its `google.api.http` option.
+    ///
+    /// Both the aggregate form (`option (google.api.http) = { get: "/v1/x" };`)
+    /// and the sub-field form (`option (google.api.http).get = "/v1/x";`) are
+    /// understood, as are the `additional_bindings` of the aggregate form.
+    pub fn http_rule(&self) -> Option<HttpRule> {
+        if !matches!(self.kind, ElementKind::Rpc { .. }) {
+            return None;
+        }
+
+        let mut pairs: Vec<(String, String)> = Vec::new();
+        let mut additional = Vec::new();
+        for option in &self.meta.options {
+            if option.name == Self::HTTP_OPTION {
+                let mut blocks = aggregate_string_fields(&option.value).into_iter();
+                pairs.extend(blocks.next().unwrap_or_default());
+                additional.extend(blocks);
+            } else if let Some(key) = option
+                .name
+                .strip_prefix(Self::HTTP_OPTION)
+                .and_then(|rest| rest.strip_prefix('.'))
+                && let Some(value) = option.string_value()
+            {
+                pairs.push((key.to_string(), value.to_string()));
+            }
+        }
+
+        let mut rule = Self::http_binding(&pairs)?;
+        rule.additional_bindings = additional
+            .iter()
+            .filter_map(|pairs| Self::http_binding(pairs))
+            .collect();
+        Some(rule)
+    }
+
+    /// Builds a single HTTP mapping, without additional bindings, from the
+    /// fields of a `google.api.HttpRule`.
+    fn http_binding(pairs: &[(String, String)]) -> Option<HttpRule> {
+        let lookup = |key: &str| {
+            pairs
+                .iter()
+                .rev()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.clone())
+        };
+
+        let (method, path) = Self::HTTP_VERBS
+            .iter()
+            .find_map(|verb| lookup(verb).map(|path| (verb.to_uppercase(), path)))
+            .or_else(|| Some((lookup("custom.kind")?, lookup("custom.path")?)))?;
+
+        Some(HttpRule {
+            method,
+            path,
+            body: lookup("body"),
+            additional_bindings: Vec::new(),
+        })
+    }
+}
+
+/// Collects the string-valued fields of a protobuf text-format aggregate
+/// literal as `(dotted.key, value)` pairs: first those of the literal itself,
+/// then those of each of its top-level `additional_bindings` blocks, with keys
+/// relative to the block.
+fn aggregate_string_fields(literal: &str) -> Vec<Vec<(String, String)>> {
+    let mut blocks = vec![Vec::new()];
+    let mut path: Vec<String> = Vec::new();
+    let mut pending_key: Option<String> = None;
+    // The depth of the `additional_bindings` block being read, if any.
+    let mut binding_depth: Option<usize> = None;
+    let mut chars = literal.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let key = pending_key.take().unwrap_or_default();
+                if binding_depth.is_none() && path.len() == 1 && key == "additional_bindings" {
+                    binding_depth = Some(path.len());
+                    blocks.push(Vec::new());
+                }
+                path.push(key);
+            }
+            '}' => {
+                path.pop();
+                if binding_depth == Some(path.len()) {
+                    binding_depth = None;
+                }
+            }
+            '"' | '\'' => {
+                let mut value = String::new();
+                while let Some(v) = chars.next() {
+                    match v {
+                        '\\' => value.extend(chars.next()),
+                        v if v == c => break,
+                        v => value.push(v),
+                    }
+                }
+                if let Some(key) = pending_key.take() {
+                    // Keys start below the literal or the binding block.
+                    let skip = binding_depth.map_or(1, |depth| depth + 1);
+                    let mut dotted: Vec<&str> =
+                        path.iter().skip(skip).map(String::as_str).collect();
+                    dotted.push(&key);
+                    let block = if binding_depth.is_some() {
+                        blocks.len() - 1
+                    } else {
+                        0
+                    };
+                    blocks[block].push((dotted.join("."), value));
+                }
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut ident = String::from(c);
+                while let Some(&n) = chars.peek() {
+                    if !(n.is_alphanumeric() || n == '_') {
+                        break;
+                    }
+                    ident.push(n);
+                    chars.next();
+                }
+                pending_key = Some(ident);
+            }
+            _ => {}
+        }
+    }
+
+    blocks
+}
+
+impl OptionEntry {
+    /// Returns the assigned value with its surrounding quotes removed if the
+    /// constant is a string literal, or `None` for any other constant.
+    pub fn string_value(&self) -> Option<&str> {
+        let value = self.value.trim();
+        value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+    }
+}
+
+impl fmt::Display for RpcShape {
+    /// Formats the shape as the conventional gRPC terminology.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Self::Unary => "unary",
+            Self::ServerStreaming => "server-streaming",
+            Self::ClientStreaming => "client-streaming",
+            Self::Bidirectional => "bidirectional streaming",
+        };
+        f.write_str(s)
+    }
+}
+
 impl fmt::Display for CardinalityKind {
     /// Formats the enum variant into its canonical, lowercase protobuf keyword
     /// token string sequence.
@@ -40,6 +40,82 @@ pub struct ElementMeta {
     /// A collection of chronological adjacent comment blocks serving as the
     /// active documentation for this element.
     pub documentation: Vec<CommentBlock>,
+
+    /// The comments following the element on the line where it opens or
+    /// closes, such as `int32 id = 1; // The id.`.
+    pub trailing_comments: Vec<CommentBlock>,
+
+    /// The options declared directly on this element, either as bracketed
+    /// field/enum value options or as `option` statements inside its body.
+    pub options: Vec<OptionEntry>,
+}
+
+/// Represents a single option assignment attached to an element or to the
+/// file itself.
+///
+/// # Examples
+///
+/// ```protobuf
+/// option java_package = "com.book";
+/// string title = 1 [json_name = "bookTitle"];
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionEntry {
+    /// The option name exactly as written, without surrounding whitespace
+    /// (e.g., `"json_name"`, `"(google.api.http)"`, `"features.field_presence"`).
+    pub name: String,
+
+    /// The raw source text of the assigned constant, including quotes for
+    /// string literals and braces for block literals.
+    pub value: String,
+
+    /// The text range spanning the complete option assignment.
+    pub range: Range,
+
+    /// The text range spanning strictly the assigned constant.
+    pub value_range: Range,
+}
+
+/// The language revision a protobuf file is written against, as declared by
+/// its leading `syntax` or `edition` statement.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SyntaxKind {
+    /// `syntax = "proto2";`, which is also the implied default when a file
+    /// declares neither `syntax` nor `edition`.
+    #[default]
+    Proto2,
+
+    /// `syntax = "proto3";`
+    Proto3,
+
+    /// `edition = "2023";`
+    Editions {
+        /// The declared edition year without quotes.
+        ///
+        /// # Example
+        ///
+        /// ```text
+        /// 2023
+        /// ```
+        edition: String,
+    },
+}
+
+/// The file-level results of running the extraction pipeline over a single
+/// protobuf document.
+#[derive(Debug, Clone, Default)]
+pub struct MetaModel {
+    /// The package namespace of the file, empty when undeclared.
+    pub package: String,
+
+    /// The declared language revision of the file.
+    pub syntax: SyntaxKind,
+
+    /// The `option` statements declared at the root of the file.
+    pub options: Vec<OptionEntry>,
+
+    /// The flat hierarchical registry of every extracted element.
+    pub elements: Vec<ModelElement>,
 }
 
 /// Specifies the explicit iteration or optional presence strategy applied to a
@@ -99,6 +175,40 @@ pub struct StreamModifier {
     pub range: Range,
 }
 
+/// Classifies an RPC by which of its sides are declared with the `stream`
+/// modifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpcShape {
+    /// A single request answered by a single response.
+    Unary,
+
+    /// A single request answered by a stream of responses.
+    ServerStreaming,
+
+    /// A stream of requests answered by a single response.
+    ClientStreaming,
+
+    /// Independent request and response streams.
+    Bidirectional,
+}
+
+/// The REST mapping declared on an RPC through the `google.api.http` option.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRule {
+    /// The HTTP verb in upper case (e.g., `GET`), or the `kind` of a `custom`
+    /// pattern.
+    pub method: String,
+
+    /// The URL path template (e.g., `/v1/{name=shelves/*}`).
+    pub path: String,
+
+    /// The request field mapped to the HTTP body, `*` for the whole request.
+    pub body: Option<String>,
+
+    /// The other mappings of the same RPC, declared in `additional_bindings`.
+    pub additional_bindings: Vec<HttpRule>,
+}
+
 /// Represents a syntactic reference to a type name within a protobuf schema
 /// (e.g., a field type, a `map` key/value type, or an RPC request/response
 /// type).
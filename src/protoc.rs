@@ -1,15 +1,101 @@
+use std::collections::HashMap;
 use std::process::Command;
+use std::time::Duration;
 
-use async_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Range};
+use async_lsp::ClientSocket;
+use async_lsp::lsp_types::{DiagnosticSeverity, Url};
+use tokio::task::JoinHandle;
 use tree_sitter::Point;
 
-use crate::utils::to_lsp_position;
+/// A message printed by protoc.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtocDiagnostic {
+    /// The file the message is about, as named by protoc: relative to the
+    /// include path it was found in.
+    pub file: String,
+    /// Zero-based location of the message, if protoc gave one.
+    pub position: Option<Point>,
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+}
 
-pub fn collect_diagnostics(
-    protoc_path: &str,
-    file_path: &str,
-    include_paths: &[String],
-) -> Vec<Diagnostic> {
+/// A protoc invocation checking one file.
+#[derive(Debug, Clone)]
+pub struct ProtocRun {
+    pub uri: Url,
+    pub protoc_path: String,
+    pub file_path: String,
+    pub include_paths: Vec<String>,
+    pub timeout: Duration,
+    /// Hash of the content of the file and of everything it imports.
+    pub content_hash: u64,
+}
+
+/// Event emitted to the server when a background protoc run ends.
+pub struct ProtocFinished {
+    pub run: ProtocRun,
+    id: u64,
+    /// `None` if protoc could not run or timed out.
+    diagnostics: Option<Vec<ProtocDiagnostic>>,
+}
+
+/// Runs protoc in the background, at most once per file at a time, and
+/// remembers the last result of each file.
+#[derive(Default)]
+pub struct ProtocRunner {
+    next_id: u64,
+    running: HashMap<Url, (u64, JoinHandle<()>)>,
+    cache: HashMap<Url, (ProtocRun, Vec<ProtocDiagnostic>)>,
+}
+
+impl ProtocRunner {
+    /// Returns the diagnostics of a previous run with the same protoc, include
+    /// paths and content.
+    pub fn cached(&self, run: &ProtocRun) -> Option<Vec<ProtocDiagnostic>> {
+        let (previous, diagnostics) = self.cache.get(&run.uri)?;
+        (previous.content_hash == run.content_hash
+            && previous.protoc_path == run.protoc_path
+            && previous.include_paths == run.include_paths)
+            .then(|| diagnostics.clone())
+    }
+
+    /// Starts `run` in the background, cancelling the one still in flight for
+    /// the same file. [`ProtocFinished`] is emitted to `client` once it ends.
+    pub fn spawn(&mut self, client: ClientSocket, run: ProtocRun) {
+        self.next_id += 1;
+        let id = self.next_id;
+
+        let uri = run.uri.clone();
+        let handle = tokio::spawn(async move {
+            let diagnostics = collect_diagnostics_async(&run).await;
+            let _ = client.emit(ProtocFinished {
+                run,
+                id,
+                diagnostics,
+            });
+        });
+        if let Some((_, previous)) = self.running.insert(uri, (id, handle)) {
+            previous.abort();
+        }
+    }
+
+    /// Records the outcome of a run and returns its diagnostics, or `None` if
+    /// it failed or was superseded by a newer run.
+    pub fn finish(&mut self, event: ProtocFinished) -> Option<Vec<ProtocDiagnostic>> {
+        match self.running.get(&event.run.uri) {
+            Some((id, _)) if *id == event.id => {
+                self.running.remove(&event.run.uri);
+            }
+            _ => return None,
+        }
+        let diagnostics = event.diagnostics?;
+        self.cache
+            .insert(event.run.uri.clone(), (event.run, diagnostics.clone()));
+        Some(diagnostics)
+    }
+}
+
+fn protoc_command(protoc_path: &str, file_path: &str, include_paths: &[String]) -> Command {
     let mut cmd = Command::new(protoc_path);
 
     // Add include paths
@@ -23,17 +109,19 @@ pub fn collect_diagnostics(
 
     // Add the file to check
     cmd.arg(file_path);
+    cmd
+}
+
+pub fn collect_diagnostics(
+    protoc_path: &str,
+    file_path: &str,
+    include_paths: &[String],
+) -> Vec<ProtocDiagnostic> {
+    let mut cmd = protoc_command(protoc_path, file_path, include_paths);
 
-    // Run protoc and capture output
+    // Run protoc and capture output, warnings are printed on success too
     match cmd.output() {
-        Ok(output) => {
-            if output.status.success() {
-                Vec::new()
-            } else {
-                let error = String::from_utf8_lossy(&output.stderr);
-                parse_protoc_output(&error)
-            }
-        }
+        Ok(output) => parse_protoc_output(&String::from_utf8_lossy(&output.stderr)),
         Err(e) => {
             tracing::error!(error=%e, "failed to run protoc");
             Vec::new()
@@ -41,42 +129,83 @@ pub fn collect_diagnostics(
     }
 }
 
+/// Same as [`collect_diagnostics`] without blocking, killing protoc if it
+/// runs longer than the timeout of `run` or if the future is dropped.
+async fn collect_diagnostics_async(run: &ProtocRun) -> Option<Vec<ProtocDiagnostic>> {
+    let mut cmd = tokio::process::Command::from(protoc_command(
+        &run.protoc_path,
+        &run.file_path,
+        &run.include_paths,
+    ));
+    cmd.kill_on_drop(true);
+
+    match tokio::time::timeout(run.timeout, cmd.output()).await {
+        Ok(Ok(output)) => Some(parse_protoc_output(&String::from_utf8_lossy(
+            &output.stderr,
+        ))),
+        Ok(Err(e)) => {
+            tracing::error!(error=%e, "failed to run protoc");
+            None
+        }
+        Err(_) => {
+            tracing::warn!(uri=%run.uri, timeout=?run.timeout, "protoc timed out");
+            None
+        }
+    }
+}
+
 // Visible for testing
-fn parse_protoc_output(output: &str) -> Vec<Diagnostic> {
+fn parse_protoc_output(output: &str) -> Vec<ProtocDiagnostic> {
     let mut diagnostics = Vec::new();
 
     for line in output.lines() {
-        // Parse protoc error format: file:line:column: message
+        // Parse protoc error format: file[:line:column]: [warning: ]message
         if let Some((file_info, message)) = line.split_once(": ") {
-            let parts: Vec<&str> = file_info.split(':').collect();
-            if parts.len() >= 3
-                && let (Ok(line), Ok(col)) = (parts[1].parse::<u32>(), parts[2].parse::<u32>())
+            // Unused imports are reported natively, with the right severity.
+            if is_unused_import_warning(message) {
+                continue;
+            }
+            let (severity, message) = match message.strip_prefix("warning: ") {
+                Some(message) => (DiagnosticSeverity::WARNING, message),
+                None => (DiagnosticSeverity::ERROR, message),
+            };
+
+            let parts: Vec<&str> = file_info.rsplitn(3, ':').collect();
+            let (file, position) = if let [col, line, file] = parts.as_slice()
+                && let (Ok(line), Ok(col)) = (line.parse::<usize>(), col.parse::<usize>())
             {
                 let point = Point {
-                    row: (line - 1) as usize,
-                    column: (col - 1) as usize,
-                };
-                let diagnostic = Diagnostic {
-                    range: Range {
-                        start: to_lsp_position(point),
-                        end: to_lsp_position(Point {
-                            row: point.row,
-                            column: point.column + 1,
-                        }),
-                    },
-                    severity: Some(DiagnosticSeverity::ERROR),
-                    source: Some("protoc".to_string()),
-                    message: message.to_string(),
-                    ..Default::default()
+                    row: line.saturating_sub(1),
+                    column: col.saturating_sub(1),
                 };
-                diagnostics.push(diagnostic);
-            }
+                (*file, Some(point))
+            } else if std::path::Path::new(file_info)
+                .extension()
+                .is_some_and(|ext| ext == "proto")
+            {
+                (file_info, None)
+            } else {
+                continue;
+            };
+
+            diagnostics.push(ProtocDiagnostic {
+                file: file.to_string(),
+                position,
+                severity,
+                message: message.to_string(),
+            });
         }
     }
 
     diagnostics
 }
 
+fn is_unused_import_warning(message: &str) -> bool {
+    message
+        .strip_prefix("warning: Import ")
+        .is_some_and(|m| m.ends_with(" is unused."))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -87,12 +216,9 @@ mod test {
         let diags = parse_protoc_output(output);
         assert_eq!(diags.len(), 1);
         assert_eq!(diags[0].message, "Expected field name.");
-        assert_eq!(diags[0].source, Some("protoc".to_string()));
-        assert_eq!(diags[0].severity, Some(DiagnosticSeverity::ERROR));
-        assert_eq!(diags[0].range.start.line, 4);
-        assert_eq!(diags[0].range.start.character, 2);
-        assert_eq!(diags[0].range.end.line, 4);
-        assert_eq!(diags[0].range.end.character, 3);
+        assert_eq!(diags[0].file, "foo.proto");
+        assert_eq!(diags[0].severity, DiagnosticSeverity::ERROR);
+        assert_eq!(diags[0].position, Some(Point { row: 4, column: 2 }));
     }
 
     #[test]
@@ -102,6 +228,7 @@ mod test {
         assert_eq!(diags.len(), 2);
         assert_eq!(diags[0].message, "Syntax error.");
         assert_eq!(diags[1].message, "Unknown type.");
+        assert_eq!(diags[1].file, "b.proto");
     }
 
     #[test]
@@ -141,4 +268,92 @@ mod test {
             "'Foo' is not defined. It could be a typo for 'Bar'."
         );
     }
+
+    #[test]
+    fn test_parse_protoc_output_skips_unused_import() {
+        let output = "foo.proto:3:1: warning: Import bar.proto is unused.\n";
+        assert!(parse_protoc_output(output).is_empty());
+    }
+
+    #[test]
+    fn test_parse_protoc_output_warning() {
+        let output = "foo.proto:7:3: warning: Field name \"fooBar\" should be lowercase.\n";
+        let diags = parse_protoc_output(output);
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].severity, DiagnosticSeverity::WARNING);
+        assert_eq!(
+            diags[0].message,
+            "Field name \"fooBar\" should be lowercase."
+        );
+    }
+
+    #[test]
+    fn test_parse_protoc_output_without_location() {
+        let output = "dir/bar.proto: File not found.\n";
+        let diags = parse_protoc_output(output);
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].file, "dir/bar.proto");
+        assert_eq!(diags[0].position, None);
+        assert_eq!(diags[0].message, "File not found.");
+    }
+
+    fn run(content_hash: u64) -> ProtocRun {
+        ProtocRun {
+            uri: "file:///foo.proto".parse().unwrap(),
+            protoc_path: "protoc".to_string(),
+            file_path: "/foo.proto".to_string(),
+            include_paths: vec!["/".to_string()],
+            timeout: Duration::from_secs(10),
+            content_hash,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_protoc_runner_keeps_latest_run() {
+        let mut runner = ProtocRunner::default();
+        let handle = tokio::spawn(async {});
+        runner.running.insert(run(1).uri, (2, handle));
+
+        let diagnostics = parse_protoc_output("foo.proto:1:1: Syntax error.\n");
+        let superseded = ProtocFinished {
+            run: run(1),
+            id: 1,
+            diagnostics: Some(diagnostics.clone()),
+        };
+        assert_eq!(runner.finish(superseded), None);
+        assert_eq!(runner.cached(&run(1)), None);
+
+        let latest = ProtocFinished {
+            run: run(1),
+            id: 2,
+            diagnostics: Some(diagnostics.clone()),
+        };
+        assert_eq!(runner.finish(latest), Some(diagnostics.clone()));
+        assert_eq!(runner.cached(&run(1)), Some(diagnostics));
+        // Content changed since.
+        assert_eq!(runner.cached(&run(2)), None);
+    }
+
+    #[tokio::test]
+    async fn test_collect_diagnostics_async_missing_protoc() {
+        let mut run = run(1);
+        run.protoc_path = "/nonexistent/protoc".to_string();
+        assert_eq!(collect_diagnostics_async(&run).await, None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_collect_diagnostics_async_timeout() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let protoc = dir.path().join("protoc");
+        std::fs::write(&protoc, "#!/bin/sh\nsleep 10\n").unwrap();
+        std::fs::set_permissions(&protoc, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut run = run(1);
+        run.protoc_path = protoc.to_str().unwrap().to_string();
+        run.timeout = Duration::from_millis(100);
+        assert_eq!(collect_diagnostics_async(&run).await, None);
+    }
 }
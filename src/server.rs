@@ -2,20 +2,29 @@ use async_lsp::{
     ClientSocket,
     lsp_types::{
         notification::{
-            DidChangeTextDocument, DidCreateFiles, DidDeleteFiles, DidOpenTextDocument,
-            DidRenameFiles, DidSaveTextDocument, Exit, SetTrace,
+            DidChangeTextDocument, DidChangeWatchedFiles, DidCreateFiles, DidDeleteFiles,
+            DidOpenTextDocument, DidRenameFiles, DidSaveTextDocument, Exit, Initialized, SetTrace,
         },
         request::{
-            Completion, DocumentSymbolRequest, Formatting, GotoDefinition, HoverRequest,
-            Initialize, PrepareRenameRequest, RangeFormatting, References, Rename, Shutdown,
-            WorkspaceSymbolRequest,
+            CallHierarchyIncomingCalls, CallHierarchyOutgoingCalls, CallHierarchyPrepare,
+            CodeActionRequest, Completion, DocumentHighlightRequest, DocumentSymbolRequest,
+            ExecuteCommand, FoldingRangeRequest, Formatting, GotoDeclaration, GotoDefinition,
+            GotoTypeDefinition, HoverRequest, Initialize, InlayHintRequest, OnTypeFormatting,
+            PrepareRenameRequest, RangeFormatting, References, Rename, SelectionRangeRequest,
+            Shutdown, WillRenameFiles, WorkspaceSymbolRequest,
         },
     },
     router::Router,
 };
 use std::{ops::ControlFlow, path::PathBuf};
 
-use crate::{config::WorkspaceProtoConfigs, log, state::ProtoLanguageState};
+use crate::{
+    baseline::{BaselineLoaded, Baselines},
+    config::WorkspaceProtoConfigs,
+    log,
+    protoc::{ProtocFinished, ProtocRunner},
+    state::ProtoLanguageState,
+};
 
 pub struct TickEvent;
 pub struct ProtoLanguageServer {
@@ -24,6 +33,15 @@ pub struct ProtoLanguageServer {
     pub counter: i32,
     pub state: ProtoLanguageState,
     pub configs: WorkspaceProtoConfigs,
+    pub protoc: ProtocRunner,
+    /// Breaking change baselines of the workspaces.
+    pub baselines: Baselines,
+    /// Whether the client applies workspace edits with change annotations,
+    /// which renames spanning several files are previewed with.
+    pub change_annotations: bool,
+    /// Whether the client reports changes to files registered with it, which
+    /// the baselines are reloaded on.
+    pub watched_files: bool,
     pub shutdown_received: bool,
 }
 
@@ -40,6 +58,10 @@ impl ProtoLanguageServer {
             counter: 0,
             state: ProtoLanguageState::new(),
             configs: WorkspaceProtoConfigs::new(cli_include_paths, fallback_include_path),
+            protoc: ProtocRunner::default(),
+            baselines: Baselines::default(),
+            change_annotations: false,
+            watched_files: false,
             shutdown_received: false,
         });
 
@@ -47,6 +69,8 @@ impl ProtoLanguageServer {
             st.counter += 1;
             ControlFlow::Continue(())
         });
+        router.event::<ProtocFinished>(ProtoLanguageServer::protoc_finished);
+        router.event::<BaselineLoaded>(ProtoLanguageServer::baseline_loaded);
 
         // Ignore any unknown notification.
         router.unhandled_notification(|_, notif| {
@@ -63,12 +87,26 @@ impl ProtoLanguageServer {
         router.request::<Rename, _>(ProtoLanguageServer::rename);
         router.request::<References, _>(ProtoLanguageServer::references);
         router.request::<GotoDefinition, _>(ProtoLanguageServer::definition);
+        router.request::<InlayHintRequest, _>(ProtoLanguageServer::inlay_hint);
+        router.request::<GotoTypeDefinition, _>(ProtoLanguageServer::type_definition);
+        router.request::<GotoDeclaration, _>(ProtoLanguageServer::declaration);
+        router.request::<DocumentHighlightRequest, _>(ProtoLanguageServer::document_highlight);
+        router.request::<CallHierarchyPrepare, _>(ProtoLanguageServer::prepare_call_hierarchy);
+        router.request::<CallHierarchyIncomingCalls, _>(ProtoLanguageServer::incoming_calls);
+        router.request::<CallHierarchyOutgoingCalls, _>(ProtoLanguageServer::outgoing_calls);
         router.request::<DocumentSymbolRequest, _>(|st, params| st.document_symbol(params));
+        router.request::<FoldingRangeRequest, _>(|st, params| st.folding_range(params));
+        router.request::<SelectionRangeRequest, _>(|st, params| st.selection_range(params));
         router.request::<WorkspaceSymbolRequest, _>(ProtoLanguageServer::workspace_symbol);
         router.request::<Formatting, _>(ProtoLanguageServer::formatting);
         router.request::<RangeFormatting, _>(ProtoLanguageServer::range_formatting);
+        router.request::<OnTypeFormatting, _>(|st, params| st.on_type_formatting(params));
+        router.request::<CodeActionRequest, _>(|st, params| st.code_action(params));
+        router.request::<ExecuteCommand, _>(ProtoLanguageServer::execute_command);
+        router.request::<WillRenameFiles, _>(|st, params| st.will_rename_files(params));
 
         // Handling notification
+        router.notification::<Initialized>(ProtoLanguageServer::initialized);
         router.notification::<SetTrace>(ProtoLanguageServer::set_trace);
         router.notification::<DidSaveTextDocument>(ProtoLanguageServer::did_save);
         router.notification::<DidOpenTextDocument>(ProtoLanguageServer::did_open);
@@ -76,6 +114,7 @@ impl ProtoLanguageServer {
         router.notification::<DidCreateFiles>(ProtoLanguageServer::did_create_files);
         router.notification::<DidRenameFiles>(ProtoLanguageServer::did_rename_files);
         router.notification::<DidDeleteFiles>(ProtoLanguageServer::did_delete_files);
+        router.notification::<DidChangeWatchedFiles>(ProtoLanguageServer::did_change_watched_files);
         router.notification::<Exit>(ProtoLanguageServer::exit);
 
         router
@@ -0,0 +1,222 @@
+//! Breaking changes of the workspace against a baseline version of it.
+
+use std::path::{Path, PathBuf};
+
+use async_lsp::lsp_types::{
+    Diagnostic, DiagnosticSeverity, NumberOrString, Range, Url, WorkspaceFolder,
+};
+
+use crate::config::WorkspaceProtoConfigs;
+use crate::descriptor::{
+    Breakage, BreakingChange, FileDescriptor, breaking_changes, decode_file_descriptor_set,
+};
+use crate::document::ProtoDocument;
+use crate::state::ProtoLanguageState;
+use crate::utils::to_lsp_range;
+
+impl ProtoLanguageState {
+    /// Indexes every proto file under `root` with the include paths
+    /// `configs` gives it, and returns their descriptors sorted by name.
+    pub fn compile_workspace(
+        &mut self,
+        root: &Path,
+        configs: &WorkspaceProtoConfigs,
+    ) -> Vec<(Url, FileDescriptor)> {
+        self.parse_all_from_workspace(root, None);
+
+        let mut files: Vec<(Url, Vec<PathBuf>)> = self
+            .get_documents()
+            .into_iter()
+            .filter(|document| {
+                document
+                    .uri
+                    .to_file_path()
+                    .is_ok_and(|path| path.starts_with(root))
+            })
+            .filter_map(|document| {
+                let ipath = configs.get_include_paths(&document.uri)?;
+                Some((document.uri, ipath))
+            })
+            .collect();
+        files.sort();
+
+        // Index the imports from outside the workspace too.
+        for (uri, ipath) in &files {
+            let content = self.get_content(uri);
+            self.upsert_content(uri, &content, ipath, 8);
+        }
+
+        let mut descriptors: Vec<(Url, FileDescriptor)> = files
+            .into_iter()
+            .filter_map(|(uri, ipath)| {
+                let document = self.get_document(&uri)?;
+                Some((uri, self.compile_document(&document, &ipath).0))
+            })
+            .collect();
+        descriptors.sort_by(|(_, a), (_, b)| a.name.cmp(&b.name));
+        descriptors
+    }
+
+    /// Reports the breaking changes of `document` against the version of the
+    /// same file in `baseline`, as warnings. Nothing is reported while the
+    /// document has syntax errors. The descriptor of the semantic pass is
+    /// reused when there is one.
+    pub fn breaking_diagnostics(
+        &self,
+        document: &ProtoDocument,
+        ipath: &[PathBuf],
+        baseline: &[FileDescriptor],
+    ) -> Vec<Diagnostic> {
+        if document.tree.root_node().has_error() {
+            return vec![];
+        }
+        let compiled = self.compiled.write().expect("poison").remove(&document.uri);
+        let current = compiled.unwrap_or_else(|| self.compile_document(document, ipath).0);
+        let Some(old) = baseline.iter().find(|file| file.name == current.name) else {
+            return vec![];
+        };
+
+        breaking_changes(std::slice::from_ref(old), std::slice::from_ref(&current))
+            .iter()
+            .map(|change| breaking_diagnostic(change, breaking_change_range(document, change)))
+            .collect()
+    }
+}
+
+/// Loads the baseline at `path`: a `FileDescriptorSet`, or a directory
+/// indexed as a workspace with its own `protols.toml` and the extra include
+/// paths of `configs`.
+pub fn load_baseline(
+    path: &Path,
+    configs: &WorkspaceProtoConfigs,
+) -> Result<Vec<FileDescriptor>, String> {
+    if path.is_file() {
+        let bytes = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        return decode_file_descriptor_set(&bytes).map_err(|e| format!("{}: {e}", path.display()));
+    }
+    if !path.is_dir() {
+        return Err(format!("{}: no such file or directory", path.display()));
+    }
+
+    let root = std::path::absolute(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let uri = Url::from_directory_path(&root)
+        .map_err(|()| format!("{}: not a valid baseline path", path.display()))?;
+    let name = root
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let configs = configs.for_root(&WorkspaceFolder { uri, name });
+
+    Ok(ProtoLanguageState::new()
+        .compile_workspace(&root, &configs)
+        .into_iter()
+        .map(|(_, descriptor)| descriptor)
+        .collect())
+}
+
+/// Returns where to show `change` in `document`: on the name of the
+/// definition it is anchored on, or else on the package statement.
+pub fn breaking_change_range(document: &ProtoDocument, change: &BreakingChange) -> Range {
+    if let Some(anchor) = &change.anchor
+        && let Some(element) = document
+            .elements
+            .iter()
+            .find(|e| e.kind.fqn() == Some(anchor.as_str()))
+    {
+        return element.meta.selection_range;
+    }
+    let root = document.tree.root_node();
+    root.children(&mut root.walk())
+        .find(|n| n.kind() == "package")
+        .map(to_lsp_range)
+        .unwrap_or_default()
+}
+
+/// A warning about `change`, identified by its rule.
+pub fn breaking_diagnostic(change: &BreakingChange, range: Range) -> Diagnostic {
+    let compatibility = match change.breakage {
+        Breakage::Wire => "wire",
+        Breakage::Source => "source",
+    };
+    Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::WARNING),
+        code: Some(NumberOrString::String(change.rule.to_string())),
+        source: Some(String::from("protols")),
+        message: format!(
+            "{} This breaks {compatibility} compatibility with the baseline.",
+            change.message
+        ),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use async_lsp::lsp_types::{Url, WorkspaceFolder};
+    use insta::assert_yaml_snapshot;
+
+    use super::load_baseline;
+    use crate::config::{Config, WorkspaceProtoConfigs};
+    use crate::descriptor::{breaking_changes, encode_file_descriptor_set};
+    use crate::state::{ProtoLanguageState, fixture_dir};
+
+    fn input(version: &str) -> PathBuf {
+        fixture_dir("breaking").join(version)
+    }
+
+    fn configs(root: &PathBuf) -> WorkspaceProtoConfigs {
+        let mut configs = WorkspaceProtoConfigs::new(vec![], None);
+        configs.add_workspace(&WorkspaceFolder {
+            uri: Url::from_directory_path(root).unwrap(),
+            name: String::from("current"),
+        });
+        configs
+    }
+
+    #[test]
+    fn test_breaking_changes() {
+        let current_root = input("current");
+        let configs = configs(&current_root);
+        let baseline = load_baseline(&input("baseline"), &configs).unwrap();
+        let current: Vec<_> = ProtoLanguageState::new()
+            .compile_workspace(&current_root, &configs)
+            .into_iter()
+            .map(|(_, descriptor)| descriptor)
+            .collect();
+
+        let changes = breaking_changes(&baseline, &current);
+        assert_yaml_snapshot!(changes);
+
+        // A descriptor set of the baseline gives the same changes.
+        let snapshot = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(snapshot.path(), encode_file_descriptor_set(&baseline)).unwrap();
+        let decoded = load_baseline(snapshot.path(), &configs).unwrap();
+        assert_eq!(breaking_changes(&decoded, &current), changes);
+
+        assert!(breaking_changes(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn test_breaking_diagnostics() {
+        let current_root = input("current");
+        let configs = configs(&current_root);
+        let baseline = load_baseline(&input("baseline"), &configs).unwrap();
+
+        let mut state = ProtoLanguageState::new();
+        let uri = Url::from_file_path(current_root.join("api.proto")).unwrap();
+        let ipath = configs.get_include_paths(&uri).unwrap();
+        state.upsert_fixture(&current_root, "api.proto", &ipath, &Config::default());
+
+        let document = state.get_document(&uri).unwrap();
+        assert_yaml_snapshot!(state.breaking_diagnostics(&document, &ipath, &baseline));
+    }
+
+    #[test]
+    fn test_load_missing_baseline() {
+        let configs = configs(&input("current"));
+        assert!(load_baseline(&input("missing"), &configs).is_err());
+    }
+}
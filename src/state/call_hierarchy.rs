@@ -0,0 +1,321 @@
+//! Type usage hierarchy exposed through the LSP call hierarchy requests.
+//!
+//! Protobuf has no calls, but its containment graph answers the same
+//! question: which definitions are affected when a type changes. Incoming
+//! calls of a message or enum are the messages embedding it as a field and the
+//! rpcs using it as request or response ("used by"); outgoing calls are the
+//! message and enum types its own fields depend on ("uses"). Clients expand
+//! either direction one level per request, which makes the whole graph
+//! navigable transitively.
+
+use async_lsp::lsp_types::{
+    CallHierarchyIncomingCall, CallHierarchyItem, CallHierarchyOutgoingCall, Position, SymbolKind,
+    SymbolTag, Url,
+};
+use serde_json::Value;
+
+use crate::docs;
+use crate::document::ProtoDocument;
+use crate::model::{ElementKind, ModelElement};
+use crate::state::ProtoLanguageState;
+
+impl ProtoLanguageState {
+    /// Resolves the message, enum or rpc under `position` (either its
+    /// declaration or a type reference to it) into hierarchy items.
+    pub fn prepare_call_hierarchy(&self, uri: &Url, position: Position) -> Vec<CallHierarchyItem> {
+        let Some(fqn) = self.resolve_target_fqn(uri, position) else {
+            return vec![];
+        };
+
+        self.lookup_fqn(&fqn)
+            .iter()
+            .filter_map(|target| hierarchy_item(&target.uri, &target.element))
+            .collect()
+    }
+
+    /// Returns the messages and rpcs referencing the type behind `item`,
+    /// grouped per user with the ranges of each referencing type token.
+    pub fn incoming_calls(&self, item: &CallHierarchyItem) -> Vec<CallHierarchyIncomingCall> {
+        let Some(target_fqn) = item_fqn(item) else {
+            return vec![];
+        };
+
+        let mut calls: Vec<CallHierarchyIncomingCall> = Vec::new();
+        for document in self.get_documents() {
+            for element in &document.elements {
+                let scope = element.kind.fqn().unwrap_or(&document.package);
+                for type_ref in element.kind.type_references() {
+                    let resolves_to_target = self
+                        .resolve_reference(scope, &type_ref.name)
+                        .iter()
+                        .any(|r| r.element.kind.fqn() == Some(target_fqn));
+                    if !resolves_to_target {
+                        continue;
+                    }
+
+                    let Some(from) = using_element(&document, element)
+                        .and_then(|user| hierarchy_item(&document.uri, user))
+                    else {
+                        continue;
+                    };
+
+                    match calls.iter_mut().find(|call| same_item(&call.from, &from)) {
+                        Some(call) => call.from_ranges.push(type_ref.range),
+                        None => calls.push(CallHierarchyIncomingCall {
+                            from,
+                            from_ranges: vec![type_ref.range],
+                        }),
+                    }
+                }
+            }
+        }
+
+        calls.sort_by(|a, b| item_order(&a.from).cmp(&item_order(&b.from)));
+        calls
+    }
+
+    /// Returns the message and enum types the fields of `item` (or the
+    /// request and response of an rpc) depend on, grouped per type with the
+    /// ranges of each referencing type token.
+    pub fn outgoing_calls(&self, item: &CallHierarchyItem) -> Vec<CallHierarchyOutgoingCall> {
+        let Some(target_fqn) = item_fqn(item) else {
+            return vec![];
+        };
+        let Some(document) = self.get_document(&item.uri) else {
+            return vec![];
+        };
+        let Some(source) = document
+            .elements
+            .iter()
+            .find(|e| e.kind.fqn() == Some(target_fqn))
+        else {
+            return vec![];
+        };
+
+        let dependants: Vec<&ModelElement> = match source.kind {
+            ElementKind::Rpc { .. } => vec![source],
+            ElementKind::Message { .. } => document
+                .elements
+                .iter()
+                .filter(|e| {
+                    !matches!(e.kind, ElementKind::Rpc { .. })
+                        && using_element(&document, e).is_some_and(|m| m.id == source.id)
+                })
+                .collect(),
+            _ => vec![],
+        };
+
+        let mut calls: Vec<CallHierarchyOutgoingCall> = Vec::new();
+        for element in dependants {
+            let scope = element.kind.fqn().unwrap_or(&document.package);
+            for type_ref in element.kind.type_references() {
+                if docs::BUILTIN.contains_key(type_ref.name.as_str()) {
+                    continue;
+                }
+                let Some(to) = self
+                    .resolve_reference(scope, &type_ref.name)
+                    .into_iter()
+                    .find_map(|r| hierarchy_item(&r.uri, &r.element))
+                else {
+                    continue;
+                };
+
+                match calls.iter_mut().find(|call| same_item(&call.to, &to)) {
+                    Some(call) => call.from_ranges.push(type_ref.range),
+                    None => calls.push(CallHierarchyOutgoingCall {
+                        to,
+                        from_ranges: vec![type_ref.range],
+                    }),
+                }
+            }
+        }
+
+        calls.sort_by(|a, b| item_order(&a.to).cmp(&item_order(&b.to)));
+        calls
+    }
+}
+
+/// Returns the definition that owns a type reference made by `element`: the
+/// rpc itself, or the closest message enclosing a field (skipping oneofs).
+fn using_element<'a>(
+    document: &'a ProtoDocument,
+    element: &'a ModelElement,
+) -> Option<&'a ModelElement> {
+    if matches!(element.kind, ElementKind::Rpc { .. }) {
+        return Some(element);
+    }
+
+    let mut current = element;
+    while let Some(parent) = current.parent_id.and_then(|id| document.elements.get(id)) {
+        if matches!(parent.kind, ElementKind::Message { .. }) {
+            return Some(parent);
+        }
+        if !matches!(parent.kind, ElementKind::Oneof { .. }) {
+            return None;
+        }
+        current = parent;
+    }
+    None
+}
+
+/// Builds a hierarchy item for messages, enums and rpcs, carrying the FQN in
+/// `data` so follow-up requests can resolve it again.
+fn hierarchy_item(uri: &Url, element: &ModelElement) -> Option<CallHierarchyItem> {
+    if !matches!(
+        element.kind,
+        ElementKind::Message { .. } | ElementKind::Enum { .. } | ElementKind::Rpc { .. }
+    ) {
+        return None;
+    }
+    let fqn = element.kind.fqn()?;
+
+    Some(CallHierarchyItem {
+        name: element.meta.name.clone(),
+        kind: SymbolKind::from(&element.kind),
+        tags: element
+            .kind
+            .is_deprecated()
+            .then(|| vec![SymbolTag::DEPRECATED]),
+        detail: Some(fqn.to_string()),
+        uri: uri.clone(),
+        range: element.meta.range,
+        selection_range: element.meta.selection_range,
+        data: Some(Value::String(fqn.to_string())),
+    })
+}
+
+fn item_fqn(item: &CallHierarchyItem) -> Option<&str> {
+    match &item.data {
+        Some(Value::String(fqn)) => Some(fqn),
+        _ => item.detail.as_deref(),
+    }
+}
+
+fn same_item(a: &CallHierarchyItem, b: &CallHierarchyItem) -> bool {
+    a.uri == b.uri && a.selection_range == b.selection_range
+}
+
+fn item_order(item: &CallHierarchyItem) -> (&str, Position) {
+    (item.uri.as_str(), item.selection_range.start)
+}
+
+#[cfg(test)]
+mod test {
+    use async_lsp::lsp_types::{Position, Url};
+    use insta::assert_yaml_snapshot;
+    use std::path::PathBuf;
+
+    use crate::config::Config;
+    use crate::state::ProtoLanguageState;
+
+    fn setup() -> (Url, Url, ProtoLanguageState) {
+        let ipath = vec![PathBuf::from("src/state/input/hierarchy")];
+        let types_uri: Url = "file://input/hierarchy/types.proto".parse().unwrap();
+        let service_uri: Url = "file://input/hierarchy/service.proto".parse().unwrap();
+
+        let mut state = ProtoLanguageState::new();
+        state.upsert_file(
+            &types_uri,
+            include_str!("input/hierarchy/types.proto"),
+            &ipath,
+            2,
+            &Config::default(),
+            false,
+        );
+        state.upsert_file(
+            &service_uri,
+            include_str!("input/hierarchy/service.proto"),
+            &ipath,
+            2,
+            &Config::default(),
+            false,
+        );
+        (types_uri, service_uri, state)
+    }
+
+    #[test]
+    fn test_prepare_call_hierarchy() {
+        let (types_uri, service_uri, state) = setup();
+
+        // On the `Author` declaration.
+        assert_yaml_snapshot!(state.prepare_call_hierarchy(
+            &types_uri,
+            Position {
+                line: 9,
+                character: 9
+            }
+        ));
+        // On the `Book` response type of `GetBook`.
+        assert_yaml_snapshot!(state.prepare_call_hierarchy(
+            &service_uri,
+            Position {
+                line: 7,
+                character: 32
+            }
+        ));
+        // Fields are not part of the hierarchy.
+        assert!(
+            state
+                .prepare_call_hierarchy(
+                    &types_uri,
+                    Position {
+                        line: 14,
+                        character: 10
+                    }
+                )
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_incoming_calls_transitive() {
+        let (types_uri, _, state) = setup();
+
+        let author = state
+            .prepare_call_hierarchy(
+                &types_uri,
+                Position {
+                    line: 9,
+                    character: 9,
+                },
+            )
+            .remove(0);
+
+        let used_by_author = state.incoming_calls(&author);
+        assert_yaml_snapshot!(used_by_author);
+
+        let book = used_by_author
+            .iter()
+            .find(|call| call.from.name == "Book")
+            .map(|call| call.from.clone())
+            .unwrap();
+        assert_yaml_snapshot!(state.incoming_calls(&book));
+    }
+
+    #[test]
+    fn test_outgoing_calls() {
+        let (types_uri, service_uri, state) = setup();
+
+        let book = state
+            .prepare_call_hierarchy(
+                &types_uri,
+                Position {
+                    line: 13,
+                    character: 9,
+                },
+            )
+            .remove(0);
+        assert_yaml_snapshot!(state.outgoing_calls(&book));
+
+        let get_book = state
+            .prepare_call_hierarchy(
+                &service_uri,
+                Position {
+                    line: 7,
+                    character: 8,
+                },
+            )
+            .remove(0);
+        assert_yaml_snapshot!(state.outgoing_calls(&get_book));
+    }
+}
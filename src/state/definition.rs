@@ -5,6 +5,7 @@ use async_lsp::lsp_types::{Location, Position, Range, Url};
 use crate::{
     model::{ElementKind, SpatialEntry},
     state::ProtoLanguageState,
+    utils::{is_position_inside_range, resolve_import},
 };
 
 impl ProtoLanguageState {
@@ -44,6 +45,112 @@ impl ProtoLanguageState {
         };
         self.declarations_for_fqn(&fqn)
     }
+
+    /// Resolves the type(s) of the symbol under `position`.
+    ///
+    /// On a field name this jumps to the field's message or enum type (the
+    /// value type for maps), and on an rpc name to both its request and
+    /// response types. Anywhere else it behaves like
+    /// [`definition`](Self::definition) without import handling.
+    pub fn type_definition(&self, uri: &Url, pos: Position) -> Vec<Location> {
+        let Some(document) = self.get_document(uri) else {
+            return vec![];
+        };
+        let Some(SpatialEntry { element_id, .. }) = document.find_entry_at_position(pos) else {
+            return vec![];
+        };
+        let Some(element) = document.elements.get(*element_id) else {
+            return vec![];
+        };
+
+        if !is_position_inside_range(pos, element.meta.selection_range) {
+            return self
+                .resolve_target_fqn(uri, pos)
+                .map(|fqn| self.declarations_for_fqn(&fqn))
+                .unwrap_or_default();
+        }
+
+        let type_refs = match &element.kind {
+            ElementKind::Field { type_ref, .. } | ElementKind::OneofField { type_ref, .. } => {
+                vec![type_ref]
+            }
+            ElementKind::MapField { value_type_ref, .. } => vec![value_type_ref],
+            ElementKind::Rpc {
+                request_type_ref,
+                response_type_ref,
+                ..
+            } => vec![request_type_ref, response_type_ref],
+            _ => {
+                return element
+                    .kind
+                    .fqn()
+                    .map(|fqn| self.declarations_for_fqn(fqn))
+                    .unwrap_or_default();
+            }
+        };
+
+        let scope = element.kind.fqn().unwrap_or(&document.package);
+        let mut locations: Vec<Location> = Vec::new();
+        for type_ref in type_refs {
+            for target in self.resolve_reference(scope, &type_ref.name) {
+                let location = Location {
+                    uri: target.uri,
+                    range: target.element.meta.selection_range,
+                };
+                if !locations.contains(&location) {
+                    locations.push(location);
+                }
+            }
+        }
+        locations
+    }
+
+    /// Resolves the declaration site of the symbol under `position` as seen
+    /// from the current file.
+    ///
+    /// An `import` statement is declared by the file it imports. A type
+    /// reference to a symbol defined in another file is declared by the
+    /// `import` statement that makes that file visible here. Symbols declared
+    /// locally, or reached without a matching import, fall back to
+    /// [`definition`](Self::definition).
+    pub fn declaration(&self, uri: &Url, pos: Position, ipath: &[PathBuf]) -> Vec<Location> {
+        let Some(document) = self.get_document(uri) else {
+            return vec![];
+        };
+        let Some(SpatialEntry { element_id, .. }) = document.find_entry_at_position(pos) else {
+            return vec![];
+        };
+        let Some(element) = document.elements.get(*element_id) else {
+            return vec![];
+        };
+
+        if matches!(element.kind, ElementKind::Import { .. })
+            || is_position_inside_range(pos, element.meta.selection_range)
+        {
+            return self.definition(uri, pos, ipath);
+        }
+
+        let targets = self.definition(uri, pos, ipath);
+        let imports: Vec<Location> = targets
+            .iter()
+            .filter(|target| &target.uri != uri)
+            .filter_map(|target| {
+                document.elements.iter().find_map(|e| match &e.kind {
+                    ElementKind::Import { path }
+                        if resolve_import(path, ipath).as_ref() == Some(&target.uri) =>
+                    {
+                        Some(Location {
+                            uri: uri.clone(),
+                            range: e.meta.range,
+                        })
+                    }
+                    _ => None,
+                })
+            })
+            .collect();
+
+        if imports.is_empty() { targets } else { imports }
+    }
 }
 
 #[cfg(test)]
@@ -238,4 +345,148 @@ mod test {
                 .is_empty()
         );
     }
+
+    #[test]
+    fn test_type_definition() {
+        let (_ipath, a_uri, _b_uri, _c_uri, mut state) = setup_workspace();
+
+        // Cursor on the field name `author` -> its `Author` message.
+        assert_yaml_snapshot!(state.type_definition(
+            &a_uri,
+            Position {
+                line: 11,
+                character: 11
+            }
+        ));
+        // Cursor on the field name `foo` of a nested type -> `Author.Address`.
+        assert_yaml_snapshot!(state.type_definition(
+            &a_uri,
+            Position {
+                line: 12,
+                character: 19
+            }
+        ));
+
+        let ipath = vec![PathBuf::from("src/state/input/hierarchy")];
+        let types_uri: Url = "file://input/hierarchy/types.proto".parse().unwrap();
+        let service_uri: Url = "file://input/hierarchy/service.proto".parse().unwrap();
+        state.upsert_file(
+            &types_uri,
+            include_str!("input/hierarchy/types.proto"),
+            &ipath,
+            2,
+            &Config::default(),
+            false,
+        );
+        state.upsert_file(
+            &service_uri,
+            include_str!("input/hierarchy/service.proto"),
+            &ipath,
+            2,
+            &Config::default(),
+            false,
+        );
+
+        // Cursor on the rpc name `GetBook` -> request and response types.
+        assert_yaml_snapshot!(state.type_definition(
+            &service_uri,
+            Position {
+                line: 7,
+                character: 8
+            }
+        ));
+        // Cursor on a scalar field name -> nothing to jump to.
+        assert!(
+            state
+                .type_definition(
+                    &types_uri,
+                    Position {
+                        line: 10,
+                        character: 10
+                    }
+                )
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_declaration() {
+        let ipath = vec![std::env::current_dir().unwrap().join("src/state/input")];
+        let a_uri: Url = "file://input/a.proto".parse().unwrap();
+        let mut state: ProtoLanguageState = ProtoLanguageState::new();
+        state.upsert_file(
+            &a_uri,
+            include_str!("input/a.proto"),
+            &ipath,
+            2,
+            &Config::default(),
+            false,
+        );
+
+        // Cursor on the `Author` type reference -> the `import "b.proto"` line.
+        assert_yaml_snapshot!(state.declaration(
+            &a_uri,
+            Position {
+                line: 11,
+                character: 5
+            },
+            &ipath
+        ));
+
+        // Cursor on the `import "b.proto"` statement -> the imported file.
+        let loc = state.declaration(
+            &a_uri,
+            Position {
+                line: 5,
+                character: 10,
+            },
+            &ipath,
+        );
+        assert_yaml_snapshot!(loc, {"[0].uri" => insta::dynamic_redaction(|c, _| {
+            assert!(c.as_str().unwrap().ends_with("b.proto"));
+            "file://<redacted>/b.proto".to_string()
+        })});
+    }
+
+    #[test]
+    fn test_declaration_matches_resolved_import() {
+        let dir = std::env::current_dir()
+            .unwrap()
+            .join("src/state/input/declaration");
+        let ipath = vec![dir.clone()];
+        let uri = Url::from_file_path(dir.join("main.proto")).unwrap();
+        let mut state: ProtoLanguageState = ProtoLanguageState::new();
+        state.upsert_file(
+            &uri,
+            include_str!("input/declaration/main.proto"),
+            &ipath,
+            2,
+            &Config::default(),
+            false,
+        );
+
+        // `other/x.proto` declares `Remote`, not `x.proto` whose path it ends
+        // with.
+        let loc = state.declaration(
+            &uri,
+            Position {
+                line: 9,
+                character: 14,
+            },
+            &ipath,
+        );
+        assert_eq!(loc.len(), 1);
+        assert_eq!(loc[0].range.start.line, 5);
+
+        let loc = state.declaration(
+            &uri,
+            Position {
+                line: 8,
+                character: 14,
+            },
+            &ipath,
+        );
+        assert_eq!(loc.len(), 1);
+        assert_eq!(loc[0].range.start.line, 4);
+    }
 }
@@ -0,0 +1,131 @@
+//! `FileDescriptorSet` export of a file and of everything it imports.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use async_lsp::lsp_types::{DiagnosticSeverity, Url};
+
+use crate::descriptor::FileDescriptor;
+use crate::state::ProtoLanguageState;
+use crate::state::semantic::file_name;
+use crate::utils::resolve_import;
+
+/// How deep imports are followed when indexing the file to export.
+const MAX_IMPORT_DEPTH: usize = 64;
+
+impl ProtoLanguageState {
+    /// Builds the descriptors of `uri` and of every file it imports, directly
+    /// or not, dependencies first, as `protoc --include_imports` writes them.
+    ///
+    /// `uri` is indexed again with `content`, and its imports from the disk,
+    /// so that the set is complete whatever was indexed before. Errors are
+    /// returned as `file:line:column: message` lines, the way protoc prints
+    /// them.
+    pub fn descriptor_set(
+        &mut self,
+        uri: &Url,
+        content: &str,
+        ipath: &[PathBuf],
+    ) -> Result<Vec<FileDescriptor>, Vec<String>> {
+        self.upsert_content(uri, content, ipath, MAX_IMPORT_DEPTH);
+
+        let mut order = Vec::new();
+        let mut errors = Vec::new();
+        self.collect_dependencies(uri, ipath, &mut HashSet::new(), &mut order, &mut errors);
+
+        let mut files = Vec::new();
+        for uri in order {
+            let Some(document) = self.get_document(&uri) else {
+                continue;
+            };
+            let mut diagnostics = document.collect_parse_diagnostics();
+            if diagnostics.is_empty() {
+                diagnostics.extend(self.import_cycle_diagnostics(&document, ipath));
+                let (descriptor, semantic) = self.compile_document(&document, ipath);
+                diagnostics.extend(semantic);
+                files.push(descriptor);
+            }
+
+            let name = file_name(&uri, ipath);
+            errors.extend(
+                diagnostics
+                    .iter()
+                    .filter(|d| d.severity == Some(DiagnosticSeverity::ERROR))
+                    .map(|d| {
+                        format!(
+                            "{name}:{}:{}: {}",
+                            d.range.start.line + 1,
+                            d.range.start.character + 1,
+                            d.message
+                        )
+                    }),
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(files)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Appends `uri` to `order` after the files it imports, reporting the
+    /// imports that cannot be found.
+    fn collect_dependencies(
+        &self,
+        uri: &Url,
+        ipath: &[PathBuf],
+        visited: &mut HashSet<Url>,
+        order: &mut Vec<Url>,
+        errors: &mut Vec<String>,
+    ) {
+        if !visited.insert(uri.clone()) {
+            return;
+        }
+        let Some(document) = self.get_document(uri) else {
+            return;
+        };
+        for path in document.import_paths() {
+            match resolve_import(&path, ipath) {
+                Some(import) => self.collect_dependencies(&import, ipath, visited, order, errors),
+                None => errors.push(format!(
+                    "{}: Import \"{path}\" was not found.",
+                    file_name(uri, ipath)
+                )),
+            }
+        }
+        order.push(uri.clone());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_lsp::lsp_types::Url;
+    use insta::assert_yaml_snapshot;
+
+    use crate::state::ProtoLanguageState;
+
+    #[test]
+    fn test_descriptor_set() {
+        let dir = std::env::current_dir()
+            .unwrap()
+            .join("src/state/input/semantic");
+        let ipath = vec![dir.clone()];
+        let mut state = ProtoLanguageState::new();
+
+        let path = dir.join("valid.proto");
+        let uri = Url::from_file_path(&path).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        let files = state.descriptor_set(&uri, &content, &ipath).unwrap();
+        let names: Vec<&str> = files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["common.proto", "hidden.proto", "via.proto", "valid.proto"]
+        );
+
+        let path = dir.join("errors.proto");
+        let uri = Url::from_file_path(&path).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_yaml_snapshot!(state.descriptor_set(&uri, &content, &ipath).unwrap_err());
+    }
+}
@@ -0,0 +1,371 @@
+//! Diagnostics published for documents: the ones computed natively and the
+//! ones reported by protoc, which may be about any file the document imports.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use async_lsp::lsp_types::{Diagnostic, PublishDiagnosticsParams, Range, Url};
+use tree_sitter::Point;
+
+use crate::config::LintConfig;
+use crate::document::ProtoDocument;
+use crate::lint::lint;
+use crate::model::ElementKind;
+use crate::protoc::ProtocDiagnostic;
+use crate::state::ProtoLanguageState;
+use crate::utils::{import_path, resolve_import, to_lsp_position, to_lsp_range};
+
+/// The protoc diagnostics of a run, by the file they are about.
+pub(super) type ProtocReports = HashMap<Url, Vec<Diagnostic>>;
+
+/// Node kinds made of several tokens that protoc reports as one.
+const COMPOUND_TOKENS: &[&str] = &["full_ident", "message_or_enum_type", "type", "string"];
+
+impl ProtoLanguageState {
+    /// Collects the diagnostics of `document` that do not need protoc: syntax
+    /// errors, imports that cannot be found and unused imports.
+    pub(super) fn native_diagnostics(
+        &self,
+        document: &ProtoDocument,
+        ipath: &[PathBuf],
+    ) -> Vec<Diagnostic> {
+        let missing: Vec<String> = document
+            .import_paths()
+            .into_iter()
+            .filter(|import| !ipath.iter().any(|p| p.join(import.as_str()).exists()))
+            .collect();
+        let missing: Vec<&str> = missing.iter().map(String::as_str).collect();
+
+        let mut diagnostics = document.collect_parse_diagnostics();
+        diagnostics.extend(document.collect_import_diagnostics(&missing));
+        diagnostics.extend(self.collect_unused_import_diagnostics(document, ipath));
+        diagnostics
+    }
+
+    /// Runs the lint rules `config` turns on over the document at `uri`, next
+    /// to the other indexed files of its directory.
+    pub fn lint_diagnostics(
+        &self,
+        uri: &Url,
+        ipath: &[PathBuf],
+        config: &LintConfig,
+    ) -> Vec<Diagnostic> {
+        let Some(document) = self.get_document(uri) else {
+            return vec![];
+        };
+        let content = self.get_content(uri);
+        let directory = |uri: &Url| {
+            uri.to_file_path()
+                .ok()
+                .and_then(|path| path.parent().map(Path::to_path_buf))
+        };
+        let own = directory(uri);
+        let siblings: Vec<(String, String)> = self
+            .get_documents()
+            .into_iter()
+            .filter(|other| &other.uri != uri && own.is_some() && directory(&other.uri) == own)
+            .map(|other| {
+                let name =
+                    import_path(&other.uri, ipath).unwrap_or_else(|| other.uri.path().to_string());
+                (name, other.package)
+            })
+            .collect();
+        lint(
+            &document,
+            &content,
+            import_path(uri, ipath).as_deref(),
+            &siblings,
+            config,
+        )
+    }
+
+    /// Returns the diagnostics to publish once protoc reported `messages` on
+    /// `uri`: its native diagnostics with the messages about it added, then,
+    /// for every other file this run or the previous run on `uri` reports
+    /// about, its native diagnostics with the messages of every run about it.
+    ///
+    /// Files having a run of their own are left to it, so that the results of
+    /// that run are not overwritten.
+    pub fn publish_protoc_diagnostics(
+        &self,
+        uri: &Url,
+        ipath: &[PathBuf],
+        messages: Vec<ProtocDiagnostic>,
+    ) -> Vec<PublishDiagnosticsParams> {
+        let mut own = self
+            .get_document(uri)
+            .map(|document| self.native_diagnostics(&document, ipath))
+            .unwrap_or_default();
+        let mut reports = self.attribute_protoc_diagnostics(uri, ipath, messages);
+        own.extend(reports.remove(uri).unwrap_or_default());
+
+        let mut runs = self.protoc_reports.write().expect("poison");
+        let touched: BTreeSet<Url> = reports.keys().cloned().collect();
+        let previous = runs.insert(uri.clone(), reports).unwrap_or_default();
+        let touched = touched.into_iter().chain(previous.into_keys());
+
+        let mut params = vec![publish(uri, own)];
+        for other in touched.collect::<BTreeSet<_>>() {
+            if runs.contains_key(&other) {
+                continue;
+            }
+            let mut diagnostics = self
+                .get_document(&other)
+                .map(|document| self.native_diagnostics(&document, ipath))
+                .unwrap_or_default();
+            for message in runs.values().filter_map(|run| run.get(&other)).flatten() {
+                if !diagnostics.contains(message) {
+                    diagnostics.push(message.clone());
+                }
+            }
+            params.push(publish(&other, diagnostics));
+        }
+        params
+    }
+
+    /// Hashes the include paths and the content of `uri` and of every file it
+    /// imports, directly or not, which is everything a protoc run depends on.
+    pub fn content_hash(&self, uri: &Url, ipath: &[PathBuf]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        ipath.hash(&mut hasher);
+
+        let mut seen = HashSet::new();
+        let mut pending = vec![uri.clone()];
+        while let Some(uri) = pending.pop() {
+            if !seen.insert(uri.clone()) {
+                continue;
+            }
+            uri.hash(&mut hasher);
+            self.get_content(&uri).hash(&mut hasher);
+            if let Some(document) = self.get_document(&uri) {
+                pending.extend(
+                    document
+                        .import_paths()
+                        .iter()
+                        .filter_map(|path| resolve_import(path, ipath)),
+                );
+            }
+        }
+        hasher.finish()
+    }
+
+    /// Maps protoc messages onto the documents they name, widening their
+    /// location to the token found there. Messages without location are
+    /// attached to the import statement of `uri` that brings their file in,
+    /// or to the start of `uri`.
+    fn attribute_protoc_diagnostics(
+        &self,
+        uri: &Url,
+        ipath: &[PathBuf],
+        messages: Vec<ProtocDiagnostic>,
+    ) -> ProtocReports {
+        let current = self.get_document(uri);
+        let mut reports = ProtocReports::new();
+
+        for message in messages {
+            let target = resolve_import(&message.file, ipath);
+            let (target, range, text) = match (message.position, target) {
+                (Some(point), Some(target)) => {
+                    let range = match self.get_document(&target) {
+                        Some(document) => token_range(&document, point),
+                        None => point_range(point),
+                    };
+                    (target, range, message.message)
+                }
+                (Some(point), None) => {
+                    let text = format!(
+                        "{}:{}:{}: {}",
+                        message.file,
+                        point.row + 1,
+                        point.column + 1,
+                        message.message
+                    );
+                    (uri.clone(), Range::default(), text)
+                }
+                (None, target) => {
+                    let import = current.as_ref().and_then(|document| {
+                        import_range(document, &message.file, target.as_ref(), ipath)
+                    });
+                    let text = if import.is_some() || target.as_ref() == Some(uri) {
+                        message.message
+                    } else {
+                        format!("{}: {}", message.file, message.message)
+                    };
+                    (uri.clone(), import.unwrap_or_default(), text)
+                }
+            };
+
+            reports.entry(target).or_default().push(Diagnostic {
+                range,
+                severity: Some(message.severity),
+                source: Some("protoc".to_string()),
+                message: text,
+                ..Default::default()
+            });
+        }
+        reports
+    }
+}
+
+fn publish(uri: &Url, diagnostics: Vec<Diagnostic>) -> PublishDiagnosticsParams {
+    PublishDiagnosticsParams {
+        uri: uri.clone(),
+        diagnostics,
+        version: None,
+    }
+}
+
+/// Returns the range of the import statement of `document` bringing in `file`.
+fn import_range(
+    document: &ProtoDocument,
+    file: &str,
+    target: Option<&Url>,
+    ipath: &[PathBuf],
+) -> Option<Range> {
+    document.elements.iter().find_map(|e| match &e.kind {
+        ElementKind::Import { path }
+            if path == file
+                || target.is_some_and(|t| resolve_import(path, ipath).as_ref() == Some(t)) =>
+        {
+            Some(e.meta.range)
+        }
+        _ => None,
+    })
+}
+
+/// Returns the range of the token of `document` at `point`, or of the single
+/// character there if it is not on a token.
+fn token_range(document: &ProtoDocument, point: Point) -> Range {
+    let root = document.tree.root_node();
+    let Some(mut node) = root
+        .descendant_for_point_range(point, point)
+        .filter(|n| n.child_count() == 0 && n.start_position().row == n.end_position().row)
+    else {
+        return point_range(point);
+    };
+    while let Some(parent) = node.parent()
+        && COMPOUND_TOKENS.contains(&parent.kind())
+        && parent.start_position().row == parent.end_position().row
+    {
+        node = parent;
+    }
+    to_lsp_range(node)
+}
+
+fn point_range(point: Point) -> Range {
+    Range {
+        start: to_lsp_position(point),
+        end: to_lsp_position(Point {
+            row: point.row,
+            column: point.column + 1,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_lsp::lsp_types::{DiagnosticSeverity, PublishDiagnosticsParams, Url};
+    use insta::assert_yaml_snapshot;
+    use tree_sitter::Point;
+
+    use crate::protoc::ProtocDiagnostic;
+    use crate::state::ProtoLanguageState;
+
+    fn message(file: &str, position: Option<(usize, usize)>, text: &str) -> ProtocDiagnostic {
+        ProtocDiagnostic {
+            file: file.to_string(),
+            position: position.map(|(row, column)| Point { row, column }),
+            severity: DiagnosticSeverity::ERROR,
+            message: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_attribute_protoc_diagnostics() {
+        let (dir, state) = ProtoLanguageState::with_fixtures("protoc", &["main.proto"]);
+        let ipath = vec![dir.clone()];
+        let uri = Url::from_file_path(dir.join("main.proto")).unwrap();
+
+        let mut warning = message(
+            "main.proto",
+            Some((9, 9)),
+            "Field name should be lowercase.",
+        );
+        warning.severity = DiagnosticSeverity::WARNING;
+        let messages = vec![
+            // Located in an imported file, on a qualified type.
+            message(
+                "dep.proto",
+                Some((5, 2)),
+                "\"org.dep.Unknown\" is not defined.",
+            ),
+            warning,
+            // Without location, about an import.
+            message("missing.proto", None, "File not found."),
+            // Neither located nor imported.
+            message("elsewhere.proto", None, "File not found."),
+        ];
+
+        let mut reports: Vec<_> = state
+            .attribute_protoc_diagnostics(&uri, &ipath, messages)
+            .into_iter()
+            .map(|(uri, diagnostics)| {
+                let file = uri
+                    .path_segments()
+                    .unwrap()
+                    .next_back()
+                    .unwrap()
+                    .to_string();
+                (file, diagnostics)
+            })
+            .collect();
+        reports.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_yaml_snapshot!(reports);
+    }
+
+    #[test]
+    fn test_publish_protoc_diagnostics_merges_runs() {
+        let (dir, state) =
+            ProtoLanguageState::with_fixtures("protoc", &["main.proto", "other.proto"]);
+        let ipath = vec![dir.clone()];
+        let uri = |file: &str| Url::from_file_path(dir.join(file)).unwrap();
+        let published = |params: Vec<PublishDiagnosticsParams>, file: &str| {
+            params.into_iter().find(|p| p.uri == uri(file)).map(|p| {
+                p.diagnostics
+                    .into_iter()
+                    .map(|d| d.message)
+                    .collect::<Vec<_>>()
+            })
+        };
+        let undefined = message(
+            "dep.proto",
+            Some((5, 2)),
+            "\"org.dep.Unknown\" is not defined.",
+        );
+        let unused = message("dep.proto", Some((0, 0)), "Import is unused.");
+
+        // Both importers report on `dep.proto`, which shows each message once.
+        let params =
+            state.publish_protoc_diagnostics(&uri("main.proto"), &ipath, vec![undefined.clone()]);
+        assert_eq!(
+            published(params, "dep.proto").unwrap(),
+            ["\"org.dep.Unknown\" is not defined."]
+        );
+        let params =
+            state.publish_protoc_diagnostics(&uri("other.proto"), &ipath, vec![undefined, unused]);
+        assert_eq!(
+            published(params, "dep.proto").unwrap(),
+            ["\"org.dep.Unknown\" is not defined.", "Import is unused."]
+        );
+
+        // Once `dep.proto` has a run of its own, the others leave it alone.
+        let params = state.publish_protoc_diagnostics(&uri("dep.proto"), &ipath, vec![]);
+        assert_eq!(
+            published(params, "dep.proto").unwrap(),
+            Vec::<String>::new()
+        );
+        let params = state.publish_protoc_diagnostics(&uri("main.proto"), &ipath, vec![]);
+        assert_eq!(published(params, "dep.proto"), None);
+    }
+}
@@ -0,0 +1,131 @@
+//! Same-document occurrence highlighting.
+
+use async_lsp::lsp_types::{DocumentHighlight, DocumentHighlightKind, Position, Url};
+
+use crate::state::ProtoLanguageState;
+
+impl ProtoLanguageState {
+    /// Highlights every occurrence of the symbol under `position` within
+    /// `uri`.
+    ///
+    /// The symbol is identified by its FQN, so occurrences are matched
+    /// semantically rather than textually. Each entry of the document's
+    /// spatial index is either a declaration name, reported as
+    /// [`DocumentHighlightKind::WRITE`] when it declares the symbol, or a type
+    /// reference, reported as [`DocumentHighlightKind::READ`] when it resolves
+    /// to the symbol.
+    pub fn document_highlights(&self, uri: &Url, position: Position) -> Vec<DocumentHighlight> {
+        let Some(target_fqn) = self.resolve_target_fqn(uri, position) else {
+            return vec![];
+        };
+        let Some(document) = self.get_document(uri) else {
+            return vec![];
+        };
+
+        let mut highlights = Vec::new();
+        for entry in &document.spatial_index {
+            let Some(element) = document.elements.get(entry.element_id) else {
+                continue;
+            };
+
+            if entry.range == element.meta.selection_range {
+                if element.kind.fqn() == Some(target_fqn.as_str()) {
+                    highlights.push(DocumentHighlight {
+                        range: entry.range,
+                        kind: Some(DocumentHighlightKind::WRITE),
+                    });
+                }
+                continue;
+            }
+
+            let Some(type_ref) = element.type_reference_at(entry.range.start) else {
+                continue;
+            };
+            let scope = element.kind.fqn().unwrap_or(&document.package);
+            if self
+                .resolve_reference(scope, &type_ref.name)
+                .iter()
+                .any(|r| r.element.kind.fqn() == Some(target_fqn.as_str()))
+            {
+                highlights.push(DocumentHighlight {
+                    range: entry.range,
+                    kind: Some(DocumentHighlightKind::READ),
+                });
+            }
+        }
+
+        highlights
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_lsp::lsp_types::{Position, Url};
+    use insta::assert_yaml_snapshot;
+    use std::path::PathBuf;
+
+    use crate::config::Config;
+    use crate::state::ProtoLanguageState;
+
+    #[test]
+    fn test_document_highlights() {
+        let ipath = vec![PathBuf::from("src/state/input/hierarchy")];
+        let types_uri: Url = "file://input/hierarchy/types.proto".parse().unwrap();
+        let service_uri: Url = "file://input/hierarchy/service.proto".parse().unwrap();
+
+        let mut state = ProtoLanguageState::new();
+        state.upsert_file(
+            &types_uri,
+            include_str!("input/hierarchy/types.proto"),
+            &ipath,
+            2,
+            &Config::default(),
+            false,
+        );
+        state.upsert_file(
+            &service_uri,
+            include_str!("input/hierarchy/service.proto"),
+            &ipath,
+            2,
+            &Config::default(),
+            false,
+        );
+
+        // From the `Author` declaration.
+        assert_yaml_snapshot!(state.document_highlights(
+            &types_uri,
+            Position {
+                line: 9,
+                character: 9
+            }
+        ));
+        // From a `Book` reference inside a map value.
+        assert_yaml_snapshot!(state.document_highlights(
+            &types_uri,
+            Position {
+                line: 25,
+                character: 16
+            }
+        ));
+        // Declared in another file: only references are highlighted.
+        assert_yaml_snapshot!(state.document_highlights(
+            &service_uri,
+            Position {
+                line: 7,
+                character: 14
+            }
+        ));
+        // Nothing under the cursor.
+        assert!(
+            state
+                .document_highlights(
+                    &types_uri,
+                    Position {
+                        line: 1,
+                        character: 0
+                    }
+                )
+                .is_empty()
+        );
+    }
+}
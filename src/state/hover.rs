@@ -1,3 +1,5 @@
+use std::fmt::Write;
+
 use async_lsp::lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind, Position, Url};
 
 use crate::model::{ElementKind, ModelElement, SpatialEntry};
@@ -19,7 +21,7 @@ impl ProtoLanguageState {
             current_document.find_entry_at_position(position).copied()?;
         let element = current_document.elements.get(element_id)?;
 
-        let value = element.to_hover_markdown(position).or_else(|| {
+        let mut value = element.to_hover_markdown(position).or_else(|| {
             let scope = element.kind.fqn().unwrap_or(&current_document.package);
             element
                 .inspect_nested_type_reference(position)
@@ -35,6 +37,10 @@ impl ProtoLanguageState {
                 })
         })?;
 
+        if element.inspect_nested_type_reference(position).is_none() {
+            self.fill_rpc_details(element, &mut value);
+        }
+
         Some(Hover {
             contents: HoverContents::Markup(MarkupContent {
                 kind: MarkupKind::Markdown,
@@ -43,6 +49,106 @@ impl ProtoLanguageState {
             range: Some(range),
         })
     }
+
+    /// Appends the streaming shape, the HTTP mappings and the expanded request
+    /// and response messages of an RPC to its hover markdown, each message
+    /// under its own heading, or once when both sides share the same type.
+    ///
+    /// Does nothing for elements that are not RPCs. Messages that cannot be
+    /// resolved in the workspace (e.g., well-known types) are left out.
+    fn fill_rpc_details(&self, element: &ModelElement, buffer: &mut String) {
+        let ElementKind::Rpc {
+            fqn,
+            request_type_ref,
+            response_type_ref,
+            ..
+        } = &element.kind
+        else {
+            return;
+        };
+        let Some(shape) = element.kind.rpc_shape() else {
+            return;
+        };
+
+        let _ = write!(buffer, "\n\n---\n**Shape:** {shape}");
+
+        if let Some(rule) = element.http_rule() {
+            for binding in std::iter::once(&rule).chain(&rule.additional_bindings) {
+                let _ = write!(
+                    buffer,
+                    "\n\n**HTTP:** `{} {}`",
+                    binding.method, binding.path
+                );
+                if let Some(body) = &binding.body {
+                    let _ = write!(buffer, " (body: `{body}`)");
+                }
+            }
+        }
+
+        let request = self.expand_message(fqn, &request_type_ref.name);
+        let response = self.expand_message(fqn, &response_type_ref.name);
+        let sections = match (request, response) {
+            (Some(request), Some(response)) if request.0 == response.0 => {
+                vec![("Request and response", request.1)]
+            }
+            (request, response) => [("Request", request), ("Response", response)]
+                .into_iter()
+                .filter_map(|(label, expanded)| Some((label, expanded?.1)))
+                .collect(),
+        };
+        for (label, expanded) in sections {
+            let _ = write!(buffer, "\n\n**{label}:**\n```protobuf\n{expanded}\n```");
+        }
+    }
+
+    /// Renders the message referenced by `type_name` from `scope` together
+    /// with its direct fields and oneofs as a protobuf declaration block,
+    /// returned with the fully qualified name of the message.
+    fn expand_message(&self, scope: &str, type_name: &str) -> Option<(String, String)> {
+        let target = self
+            .resolve_reference(scope, type_name)
+            .into_iter()
+            .next()?;
+        if !matches!(target.element.kind, ElementKind::Message { .. }) {
+            return None;
+        }
+        let document = self.get_document(&target.uri)?;
+
+        let children = |parent_id: usize| {
+            document
+                .elements
+                .iter()
+                .filter(move |e| e.parent_id == Some(parent_id))
+        };
+
+        let mut buffer = String::new();
+        target.element.fill_declaration(&mut buffer)?;
+        buffer.push_str(" {");
+
+        for child in children(target.element.id) {
+            match child.kind {
+                ElementKind::Field { .. } | ElementKind::MapField { .. } => {
+                    buffer.push_str("\n  ");
+                    child.fill_declaration(&mut buffer);
+                }
+                ElementKind::Oneof { .. } => {
+                    buffer.push_str("\n  ");
+                    child.fill_declaration(&mut buffer);
+                    buffer.push_str(" {");
+                    for field in children(child.id) {
+                        buffer.push_str("\n    ");
+                        field.fill_declaration(&mut buffer);
+                    }
+                    buffer.push_str("\n  }");
+                }
+                _ => {}
+            }
+        }
+        buffer.push_str("\n}");
+
+        let fqn = target.element.kind.fqn()?.to_owned();
+        Some((fqn, buffer))
+    }
 }
 
 impl ModelElement {
@@ -220,4 +326,71 @@ mod test {
             }
         ));
     }
+
+    #[test]
+    fn test_hover_rpc_details() {
+        let ipath = vec![];
+        let uri = "file:///rpc.proto".parse().unwrap();
+        let mut state: ProtoLanguageState = ProtoLanguageState::new();
+        state.upsert_file(
+            &uri,
+            concat!(
+                "syntax = \"proto3\";\n",
+                "package com.rpc;\n",
+                "message Shelf {\n",
+                "  string name = 1;\n",
+                "  oneof location {\n",
+                "    string room = 2;\n",
+                "    int32 floor = 3;\n",
+                "  }\n",
+                "  map<string, int32> counts = 4;\n",
+                "}\n",
+                "service Library {\n",
+                "  rpc GetShelf(Shelf) returns (Shelf) {\n",
+                "    option (google.api.http).get = \"/v1/{name=shelves/*}\";\n",
+                "  }\n",
+                "  rpc Sync(stream Shelf) returns (stream Shelf) {\n",
+                "    option (google.api.http) = {\n",
+                "      custom: { kind: \"HEAD\" path: \"/v1/sync\" }\n",
+                "      additional_bindings { get: \"/v2/sync\" }\n",
+                "      additional_bindings { post: \"/v2/sync:run\" body: \"*\" }\n",
+                "    };\n",
+                "  }\n",
+                "  rpc GetBook(Shelf) returns (Book);\n",
+                "}\n",
+                "message Book {\n",
+                "  string title = 1;\n",
+                "}\n",
+            ),
+            &ipath,
+            3,
+            &Config::default(),
+            false,
+        );
+
+        // Unary rpc with a sub-field http option.
+        assert_yaml_snapshot!(state.hover(
+            &uri,
+            Position {
+                line: 11,
+                character: 8
+            }
+        ));
+        // Bidirectional streaming rpc with a custom http pattern.
+        assert_yaml_snapshot!(state.hover(
+            &uri,
+            Position {
+                line: 14,
+                character: 8
+            }
+        ));
+        // Distinct request and response messages.
+        assert_yaml_snapshot!(state.hover(
+            &uri,
+            Position {
+                line: 21,
+                character: 8
+            }
+        ));
+    }
 }
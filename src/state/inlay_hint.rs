@@ -0,0 +1,287 @@
+//! Inlay hint compilation layer for protobuf documents.
+//!
+//! This module surfaces semantics that protobuf leaves implicit in the source
+//! text: the JSON name a field is serialized under, whether a singular field
+//! tracks presence, the fully-qualified target of short type references,
+//! which enum values alias an earlier value, and how an RPC streams.
+
+use std::collections::HashMap;
+
+use async_lsp::lsp_types::{
+    InlayHint, InlayHintKind, InlayHintLabel, InlayHintTooltip, Position, Range, Url,
+};
+
+use crate::docs;
+use crate::document::ProtoDocument;
+use crate::model::{ElementKind, ModelElement, RpcShape, SyntaxKind, TypeReference};
+use crate::state::ProtoLanguageState;
+
+impl ProtoLanguageState {
+    /// Compiles the inlay hints for every element of `uri` overlapping
+    /// `range`.
+    ///
+    /// Hints are emitted in document order:
+    ///
+    /// * `implicit` / `explicit` before the type of singular fields without a
+    ///   cardinality label.
+    /// * The resolved `.package.Type` FQN after short type references.
+    /// * `json: name` after field names whose JSON name differs from the
+    ///   declared name.
+    /// * `alias of VALUE` after enum values sharing a number with an earlier
+    ///   value of the same enum.
+    /// * The streaming shape after the name of streaming RPCs.
+    pub fn inlay_hints(&self, uri: &Url, range: Range) -> Vec<InlayHint> {
+        let Some(document) = self.get_document(uri) else {
+            return vec![];
+        };
+
+        let mut hints = Vec::new();
+        let mut enum_numbers: HashMap<(Option<usize>, i32), &str> = HashMap::new();
+
+        for element in &document.elements {
+            if let ElementKind::EnumValue { number, .. } = &element.kind {
+                let canonical = *enum_numbers
+                    .entry((element.parent_id, *number))
+                    .or_insert(&element.meta.name);
+
+                if canonical != element.meta.name && overlaps(element.meta.range, range) {
+                    hints.push(hint(
+                        element.meta.range.end,
+                        format!("alias of {canonical}"),
+                        None,
+                        "Shares its number with an earlier value of this enum.",
+                    ));
+                }
+                continue;
+            }
+
+            if !overlaps(element.meta.range, range) {
+                continue;
+            }
+
+            let scope = element.kind.fqn().unwrap_or(&document.package);
+
+            if let ElementKind::Field {
+                type_ref,
+                cardinality: None,
+                ..
+            } = &element.kind
+                && let Some(label) = self.presence_label(&document, element, type_ref, scope)
+            {
+                hints.push(InlayHint {
+                    padding_left: None,
+                    padding_right: Some(true),
+                    ..hint(
+                        type_ref.range.start,
+                        label.to_string(),
+                        None,
+                        "Field presence tracking for this singular field.",
+                    )
+                });
+            }
+
+            for type_ref in element.kind.type_references() {
+                if let Some(fqn) = self.resolved_type_fqn(scope, type_ref) {
+                    hints.push(hint(
+                        type_ref.range.end,
+                        format!(".{fqn}"),
+                        Some(InlayHintKind::TYPE),
+                        "Fully-qualified name of the referenced type.",
+                    ));
+                }
+            }
+
+            if let Some(shape) = element.kind.rpc_shape()
+                && shape != RpcShape::Unary
+            {
+                hints.push(hint(
+                    element.meta.selection_range.end,
+                    shape.to_string(),
+                    None,
+                    "Streaming shape of this RPC.",
+                ));
+            }
+
+            if let Some(json_name) = element.json_name()
+                && json_name != element.meta.name
+            {
+                hints.push(hint(
+                    element.meta.selection_range.end,
+                    format!("json: {json_name}"),
+                    None,
+                    "Name used by the canonical JSON mapping.",
+                ));
+            }
+        }
+
+        hints.sort_by_key(|h| h.position);
+        hints
+    }
+
+    /// Resolves a short type reference to the FQN of its target, returning
+    /// `None` for scalars, unresolvable names and references already written
+    /// fully-qualified.
+    fn resolved_type_fqn(&self, scope: &str, type_ref: &TypeReference) -> Option<String> {
+        if docs::BUILTIN.contains_key(type_ref.name.as_str()) {
+            return None;
+        }
+
+        let target = self
+            .resolve_reference(scope, &type_ref.name)
+            .into_iter()
+            .next()?;
+        let fqn = target.element.kind.fqn()?;
+
+        (fqn != type_ref.name.trim_start_matches('.')).then(|| fqn.to_string())
+    }
+
+    /// Determines whether a singular field without a cardinality label tracks
+    /// presence, following the rules of the file's syntax revision. Under
+    /// editions, `features.field_presence` is resolved from the field outward.
+    fn presence_label(
+        &self,
+        document: &ProtoDocument,
+        element: &ModelElement,
+        type_ref: &TypeReference,
+        scope: &str,
+    ) -> Option<&'static str> {
+        let is_message = if docs::BUILTIN.contains_key(type_ref.name.as_str()) {
+            false
+        } else {
+            let target = self
+                .resolve_reference(scope, &type_ref.name)
+                .into_iter()
+                .next()?;
+            matches!(target.element.kind, ElementKind::Message { .. })
+        };
+
+        if is_message {
+            return Some("explicit");
+        }
+
+        match &document.syntax {
+            SyntaxKind::Proto2 => Some("explicit"),
+            SyntaxKind::Proto3 => Some("implicit"),
+            SyntaxKind::Editions { .. } => {
+                // The nearest setting wins: the field's own, then those of
+                // its enclosing messages, then the file's.
+                let feature = std::iter::successors(Some(element), |current| {
+                    current.parent_id.and_then(|id| document.elements.get(id))
+                })
+                .find_map(|element| element.option("features.field_presence"))
+                .or_else(|| {
+                    document
+                        .options
+                        .iter()
+                        .rev()
+                        .find(|o| o.name == "features.field_presence")
+                })
+                .map(|o| o.value.as_str());
+
+                match feature {
+                    Some("IMPLICIT") => Some("implicit"),
+                    Some("LEGACY_REQUIRED") => None,
+                    _ => Some("explicit"),
+                }
+            }
+        }
+    }
+}
+
+fn hint(
+    position: Position,
+    label: String,
+    kind: Option<InlayHintKind>,
+    tooltip: &str,
+) -> InlayHint {
+    InlayHint {
+        position,
+        label: InlayHintLabel::String(label),
+        kind,
+        text_edits: None,
+        tooltip: Some(InlayHintTooltip::String(tooltip.to_string())),
+        padding_left: Some(true),
+        padding_right: None,
+        data: None,
+    }
+}
+
+fn overlaps(a: Range, b: Range) -> bool {
+    a.start <= b.end && b.start <= a.end
+}
+
+#[cfg(test)]
+mod test {
+    use async_lsp::lsp_types::{Position, Range, Url};
+    use insta::assert_yaml_snapshot;
+    use std::path::PathBuf;
+
+    use crate::config::Config;
+    use crate::state::ProtoLanguageState;
+
+    const FULL: Range = Range {
+        start: Position {
+            line: 0,
+            character: 0,
+        },
+        end: Position {
+            line: u32::MAX,
+            character: 0,
+        },
+    };
+
+    fn load(state: &mut ProtoLanguageState, name: &str, content: &str) -> Url {
+        let ipath = vec![PathBuf::from("src/state/input/inlay")];
+        let uri: Url = format!("file://input/inlay/{name}").parse().unwrap();
+        state.upsert_file(&uri, content, &ipath, 2, &Config::default(), false);
+        uri
+    }
+
+    #[test]
+    fn test_inlay_hints() {
+        let mut state = ProtoLanguageState::new();
+        let proto3 = load(
+            &mut state,
+            "proto3.proto",
+            include_str!("input/inlay/proto3.proto"),
+        );
+        let editions = load(
+            &mut state,
+            "editions.proto",
+            include_str!("input/inlay/editions.proto"),
+        );
+        let proto2 = load(
+            &mut state,
+            "proto2.proto",
+            include_str!("input/inlay/proto2.proto"),
+        );
+
+        assert_yaml_snapshot!(state.inlay_hints(&proto3, FULL));
+        assert_yaml_snapshot!(state.inlay_hints(&editions, FULL));
+        assert_yaml_snapshot!(state.inlay_hints(&proto2, FULL));
+    }
+
+    #[test]
+    fn test_inlay_hints_respects_range() {
+        let mut state = ProtoLanguageState::new();
+        let uri = load(
+            &mut state,
+            "proto3.proto",
+            include_str!("input/inlay/proto3.proto"),
+        );
+
+        let range = Range {
+            start: Position {
+                line: 17,
+                character: 0,
+            },
+            end: Position {
+                line: 17,
+                character: 50,
+            },
+        };
+        let hints = state.inlay_hints(&uri, range);
+        assert!(hints.iter().all(|h| h.position.line == 17));
+        assert_eq!(hints.len(), 2);
+    }
+}
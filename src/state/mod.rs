@@ -1,7 +1,18 @@
+mod breaking;
+mod call_hierarchy;
 mod definition;
+mod descriptor_set;
+mod diagnostics;
+mod document_highlight;
 mod hover;
+mod inlay_hint;
+mod move_file;
+mod organize_imports;
 mod rename;
+mod rename_fields;
 mod resolve;
+mod semantic;
+mod unused_imports;
 mod workspace_symbol;
 
 use std::{
@@ -11,6 +22,10 @@ use std::{
 };
 use tracing::info;
 
+pub use breaking::{breaking_change_range, breaking_diagnostic, load_baseline};
+pub use rename::RenameGroups;
+pub use resolve::is_external_document;
+
 use async_lsp::lsp_types::{
     CompletionItem, CompletionItemKind, Location, OneOf, ProgressParamsValue,
     PublishDiagnosticsParams, Range, SymbolKind, SymbolTag, Url, WorkspaceSymbol,
@@ -19,17 +34,27 @@ use tree_sitter::{Query, QueryError};
 use walkdir::WalkDir;
 
 use crate::{
-    config::Config,
+    config::{Config, DiagnosticsEngine},
+    descriptor::{FileDescriptor, SymbolTable},
     document::{ProtoDocument, ProtoParser},
     model::{ElementKind, generate_metamodel_query},
     protoc::collect_diagnostics,
+    state::diagnostics::ProtocReports,
 };
 
 pub struct ProtoLanguageState {
     sources: Arc<RwLock<HashMap<Url, String>>>,
     documents: Arc<RwLock<HashMap<Url, ProtoDocument>>>,
+    /// The definitions of every document, kept in step with `documents`.
+    symbols: Arc<RwLock<SymbolTable>>,
+    /// Descriptors compiled by the semantic pass of the last upsert, taken by
+    /// the breaking change check that follows it.
+    compiled: Arc<RwLock<HashMap<Url, FileDescriptor>>>,
     parser: Arc<Mutex<ProtoParser>>,
     parsed_workspaces: Arc<RwLock<HashSet<String>>>,
+    /// The protoc diagnostics the last run on each file reported about the
+    /// other files, by file.
+    protoc_reports: Arc<RwLock<HashMap<Url, ProtocReports>>>,
     metamodel_query: Query,
 }
 
@@ -50,8 +75,11 @@ impl ProtoLanguageState {
         Self {
             sources: Arc::default(),
             documents: Arc::default(),
+            symbols: Arc::default(),
+            compiled: Arc::default(),
             parser: Arc::new(Mutex::new(ProtoParser::new())),
             parsed_workspaces: Arc::new(RwLock::new(HashSet::new())),
+            protoc_reports: Arc::default(),
             metamodel_query,
         }
     }
@@ -186,6 +214,10 @@ impl ProtoLanguageState {
             return;
         };
 
+        self.symbols
+            .write()
+            .expect("poison")
+            .update_file(&parsed, content);
         self.documents
             .write()
             .expect("posion")
@@ -308,40 +340,43 @@ impl ProtoLanguageState {
         depth: usize,
         config: &Config,
         protoc_diagnostics: bool,
-    ) -> Option<PublishDiagnosticsParams> {
+    ) -> Vec<PublishDiagnosticsParams> {
         info!(%uri, %depth, "upserting file");
-        let diag = self.upsert_content(uri, content, ipath, depth);
-        let diag_slice: Vec<&str> = diag.iter().map(String::as_str).collect();
-        self.get_document(uri).map(|document| {
-            let mut d = vec![];
-            d.extend(document.collect_parse_diagnostics());
-            d.extend(document.collect_import_diagnostics(diag_slice.as_slice()));
-
-            // Add protoc diagnostics if enabled
-            if protoc_diagnostics && let Ok(file_path) = uri.to_file_path() {
-                let protoc_diags = collect_diagnostics(
-                    &config.path.protoc,
-                    file_path.to_str().unwrap_or_default(),
-                    &ipath
-                        .iter()
-                        .map(|p| p.to_str().unwrap_or_default().to_string())
-                        .collect::<Vec<_>>(),
-                );
-                d.extend(protoc_diags);
-            }
+        self.compiled.write().expect("poison").clear();
+        self.upsert_content(uri, content, ipath, depth);
+        let Some(document) = self.get_document(uri) else {
+            return vec![];
+        };
 
-            PublishDiagnosticsParams {
-                uri: document.uri.clone(),
-                diagnostics: d,
-                version: None,
-            }
-        })
+        // Add protoc diagnostics if enabled, they may be about imported files
+        if protoc_diagnostics && let Ok(file_path) = uri.to_file_path() {
+            let messages = collect_diagnostics(
+                &config.path.protoc,
+                file_path.to_str().unwrap_or_default(),
+                &ipath
+                    .iter()
+                    .map(|p| p.to_str().unwrap_or_default().to_string())
+                    .collect::<Vec<_>>(),
+            );
+            return self.publish_protoc_diagnostics(uri, ipath, messages);
+        }
+
+        let mut diagnostics = self.native_diagnostics(&document, ipath);
+        if config.diagnostics.engine == DiagnosticsEngine::Native {
+            diagnostics.extend(self.semantic_diagnostics(&document, ipath));
+        }
+        vec![PublishDiagnosticsParams {
+            uri: document.uri.clone(),
+            diagnostics,
+            version: None,
+        }]
     }
 
     pub fn delete_file(&mut self, uri: &Url) {
         info!(%uri, "deleting file");
         self.sources.write().expect("poison").remove(uri);
         self.documents.write().expect("poison").remove(uri);
+        self.symbols.write().expect("poison").remove_file(uri);
     }
 
     pub fn rename_file(&mut self, new_uri: &Url, old_uri: &Url) {
@@ -359,7 +394,10 @@ impl ProtoLanguageState {
         if let Some(ref mut v) = document {
             v.uri = new_uri.clone();
         }
+        let mut symbols = self.symbols.write().expect("poison");
+        symbols.remove_file(old_uri);
         if let Some(v) = document {
+            symbols.update_file(&v, &self.get_content(new_uri));
             self.documents
                 .write()
                 .expect("poison")
@@ -429,6 +467,44 @@ fn is_message_kind(kind: &ElementKind) -> bool {
     matches!(kind, ElementKind::Message { .. })
 }
 
+/// Returns the test fixture directory `src/state/input/<name>`.
+#[cfg(test)]
+pub(crate) fn fixture_dir(name: &str) -> PathBuf {
+    std::env::current_dir()
+        .unwrap()
+        .join("src/state/input")
+        .join(name)
+}
+
+#[cfg(test)]
+impl ProtoLanguageState {
+    /// Reads `file` of the fixture directory `dir` and upserts it.
+    pub(crate) fn upsert_fixture(
+        &mut self,
+        dir: &Path,
+        file: &str,
+        ipath: &[PathBuf],
+        config: &Config,
+    ) -> Vec<PublishDiagnosticsParams> {
+        let path = dir.join(file);
+        let uri = Url::from_file_path(&path).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        self.upsert_file(&uri, &content, ipath, 2, config, false)
+    }
+
+    /// Returns the fixture directory `name`, and a state holding its `files`
+    /// with that directory as the only include path.
+    pub(crate) fn with_fixtures(name: &str, files: &[&str]) -> (PathBuf, Self) {
+        let dir = fixture_dir(name);
+        let ipath = vec![dir.clone()];
+        let mut state = Self::new();
+        for file in files {
+            state.upsert_fixture(&dir, file, &ipath, &Config::default());
+        }
+        (dir, state)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -732,8 +808,8 @@ mod test {
             &Config::default(),
             false,
         );
-        assert!(result.is_some());
-        let params = result.unwrap();
+        assert_eq!(result.len(), 1);
+        let params = &result[0];
         assert_eq!(params.uri.as_str(), "file:///test.proto");
         // Should have no diagnostics for valid proto
         assert!(params.diagnostics.is_empty());
@@ -751,8 +827,8 @@ mod test {
             &Config::default(),
             false,
         );
-        assert!(result.is_some());
-        let params = result.unwrap();
+        assert_eq!(result.len(), 1);
+        let params = &result[0];
         assert!(
             !params.diagnostics.is_empty(),
             "expected parse diagnostics for invalid proto"
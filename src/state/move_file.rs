@@ -0,0 +1,287 @@
+//! Moving proto files: the edits keeping the imports of a moved file valid.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use async_lsp::lsp_types::{
+    DocumentChangeOperation, DocumentChanges, OneOf, OptionalVersionedTextDocumentIdentifier,
+    RenameFile, ResourceOp, TextDocumentEdit, TextEdit, Url, WorkspaceEdit,
+};
+
+use crate::state::ProtoLanguageState;
+use crate::state::resolve::is_external_document;
+use crate::utils::to_lsp_range;
+
+impl ProtoLanguageState {
+    /// Returns the edits rewriting, in every indexed file, the imports of the
+    /// file at `from` so that they import it at `to`. Each import is resolved
+    /// with the `include_paths` of the file declaring it, and keeps resolving
+    /// from the same include path when `to` is under it. Vendored files are
+    /// left untouched.
+    pub fn import_edits_for_move(
+        &self,
+        from: &Url,
+        to: &Url,
+        include_paths: &dyn Fn(&Url) -> Option<Vec<PathBuf>>,
+    ) -> HashMap<Url, Vec<TextEdit>> {
+        let (Ok(from), Ok(to)) = (from.to_file_path(), to.to_file_path()) else {
+            return HashMap::new();
+        };
+
+        let mut changes = HashMap::new();
+        for document in self.get_documents() {
+            if is_external_document(&document.uri) {
+                continue;
+            }
+            let Some(ipath) = include_paths(&document.uri) else {
+                continue;
+            };
+            let content = self.get_content(&document.uri);
+            let root = document.tree.root_node();
+            let mut edits = Vec::new();
+            for node in root
+                .children(&mut root.walk())
+                .filter(|n| n.kind() == "import")
+            {
+                let Some(string) = node
+                    .children(&mut node.walk())
+                    .find(|n| n.kind() == "string")
+                else {
+                    continue;
+                };
+                let Ok(text) = string.utf8_text(content.as_bytes()) else {
+                    continue;
+                };
+                let path = text.trim_matches(|c| c == '"' || c == '\'');
+                let Some(include) = ipath.iter().find(|p| p.join(path) == from) else {
+                    continue;
+                };
+                let Some(new_path) = relative_import(&to, include, &ipath) else {
+                    continue;
+                };
+                edits.push(TextEdit {
+                    range: to_lsp_range(string),
+                    new_text: format!("\"{new_path}\""),
+                });
+            }
+            if !edits.is_empty() {
+                changes.insert(document.uri, edits);
+            }
+        }
+        changes
+    }
+
+    /// Returns the indexed files moved when `from` is moved to `to`, each with
+    /// its new location: `from` itself when it is a file, or else the files
+    /// under it, as a directory.
+    pub fn moved_files(&self, from: &Url, to: &Url) -> Vec<(Url, Url)> {
+        if self.get_document(from).is_some() {
+            return vec![(from.clone(), to.clone())];
+        }
+        let (Ok(from_dir), Ok(to_dir)) = (from.to_file_path(), to.to_file_path()) else {
+            return vec![];
+        };
+
+        let mut moved: Vec<(Url, Url)> = self
+            .get_documents()
+            .into_iter()
+            .filter_map(|document| {
+                let path = document.uri.to_file_path().ok()?;
+                let relative = path.strip_prefix(&from_dir).ok()?;
+                let new_uri = Url::from_file_path(to_dir.join(relative)).ok()?;
+                Some((document.uri, new_uri))
+            })
+            .collect();
+        moved.sort();
+        moved
+    }
+
+    /// Returns the path `uri` should be moved to so that its directory
+    /// matches its package, relative to the include path it is under, with
+    /// the edit doing so, when it is elsewhere and nothing is there yet.
+    /// `include_paths` gives the include paths of each file.
+    pub fn move_to_package_directory(
+        &self,
+        uri: &Url,
+        include_paths: &dyn Fn(&Url) -> Option<Vec<PathBuf>>,
+    ) -> Option<(String, WorkspaceEdit)> {
+        let ipath = include_paths(uri)?;
+        let document = self.get_document(uri)?;
+        if document.package.is_empty() {
+            return None;
+        }
+        let file = uri.to_file_path().ok()?;
+        let include = ipath.iter().find(|p| file.starts_with(p))?;
+
+        let mut target = include.clone();
+        target.extend(document.package.split('.'));
+        target.push(file.file_name()?);
+        if target == file || target.exists() {
+            return None;
+        }
+        let new_uri = Url::from_file_path(&target).ok()?;
+        let name = relative_import(&target, include, &ipath)?;
+        Some((name, self.move_file_edit(uri, &new_uri, include_paths)))
+    }
+
+    /// The edit renaming `from` to `to`, after updating the imports of it.
+    pub fn move_file_edit(
+        &self,
+        from: &Url,
+        to: &Url,
+        include_paths: &dyn Fn(&Url) -> Option<Vec<PathBuf>>,
+    ) -> WorkspaceEdit {
+        let mut changes: Vec<(Url, Vec<TextEdit>)> = self
+            .import_edits_for_move(from, to, include_paths)
+            .into_iter()
+            .collect();
+        changes.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut operations: Vec<DocumentChangeOperation> = changes
+            .into_iter()
+            .map(|(uri, edits)| {
+                DocumentChangeOperation::Edit(TextDocumentEdit {
+                    text_document: OptionalVersionedTextDocumentIdentifier { uri, version: None },
+                    edits: edits.into_iter().map(OneOf::Left).collect(),
+                })
+            })
+            .collect();
+        operations.push(DocumentChangeOperation::Op(ResourceOp::Rename(
+            RenameFile {
+                old_uri: from.clone(),
+                new_uri: to.clone(),
+                options: None,
+                annotation_id: None,
+            },
+        )));
+
+        WorkspaceEdit {
+            document_changes: Some(DocumentChanges::Operations(operations)),
+            ..Default::default()
+        }
+    }
+}
+
+/// Returns the import path of `file`, relative to `include` when it is under
+/// it, or else to the first include path it is under, with `/` separators.
+fn relative_import(file: &Path, include: &Path, ipath: &[PathBuf]) -> Option<String> {
+    let relative = file
+        .strip_prefix(include)
+        .ok()
+        .or_else(|| ipath.iter().find_map(|p| file.strip_prefix(p).ok()))?;
+    let parts: Vec<String> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    Some(parts.join("/"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use async_lsp::lsp_types::Url;
+    use insta::assert_snapshot;
+
+    use crate::state::ProtoLanguageState;
+
+    fn setup() -> (PathBuf, ProtoLanguageState) {
+        ProtoLanguageState::with_fixtures(
+            "move",
+            &[
+                "misplaced.proto",
+                "shop/v1/order.proto",
+                "shop/v1/item.proto",
+                "client/client.proto",
+                "google/protobuf/vendored.proto",
+            ],
+        )
+    }
+
+    #[test]
+    fn test_moved_files() {
+        let (dir, state) = setup();
+        let uri = |path: &str| Url::from_file_path(dir.join(path)).unwrap();
+
+        assert_eq!(
+            state.moved_files(&uri("misplaced.proto"), &uri("other.proto")),
+            [(uri("misplaced.proto"), uri("other.proto"))]
+        );
+        assert_eq!(
+            state.moved_files(&uri("shop"), &uri("store")),
+            [
+                (uri("shop/v1/item.proto"), uri("store/v1/item.proto")),
+                (uri("shop/v1/order.proto"), uri("store/v1/order.proto")),
+            ]
+        );
+
+        // Every file importing the moved one is edited, whatever its quotes,
+        // but vendored ones. `client.proto` imports from `shop` only.
+        let include_paths = |uri: &Url| {
+            Some(if uri.path().contains("/client/") {
+                vec![dir.join("shop")]
+            } else {
+                vec![dir.clone()]
+            })
+        };
+        let changes = |from: &str, to: &str| {
+            let mut changes: Vec<_> = state
+                .import_edits_for_move(&uri(from), &uri(to), &include_paths)
+                .into_iter()
+                .map(|(uri, edits)| (uri, edits[0].new_text.clone()))
+                .collect();
+            changes.sort();
+            changes
+        };
+        assert_eq!(
+            changes("misplaced.proto", "shop/misplaced.proto"),
+            [
+                (
+                    uri("shop/v1/item.proto"),
+                    String::from("\"shop/misplaced.proto\"")
+                ),
+                (
+                    uri("shop/v1/order.proto"),
+                    String::from("\"shop/misplaced.proto\"")
+                ),
+            ]
+        );
+        assert_eq!(
+            changes("shop/v1/item.proto", "shop/v2/item.proto"),
+            [
+                (
+                    uri("client/client.proto"),
+                    String::from("\"v2/item.proto\"")
+                ),
+                (
+                    uri("shop/v1/order.proto"),
+                    String::from("\"shop/v2/item.proto\"")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_move_to_package_directory() {
+        let (dir, state) = setup();
+        let include_paths = |_: &Url| Some(vec![dir.clone()]);
+        let uri = Url::from_file_path(dir.join("misplaced.proto")).unwrap();
+
+        let (name, edit) = state
+            .move_to_package_directory(&uri, &include_paths)
+            .unwrap();
+        assert_eq!(name, "shop/v1/misplaced.proto");
+        let edit = serde_json::to_string_pretty(&edit)
+            .unwrap()
+            .replace(dir.to_str().unwrap(), "<dir>");
+        assert_snapshot!(edit);
+
+        // Files already in the directory of their package stay there.
+        let uri = Url::from_file_path(dir.join("shop/v1/order.proto")).unwrap();
+        assert!(
+            state
+                .move_to_package_directory(&uri, &include_paths)
+                .is_none()
+        );
+    }
+}
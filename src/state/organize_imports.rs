@@ -0,0 +1,378 @@
+//! "Organize imports" source action.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use async_lsp::lsp_types::{Position, Range, TextEdit, Url};
+use tree_sitter::Node;
+
+use crate::docs;
+use crate::document::ProtoDocument;
+use crate::model::ElementKind;
+use crate::state::ProtoLanguageState;
+use crate::state::resolve::extendees;
+use crate::utils::{import_path, resolve_import, to_lsp_range};
+
+/// An import statement of the organized document, with its own comments.
+struct ImportLine {
+    path: String,
+    /// `public` or `weak`, if any.
+    modifier: Option<String>,
+    comments: Vec<String>,
+    trailing: Option<String>,
+}
+
+impl ProtoLanguageState {
+    /// Computes the edit that sorts the imports of `uri` by path, removes
+    /// the unused ones and adds the missing ones.
+    ///
+    /// An import is used when a type reference, a custom option name or an
+    /// extended type of the document resolves to a definition in the imported
+    /// file, or in a file it publicly re-exports. `public` and `weak` imports,
+    /// and imports whose file is not indexed, are always kept. A reference
+    /// resolving only to files that are not imported gets an import of the
+    /// first one, relative to the include paths.
+    pub fn organize_imports(&self, uri: &Url, ipath: &[PathBuf]) -> Vec<TextEdit> {
+        let Some(document) = self.get_document(uri) else {
+            return vec![];
+        };
+        let content = self.get_content(uri);
+        let root = document.tree.root_node();
+        let statements: Vec<Node> = root.children(&mut root.walk()).collect();
+
+        let requirements = self.required_documents(&document);
+
+        let (mut imports, others, range) = import_block(&statements, &content);
+        let had_imports = !imports.is_empty();
+
+        self.remove_unused_imports(&mut imports, &requirements, ipath);
+        self.add_missing_imports(&mut imports, &requirements, ipath);
+        imports.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut new_text: String = imports
+            .iter()
+            .map(ImportLine::render)
+            .chain(others.into_iter().map(|other| other + "\n"))
+            .collect();
+        if !had_imports && !new_text.is_empty() {
+            new_text = if range.start.line == 0 {
+                format!("{new_text}\n")
+            } else {
+                format!("\n{new_text}")
+            };
+        }
+
+        let old_text = text_in_range(&content, range);
+        if old_text == new_text {
+            return vec![];
+        }
+        vec![TextEdit { range, new_text }]
+    }
+
+    /// Drops duplicate imports and those not providing any definition the
+    /// document refers to.
+    fn remove_unused_imports(
+        &self,
+        imports: &mut Vec<ImportLine>,
+        requirements: &[Vec<Url>],
+        ipath: &[PathBuf],
+    ) {
+        let mut seen = HashSet::new();
+        imports.retain(|import| {
+            if !seen.insert(import.path.clone()) {
+                return false;
+            }
+            if import.modifier.is_some() {
+                return true;
+            }
+            let Some(reach) = self.import_reach(&import.path, ipath) else {
+                return true;
+            };
+            requirements
+                .iter()
+                .any(|candidates| candidates.iter().any(|c| reach.contains(c)))
+        });
+    }
+
+    /// Imports a file for every requirement that no import satisfies yet.
+    fn add_missing_imports(
+        &self,
+        imports: &mut Vec<ImportLine>,
+        requirements: &[Vec<Url>],
+        ipath: &[PathBuf],
+    ) {
+        let mut reachable: HashSet<Url> = imports
+            .iter()
+            .filter_map(|import| self.import_reach(&import.path, ipath))
+            .flatten()
+            .collect();
+        for candidates in requirements {
+            if candidates.iter().any(|c| reachable.contains(c)) {
+                continue;
+            }
+            let Some(path) = candidates.iter().find_map(|c| import_path(c, ipath)) else {
+                continue;
+            };
+            reachable.extend(self.import_reach(&path, ipath).unwrap_or_default());
+            imports.push(ImportLine {
+                path,
+                modifier: None,
+                comments: vec![],
+                trailing: None,
+            });
+        }
+    }
+
+    /// Returns, for every reference of `document` to another file, the
+    /// documents it may resolve to, sorted by URI.
+    ///
+    /// Type references, custom option names and the types extended by
+    /// `extend` blocks all count as references.
+    pub(super) fn required_documents(&self, document: &ProtoDocument) -> Vec<Vec<Url>> {
+        let mut references: Vec<(&str, String)> = Vec::new();
+
+        let option_name = |name: &str| {
+            let inner = name.strip_prefix('(')?.split_once(')')?.0;
+            Some(inner.to_string())
+        };
+        for option in &document.options {
+            references.extend(option_name(&option.name).map(|n| (document.package.as_str(), n)));
+        }
+        for element in &document.elements {
+            let scope = element.kind.fqn().unwrap_or(&document.package);
+            for type_ref in element.kind.type_references() {
+                if !docs::BUILTIN.contains_key(type_ref.name.as_str()) {
+                    references.push((scope, type_ref.name.clone()));
+                }
+            }
+            for option in &element.meta.options {
+                references.extend(option_name(&option.name).map(|n| (scope, n)));
+            }
+        }
+        let content = self.get_content(&document.uri);
+        let extended = extendees(document, &content);
+        references.extend(
+            extended
+                .iter()
+                .map(|(scope, name, _)| (scope.as_str(), name.clone())),
+        );
+
+        let mut requirements = Vec::new();
+        for (scope, name) in references {
+            let mut candidates: Vec<Url> = self
+                .resolve_reference(scope, &name)
+                .into_iter()
+                .map(|target| target.uri)
+                .collect();
+            if candidates.is_empty() || candidates.contains(&document.uri) {
+                continue;
+            }
+            candidates.sort();
+            candidates.dedup();
+            if !requirements.contains(&candidates) {
+                requirements.push(candidates);
+            }
+        }
+        requirements
+    }
+
+    /// Returns the file behind an import path together with every file it
+    /// re-exports through `import public`, or `None` if it is not indexed.
+    pub(super) fn import_reach(&self, path: &str, ipath: &[PathBuf]) -> Option<HashSet<Url>> {
+        let uri = resolve_import(path, ipath)?;
+        self.get_document(&uri)?;
+
+        let mut reach = HashSet::new();
+        let mut pending = vec![uri];
+        while let Some(uri) = pending.pop() {
+            if !reach.insert(uri.clone()) {
+                continue;
+            }
+            let Some(document) = self.get_document(&uri) else {
+                continue;
+            };
+            pending.extend(
+                public_imports(&document)
+                    .iter()
+                    .filter_map(|path| resolve_import(path, ipath)),
+            );
+        }
+        Some(reach)
+    }
+}
+
+impl ImportLine {
+    fn render(&self) -> String {
+        let mut out = String::new();
+        for comment in &self.comments {
+            out.push_str(comment);
+            out.push('\n');
+        }
+        out.push_str("import ");
+        if let Some(modifier) = &self.modifier {
+            out.push_str(modifier);
+            out.push(' ');
+        }
+        out.push('"');
+        out.push_str(&self.path);
+        out.push_str("\";");
+        if let Some(comment) = &self.trailing {
+            out.push(' ');
+            out.push_str(comment);
+        }
+        out.push('\n');
+        out
+    }
+}
+
+/// Splits the top-level statements into the import lines to organize, the
+/// other statements found between imports, and the range of lines they span.
+/// Without imports, the range is an empty one after the file header.
+fn import_block(statements: &[Node], content: &str) -> (Vec<ImportLine>, Vec<String>, Range) {
+    let first = statements.iter().position(|n| n.kind() == "import");
+    let last = statements.iter().rposition(|n| n.kind() == "import");
+    if let (Some(first), Some(last)) = (first, last) {
+        let (imports, others) = import_lines(&statements[first..=last], content);
+        let range = Range {
+            start: Position {
+                line: to_lsp_range(statements[first]).start.line,
+                character: 0,
+            },
+            end: Position {
+                line: to_lsp_range(statements[last]).end.line + 1,
+                character: 0,
+            },
+        };
+        return (imports, others, range);
+    }
+
+    let line = statements
+        .iter()
+        .filter(|n| matches!(n.kind(), "syntax" | "edition" | "package"))
+        .map(|n| to_lsp_range(*n).end.line + 1)
+        .max()
+        .unwrap_or_default();
+    let position = Position { line, character: 0 };
+    (vec![], vec![], Range::new(position, position))
+}
+
+/// Returns the paths imported with `import public` by `document`.
+fn public_imports(document: &ProtoDocument) -> Vec<String> {
+    let root = document.tree.root_node();
+    root.children(&mut root.walk())
+        .filter(|n| n.kind() == "import")
+        .filter(|n| n.children(&mut n.walk()).any(|c| c.kind() == "public"))
+        .filter_map(|n| {
+            let range = to_lsp_range(n);
+            document.elements.iter().find_map(|e| match &e.kind {
+                ElementKind::Import { path } if e.meta.range == range => Some(path.clone()),
+                _ => None,
+            })
+        })
+        .collect()
+}
+
+/// Splits the statements between the first and the last import into import
+/// lines, carrying the comments around them, and the verbatim text of any
+/// other statement found in between.
+fn import_lines(statements: &[Node], content: &str) -> (Vec<ImportLine>, Vec<String>) {
+    let text = |n: Node| content[n.byte_range()].trim_end().to_string();
+
+    let mut imports: Vec<ImportLine> = Vec::new();
+    let mut others = Vec::new();
+    let mut comments = Vec::new();
+    let mut last_row = None;
+    for &node in statements {
+        let row = node.start_position().row;
+        match node.kind() {
+            "import" => {
+                let modifier = node
+                    .children(&mut node.walk())
+                    .find(|c| matches!(c.kind(), "public" | "weak"))
+                    .map(|c| c.kind().to_string());
+                let path = node
+                    .child_by_field_name("path")
+                    .map(|p| text(p).trim_matches(['"', '\'']).to_string())
+                    .unwrap_or_default();
+                imports.push(ImportLine {
+                    path,
+                    modifier,
+                    comments: std::mem::take(&mut comments),
+                    trailing: None,
+                });
+            }
+            "comment" if last_row == Some(row) && comments.is_empty() => {
+                if let Some(import) = imports.last_mut() {
+                    import.trailing = Some(text(node));
+                }
+            }
+            "comment" => comments.push(text(node)),
+            _ => {
+                others.append(&mut comments);
+                others.push(text(node));
+            }
+        }
+        last_row = Some(node.end_position().row);
+    }
+    others.append(&mut comments);
+    (imports, others)
+}
+
+fn text_in_range(content: &str, range: Range) -> String {
+    content
+        .split_inclusive('\n')
+        .skip(range.start.line as usize)
+        .take((range.end.line - range.start.line) as usize)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use async_lsp::lsp_types::Url;
+    use insta::assert_yaml_snapshot;
+    use std::path::PathBuf;
+
+    use crate::state::ProtoLanguageState;
+
+    fn setup(files: &[&str]) -> (PathBuf, ProtoLanguageState) {
+        ProtoLanguageState::with_fixtures("organize", files)
+    }
+
+    #[test]
+    fn test_organize_imports() {
+        let (dir, state) = setup(&[
+            "common.proto",
+            "extra.proto",
+            "options.proto",
+            "tags.proto",
+            "main.proto",
+        ]);
+        let uri = Url::from_file_path(dir.join("main.proto")).unwrap();
+        assert_yaml_snapshot!(state.organize_imports(&uri, &[dir]));
+    }
+
+    #[test]
+    fn test_organize_imports_without_imports() {
+        let (dir, state) = setup(&["tags.proto", "bare.proto"]);
+        let uri = Url::from_file_path(dir.join("bare.proto")).unwrap();
+        assert_yaml_snapshot!(state.organize_imports(&uri, &[dir]));
+    }
+
+    #[test]
+    fn test_organize_imports_keeps_extended_types() {
+        let (dir, state) = setup(&[
+            "extra.proto",
+            "google/protobuf/descriptor.proto",
+            "extend.proto",
+        ]);
+        let uri = Url::from_file_path(dir.join("extend.proto")).unwrap();
+        assert_yaml_snapshot!(state.organize_imports(&uri, &[dir]));
+    }
+
+    #[test]
+    fn test_organize_imports_organized() {
+        let (dir, state) = setup(&["common.proto", "tags.proto", "organized.proto"]);
+        let uri = Url::from_file_path(dir.join("organized.proto")).unwrap();
+        assert!(state.organize_imports(&uri, &[dir]).is_empty());
+    }
+}
@@ -1,11 +1,53 @@
 use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 
-use async_lsp::lsp_types::{Location, Position, TextEdit, Url};
-
-use crate::model::ElementKind;
+use async_lsp::lsp_types::{Location, Position, Range, TextEdit, Url};
+
+use crate::config::LintConfig;
+use crate::descriptor::declared_reserved_names;
+use crate::document::ProtoDocument;
+use crate::lint::{
+    is_pascal_case, is_rule_enabled, to_lower_snake_case, to_pascal_case, to_upper_snake_case,
+};
+use crate::model::{ElementKind, OptionEntry};
 use crate::state::ProtoLanguageState;
-use crate::utils::{is_position_inside_range, trailing_segment};
+use crate::state::rename_fields::offset_position;
+use crate::state::resolve::{extendees, is_external_document};
+use crate::utils::{
+    byte_offset, is_identifier, is_position_inside_range, parent_scope, trailing_segment,
+};
+
+/// The naming conventions new names are checked against.
+#[derive(Debug, Clone, Copy)]
+enum Convention {
+    Pascal,
+    LowerSnake,
+    UpperSnake,
+}
+
+impl Convention {
+    fn name(self) -> &'static str {
+        match self {
+            Convention::Pascal => "PascalCase",
+            Convention::LowerSnake => "lower_snake_case",
+            Convention::UpperSnake => "UPPER_SNAKE_CASE",
+        }
+    }
+
+    /// Returns `name` written following the convention.
+    fn apply(self, name: &str) -> String {
+        match self {
+            Convention::Pascal if is_pascal_case(name) => name.to_owned(),
+            Convention::Pascal => to_pascal_case(name),
+            Convention::LowerSnake => to_lower_snake_case(name),
+            Convention::UpperSnake => to_upper_snake_case(name),
+        }
+    }
+}
+
+/// The edits of a rename: those of the renamed symbol, and those of the
+/// rpc/request/response convention chain following it.
+pub type RenameGroups = (HashMap<Url, Vec<TextEdit>>, HashMap<Url, Vec<TextEdit>>);
 
 /// A single rename operation to apply against the workspace: rename whatever
 /// symbol is declared at `(uri, pos)` to `new_name`. Multiple ops are merged
@@ -88,24 +130,264 @@ impl ProtoLanguageState {
         ops
     }
 
-    /// Apply a sequence of rename ops, merging their per-file edits into a
-    /// single map. Returns `None` if the *primary* (first) op fails — in that
-    /// case the user's invocation should produce no edit at all. Sibling
-    /// failures are silently skipped so the primary always lands.
-    pub fn apply_rename_ops(&mut self, ops: &[RenameOp]) -> Option<HashMap<Url, Vec<TextEdit>>> {
-        let mut all: HashMap<Url, Vec<TextEdit>> = HashMap::new();
-        for (i, op) in ops.iter().enumerate() {
-            match self.run_single_rename(op) {
-                Some(edits) => {
-                    for (u, e) in edits {
-                        all.entry(u).or_default().extend(e);
+    /// Checks the chain siblings of `ops`, every op but the first, with
+    /// [`Self::validate_rename`], so that the chain never renames a message
+    /// onto an existing one. Returns the reason of the first refusal.
+    pub fn validate_chain_renames(
+        &self,
+        ops: &[RenameOp],
+        lint: &LintConfig,
+    ) -> Result<(), String> {
+        for op in ops.iter().skip(1) {
+            let Some(fqn) = self.resolve_target_fqn(&op.uri, op.pos) else {
+                continue;
+            };
+            self.validate_rename(&fqn, &op.new_name, lint)
+                .map_err(|message| {
+                    format!(
+                        "Cannot rename \"{fqn}\" along the rpc/request/response chain: {message}"
+                    )
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Apply a sequence of rename ops, merging their per-file edits into two
+    /// maps: the edits of the *primary* (first) op, and those of the chain
+    /// siblings, so that each group can be previewed on its own. Returns
+    /// `None` if the primary op fails — in that case the user's invocation
+    /// should produce no edit at all. Sibling failures are silently skipped
+    /// so the primary always lands.
+    pub fn apply_rename_ops_grouped(&mut self, ops: &[RenameOp]) -> Option<RenameGroups> {
+        let Some((primary, siblings)) = ops.split_first() else {
+            return Some(Default::default());
+        };
+        let primary = self.run_single_rename(primary)?.into_iter().collect();
+        let mut chain: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+        for op in siblings {
+            for (u, e) in self.run_single_rename(op).unwrap_or_default() {
+                chain.entry(u).or_default().extend(e);
+            }
+        }
+        Some((primary, chain))
+    }
+
+    /// Produces the edits renaming the package `old` to `new`: the `package`
+    /// statement of every file of the package, and every type reference,
+    /// custom option name or extended type naming a definition of it with a
+    /// qualification that no longer holds.
+    ///
+    /// References keep their form: fully-qualified ones stay fully-qualified,
+    /// and unqualified ones from files of the package need no edit since
+    /// those files move along. Any other reference is rewritten with the
+    /// package-qualified name of its target. Definitions of nested packages,
+    /// such as `old.sub`, are left alone.
+    pub fn rename_package(&self, old: &str, new: &str) -> BTreeMap<Url, Vec<TextEdit>> {
+        let mut edits: BTreeMap<Url, Vec<TextEdit>> = BTreeMap::new();
+        if old.is_empty() || old == new {
+            return edits;
+        }
+        let documents = self.get_documents();
+        let packages: HashMap<&Url, &str> = documents
+            .iter()
+            .map(|document| (&document.uri, document.package.as_str()))
+            .collect();
+        let prefix = format!("{old}.");
+
+        // The name a reference from `document` in `scope` takes after the
+        // rename, or `None` if it stays as written.
+        let requalify = |document: &ProtoDocument, scope: &str, name: &str| {
+            let target = self.resolve_reference(scope, name).into_iter().next()?;
+            let target_fqn = target.element.kind.fqn()?;
+            if packages.get(&target.uri) != Some(&old) {
+                return None;
+            }
+            let local = target_fqn.strip_prefix(&prefix)?;
+            let renamed = format!("{new}.{local}");
+            let new_text = if name.starts_with('.') {
+                format!(".{renamed}")
+            } else if document.package == old && name.split('.').count() <= local.split('.').count()
+            {
+                return None;
+            } else {
+                renamed
+            };
+            (new_text != name).then_some(new_text)
+        };
+
+        for document in &documents {
+            if is_external_document(&document.uri) {
+                continue;
+            }
+            let content = self.get_content(&document.uri);
+            let mut references: Vec<(&str, String, Range)> = Vec::new();
+            for element in &document.elements {
+                let scope = element.kind.fqn().unwrap_or(&document.package);
+                for type_ref in element.kind.type_references() {
+                    references.push((scope, type_ref.name.clone(), type_ref.range));
+                }
+                references.extend(option_name_references(
+                    scope,
+                    &element.meta.options,
+                    &content,
+                ));
+            }
+            references.extend(option_name_references(
+                &document.package,
+                &document.options,
+                &content,
+            ));
+            let extended = extendees(document, &content);
+            references.extend(
+                extended
+                    .iter()
+                    .map(|(scope, name, range)| (scope.as_str(), name.clone(), *range)),
+            );
+
+            let file_edits = edits.entry(document.uri.clone()).or_default();
+            if document.package == old
+                && let Some(range) = document.package_name_range()
+            {
+                file_edits.push(TextEdit {
+                    range,
+                    new_text: new.to_owned(),
+                });
+            }
+            for (scope, name, range) in references {
+                if let Some(new_text) = requalify(document, scope, &name) {
+                    file_edits.push(TextEdit { range, new_text });
+                }
+            }
+        }
+        edits.retain(|_, edits_in_file| !edits_in_file.is_empty());
+
+        for edits_in_file in edits.values_mut() {
+            edits_in_file.sort_by_key(|e| (e.range.start, e.range.end));
+        }
+        edits
+    }
+
+    /// Checks that the symbol `target_fqn` can be renamed to `new_name`: the
+    /// name must be an identifier, must follow the naming convention of the
+    /// symbol when `lint` turns on the rule enforcing it, and must be neither
+    /// declared in its scope nor reserved there. Returns the reason the
+    /// rename is refused otherwise.
+    pub fn validate_rename(
+        &self,
+        target_fqn: &str,
+        new_name: &str,
+        lint: &LintConfig,
+    ) -> Result<(), String> {
+        let Some(target) = self.lookup_fqn(target_fqn).into_iter().next() else {
+            return Err(format!(
+                "\"{target_fqn}\" is not declared in the workspace."
+            ));
+        };
+        if !is_identifier(new_name) {
+            return Err(format!("\"{new_name}\" is not a valid identifier."));
+        }
+        if target.element.meta.name == new_name {
+            return Ok(());
+        }
+
+        let (label, convention, rule) = match target.element.kind {
+            ElementKind::Message { .. } => ("Message", Convention::Pascal, "MESSAGE_PASCAL_CASE"),
+            ElementKind::Enum { .. } => ("Enum", Convention::Pascal, "ENUM_PASCAL_CASE"),
+            ElementKind::Service { .. } => ("Service", Convention::Pascal, "SERVICE_PASCAL_CASE"),
+            ElementKind::Rpc { .. } => ("Rpc", Convention::Pascal, "RPC_PASCAL_CASE"),
+            ElementKind::Field { .. }
+            | ElementKind::MapField { .. }
+            | ElementKind::OneofField { .. } => {
+                ("Field", Convention::LowerSnake, "FIELD_LOWER_SNAKE_CASE")
+            }
+            ElementKind::Oneof { .. } => {
+                ("Oneof", Convention::LowerSnake, "ONEOF_LOWER_SNAKE_CASE")
+            }
+            ElementKind::EnumValue { .. } => (
+                "Enum value",
+                Convention::UpperSnake,
+                "ENUM_VALUE_UPPER_SNAKE_CASE",
+            ),
+            ElementKind::Import { .. } => {
+                return Err(String::from("Imports cannot be renamed."));
+            }
+        };
+        let expected = convention.apply(new_name);
+        if is_rule_enabled(lint, rule) && expected != new_name {
+            return Err(format!(
+                "{label} names should be {}, such as \"{expected}\".",
+                convention.name()
+            ));
+        }
+
+        let scope = parent_scope(target_fqn);
+        let new_fqn = if scope.is_empty() {
+            new_name.to_owned()
+        } else {
+            format!("{scope}.{new_name}")
+        };
+        if !self.lookup_fqn(&new_fqn).is_empty() {
+            return Err(format!("\"{new_fqn}\" already exists."));
+        }
+
+        // Enum values are scoped like their enum, so they clash with the
+        // values of the other enums of that scope.
+        if matches!(target.element.kind, ElementKind::EnumValue { .. }) {
+            let enum_scope = parent_scope(scope);
+            for document in self.get_documents() {
+                for element in &document.elements {
+                    if let ElementKind::EnumValue { fqn, .. } = &element.kind
+                        && element.meta.name == new_name
+                        && parent_scope(parent_scope(fqn)) == enum_scope
+                    {
+                        return Err(format!(
+                            "\"{new_name}\" is already declared by \"{fqn}\", and enum values share the scope of their enum."
+                        ));
                     }
                 }
-                None if i == 0 => return None,
-                None => {}
             }
         }
-        Some(all)
+
+        // Fields and enum values cannot take a name their parent reserves.
+        if matches!(
+            target.element.kind,
+            ElementKind::Field { .. }
+                | ElementKind::MapField { .. }
+                | ElementKind::OneofField { .. }
+                | ElementKind::EnumValue { .. }
+        ) && let Some(parent) = self.lookup_fqn(scope).into_iter().next()
+            && let Some(document) = self.get_document(&parent.uri)
+        {
+            let content = self.get_content(&parent.uri);
+            let reserved =
+                declared_reserved_names(&document.tree, parent.element.meta.range, &content);
+            if reserved.iter().any(|name| name == new_name) {
+                return Err(format!("\"{new_name}\" is reserved by \"{scope}\"."));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the package `old` can be renamed to `new`: the name must
+    /// be made of lower case identifiers, and no file may declare it yet.
+    pub fn validate_package_rename(&self, old: &str, new: &str) -> Result<(), String> {
+        if !new.split('.').all(is_identifier) {
+            return Err(format!("\"{new}\" is not a valid package name."));
+        }
+        if old == new {
+            return Ok(());
+        }
+        let expected: Vec<String> = new.split('.').map(to_lower_snake_case).collect();
+        if expected.iter().zip(new.split('.')).any(|(e, n)| e != n) {
+            return Err(format!(
+                "Package names should be lower_snake_case, such as \"{}\".",
+                expected.join(".")
+            ));
+        }
+        if self.get_documents().iter().any(|d| d.package == new) {
+            return Err(format!("Package \"{new}\" already exists."));
+        }
+        Ok(())
     }
 
     fn run_single_rename(&mut self, op: &RenameOp) -> Option<BTreeMap<Url, Vec<TextEdit>>> {
@@ -113,7 +395,15 @@ impl ProtoLanguageState {
         // `initialize` handler), so cross-file rename resolves against the
         // cached metamodel pool without any per-request re-scan.
         let target_fqn = self.resolve_target_fqn(&op.uri, op.pos)?;
-        Some(self.rename_for_fqn(&target_fqn, &op.new_name))
+        let mut edits = self.rename_for_fqn(&target_fqn, &op.new_name);
+        // Fields and enum values are also named by text in options and
+        // comments.
+        for (uri, propagated) in self.rename_propagation_edits(&target_fqn, &op.new_name) {
+            let edits_in_file = edits.entry(uri).or_default();
+            edits_in_file.extend(propagated);
+            edits_in_file.sort_by_key(|e| (e.range.start, e.range.end));
+        }
+        Some(edits)
     }
 
     fn compute_chain_siblings(
@@ -296,6 +586,41 @@ impl ProtoLanguageState {
     }
 }
 
+/// Lists the extensions named by the custom `options`, as in
+/// `[(acme.v1.label) = "x"]`, each with `scope` and the range of its name.
+fn option_name_references<'a>(
+    scope: &'a str,
+    options: &[OptionEntry],
+    content: &str,
+) -> Vec<(&'a str, String, Range)> {
+    let mut out = Vec::new();
+    for option in options {
+        let Some((extension, _)) = option
+            .name
+            .strip_prefix('(')
+            .and_then(|name| name.split_once(')'))
+        else {
+            continue;
+        };
+        let source = &content
+            [byte_offset(content, option.range.start)..byte_offset(content, option.range.end)];
+        let Some(offset) = source.find(&option.name) else {
+            continue;
+        };
+        let extension = extension.trim();
+        let start = offset + 1 + option.name[1..].find(extension).unwrap_or_default();
+        out.push((
+            scope,
+            extension.to_owned(),
+            Range {
+                start: offset_position(option.range.start, source, start),
+                end: offset_position(option.range.start, source, start + extension.len()),
+            },
+        ));
+    }
+    out
+}
+
 /// If `msg_name` ends with `Request` or `Response` and `new_name` ends with
 /// the same suffix, return `(rpc_base, suffix, new_rpc_base)`. Otherwise
 /// `None`. Used to detect when a message rename can plausibly drive a chain.
@@ -315,14 +640,15 @@ fn strip_convention_suffix(
 
 #[cfg(test)]
 mod test {
+    use std::collections::HashMap;
     use std::path::PathBuf;
 
-    use async_lsp::lsp_types::{Position, Url};
+    use async_lsp::lsp_types::{Position, TextEdit, Url};
     use insta::assert_yaml_snapshot;
 
-    use crate::config::Config;
+    use crate::config::{Config, LintConfig};
     use crate::state::ProtoLanguageState;
-    use crate::state::rename::RenameOp;
+    use crate::state::rename::{RenameGroups, RenameOp};
 
     fn make_state(files: &[(&str, &str)], ipath: &[PathBuf]) -> ProtoLanguageState {
         let mut state = ProtoLanguageState::new();
@@ -333,6 +659,13 @@ mod test {
         state
     }
 
+    fn merge_groups((mut all, chain): RenameGroups) -> HashMap<Url, Vec<TextEdit>> {
+        for (u, e) in chain {
+            all.entry(u).or_default().extend(e);
+        }
+        all
+    }
+
     fn op(uri: &str, line: u32, character: u32, new_name: &str) -> RenameOp {
         RenameOp {
             uri: uri.parse().unwrap(),
@@ -362,6 +695,135 @@ mod test {
         assert_yaml_snapshot!(state.rename_for_fqn("com.utility.Foobar.Baz", "Baaz"));
     }
 
+    #[test]
+    fn test_rename_package() {
+        let ipath = vec![PathBuf::from("src/state/input/package")];
+        let state = make_state(
+            &[
+                (
+                    "file://input/foo.proto",
+                    include_str!("input/package/foo.proto"),
+                ),
+                (
+                    "file://input/baz.proto",
+                    include_str!("input/package/baz.proto"),
+                ),
+                (
+                    "file://input/client.proto",
+                    include_str!("input/package/client.proto"),
+                ),
+                (
+                    "file://input/sub.proto",
+                    include_str!("input/package/sub.proto"),
+                ),
+                (
+                    "file://input/options.proto",
+                    include_str!("input/package/options.proto"),
+                ),
+                (
+                    "file://input/ext.proto",
+                    include_str!("input/package/ext.proto"),
+                ),
+            ],
+            &ipath,
+        );
+
+        // Package statements of the package, and references qualified with it
+        // or made from a nested package, are rewritten, custom option names
+        // and extended types included; unqualified references inside the
+        // package are not.
+        assert_yaml_snapshot!(state.rename_package("acme.v1", "acme.v2"));
+        assert!(state.rename_package("acme.v1", "acme.v1").is_empty());
+        assert!(state.rename_package("acme.missing", "acme.v2").is_empty());
+    }
+
+    #[test]
+    fn test_validate_rename() {
+        let ipath = vec![PathBuf::from("src/state/input")];
+        let state = make_state(
+            &[(
+                "file://input/validate.proto",
+                include_str!("input/validate.proto"),
+            )],
+            &ipath,
+        );
+        let check = |fqn: &str, new_name: &str| {
+            state.validate_rename(fqn, new_name, &LintConfig::default())
+        };
+
+        assert_eq!(check("com.validate.Shelf", "Bookcase"), Ok(()));
+        assert_eq!(check("com.validate.Shelf", "Shelf"), Ok(()));
+        assert_eq!(check("com.validate.Shelf.name", "title"), Ok(()));
+        assert_eq!(check("com.validate.Color.COLOR_RED", "COLOR_BLUE"), Ok(()));
+
+        // Invalid identifiers.
+        assert_eq!(
+            check("com.validate.Shelf", "2Shelf"),
+            Err(String::from("\"2Shelf\" is not a valid identifier."))
+        );
+        assert_eq!(
+            check("com.validate.Shelf.name", "full-name"),
+            Err(String::from("\"full-name\" is not a valid identifier."))
+        );
+        // Collisions in the scope of the symbol.
+        assert_eq!(
+            check("com.validate.Shelf", "Library"),
+            Err(String::from("\"com.validate.Library\" already exists."))
+        );
+        assert_eq!(
+            check("com.validate.Shelf.name", "book_count"),
+            Err(String::from(
+                "\"com.validate.Shelf.book_count\" already exists."
+            ))
+        );
+        assert_eq!(
+            check("com.validate.Shelf.name", "room"),
+            Err(String::from("\"com.validate.Shelf.room\" already exists."))
+        );
+        assert_eq!(check("com.validate.Shelf.name", "label"), Ok(()));
+        assert_eq!(
+            check("com.validate.ShelfService.GetShelf", "ListShelves"),
+            Err(String::from(
+                "\"com.validate.ShelfService.ListShelves\" already exists."
+            ))
+        );
+        assert_eq!(
+            check("com.validate.Color.COLOR_RED", "SIZE_LARGE"),
+            Err(String::from(
+                "\"SIZE_LARGE\" is already declared by \"com.validate.Size.SIZE_LARGE\", and enum values share the scope of their enum."
+            ))
+        );
+        // Reserved names.
+        assert_eq!(
+            check("com.validate.Shelf.name", "legacy_name"),
+            Err(String::from(
+                "\"legacy_name\" is reserved by \"com.validate.Shelf\"."
+            ))
+        );
+        assert_eq!(
+            check("com.validate.Color.COLOR_RED", "COLOR_PURPLE"),
+            Err(String::from(
+                "\"COLOR_PURPLE\" is reserved by \"com.validate.Color\"."
+            ))
+        );
+
+        // Packages.
+        assert_eq!(
+            state.validate_package_rename("com.validate", "com.checks"),
+            Ok(())
+        );
+        assert_eq!(
+            state.validate_package_rename("com.validate", "com..checks"),
+            Err(String::from("\"com..checks\" is not a valid package name."))
+        );
+        assert_eq!(
+            state.validate_package_rename("com.validate", "com.Checks"),
+            Err(String::from(
+                "Package names should be lower_snake_case, such as \"com.checks\"."
+            ))
+        );
+    }
+
     #[test]
     fn test_references_for_fqn() {
         let ipath = vec![PathBuf::from("src/state/input")];
@@ -548,6 +1010,115 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_validate_rename_naming_conventions() {
+        let ipath = vec![PathBuf::from("src/state/input")];
+        let state = make_state(
+            &[(
+                "file://input/validate.proto",
+                include_str!("input/validate.proto"),
+            )],
+            &ipath,
+        );
+
+        let lint = LintConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let check = |fqn: &str, new_name: &str| state.validate_rename(fqn, new_name, &lint);
+
+        // Naming conventions per kind.
+        assert_eq!(check("com.validate.Color.COLOR_RED", "STATUS_4XX"), Ok(()));
+        assert_eq!(check("com.validate.Color.COLOR_RED", "TYPE_A1B"), Ok(()));
+        assert_eq!(
+            check("com.validate.Shelf", "book_shelf"),
+            Err(String::from(
+                "Message names should be PascalCase, such as \"BookShelf\"."
+            ))
+        );
+        assert_eq!(
+            check("com.validate.Shelf.name", "fullName"),
+            Err(String::from(
+                "Field names should be lower_snake_case, such as \"full_name\"."
+            ))
+        );
+        assert_eq!(
+            check("com.validate.Color.COLOR_RED", "colorBlue"),
+            Err(String::from(
+                "Enum value names should be UPPER_SNAKE_CASE, such as \"COLOR_BLUE\"."
+            ))
+        );
+        assert_eq!(
+            check("com.validate.ShelfService.GetShelf", "get_shelf"),
+            Err(String::from(
+                "Rpc names should be PascalCase, such as \"GetShelf\"."
+            ))
+        );
+
+        // Conventions are only enforced by the lint rules turned on.
+        let field_rule_off = LintConfig {
+            enabled: true,
+            rules: [(String::from("FIELD_LOWER_SNAKE_CASE"), false)].into(),
+        };
+        assert_eq!(
+            state.validate_rename("com.validate.Shelf.name", "fullName", &field_rule_off),
+            Ok(())
+        );
+        assert_eq!(
+            state.validate_rename("com.validate.Shelf", "book_shelf", &field_rule_off),
+            Err(String::from(
+                "Message names should be PascalCase, such as \"BookShelf\"."
+            ))
+        );
+        assert_eq!(
+            state.validate_rename("com.validate.Shelf", "book_shelf", &LintConfig::default()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_validate_chain_renames() {
+        let ipath = vec![PathBuf::from("src/state/input")];
+        let svc_uri = "file://input/service.proto".parse().unwrap();
+        let state = make_state(
+            &[
+                (
+                    "file://input/service.proto",
+                    include_str!("input/service.proto"),
+                ),
+                (
+                    "file://input/messages.proto",
+                    include_str!("input/messages.proto"),
+                ),
+                (
+                    "file://input/fetch.proto",
+                    "syntax = \"proto3\";\npackage com.workspace;\nmessage FetchBookRequest {}\n",
+                ),
+            ],
+            &ipath,
+        );
+
+        // `GetBookRequest` would become a second `FetchBookRequest`.
+        let pos = Position {
+            line: 7,
+            character: 10,
+        };
+        let ops = state.compute_rename_ops(&svc_uri, pos, "FetchBook", &ipath, true);
+        assert_eq!(ops.len(), 3);
+        assert_eq!(
+            state.validate_chain_renames(&ops, &LintConfig::default()),
+            Err(String::from(
+                "Cannot rename \"com.workspace.GetBookRequest\" along the rpc/request/response chain: \"com.workspace.FetchBookRequest\" already exists."
+            ))
+        );
+
+        let ops = state.compute_rename_ops(&svc_uri, pos, "LoadBook", &ipath, true);
+        assert_eq!(
+            state.validate_chain_renames(&ops, &LintConfig::default()),
+            Ok(())
+        );
+    }
+
     #[test]
     fn test_compute_rename_ops_chain_disabled() {
         // Same setup as `chain_from_rpc_cursor`, but with the chain flag off:
@@ -765,7 +1336,8 @@ mod test {
         };
         let ops = state.compute_rename_ops(&svc_uri, pos, "FetchBook", &ipath, true);
         let edits = state
-            .apply_rename_ops(&ops)
+            .apply_rename_ops_grouped(&ops)
+            .map(merge_groups)
             .expect("primary rename should not fail");
 
         // Sort within each file so the snapshot is order-independent across
@@ -935,7 +1507,10 @@ mod test {
             character: 19,
         };
         let ops = state.compute_rename_ops(&svc_uri, pos, "FetchBookRequest", &ipath, false);
-        let edits = state.apply_rename_ops(&ops).expect("rename should succeed");
+        let edits = state
+            .apply_rename_ops_grouped(&ops)
+            .map(merge_groups)
+            .expect("rename should succeed");
         let mut normalized: std::collections::BTreeMap<String, Vec<_>> =
             std::collections::BTreeMap::new();
         for (url, mut v) in edits {
@@ -0,0 +1,828 @@
+//! Renaming fields and enum values: the places naming them outside of type
+//! references, such as `json_name`, field paths of `google.api.http` rules and
+//! option literals.
+
+use std::collections::BTreeMap;
+
+use async_lsp::lsp_types::{Position, Range, TextEdit, Url};
+
+use crate::document::ProtoDocument;
+use crate::model::{ElementKind, ModelElement, OptionEntry};
+use crate::state::ProtoLanguageState;
+use crate::state::resolve::is_external_document;
+use crate::utils::{byte_offset, parent_scope, to_json_name, trailing_segment};
+
+const FIELD_MASK: &str = "google.protobuf.FieldMask";
+const HTTP_OPTION: &str = "(google.api.http)";
+const METHOD_SIGNATURE_OPTION: &str = "(google.api.method_signature)";
+
+/// The `google.api.http` keys holding a path template, whose variables are
+/// field paths of the request.
+const HTTP_TEMPLATES: [&str; 6] = ["get", "put", "post", "delete", "patch", "path"];
+
+/// The field or enum value being renamed.
+struct Target<'a> {
+    fqn: &'a str,
+    /// The FQN of the message declaring the field, or of the enum declaring
+    /// the value.
+    parent: &'a str,
+    old: &'a str,
+    new: &'a str,
+    is_enum_value: bool,
+}
+
+/// A field found by name in a message: its FQN and the FQN of its type.
+struct FieldInfo {
+    fqn: String,
+    type_fqn: Option<String>,
+}
+
+/// A token of an option constant, with its byte offsets in the constant.
+#[derive(Debug, PartialEq)]
+enum Token {
+    Ident,
+    /// A string literal, whose offsets exclude the quotes.
+    Str,
+    Punct(char),
+    Other,
+}
+
+impl ProtoLanguageState {
+    /// Returns the edits renaming the field or enum value `target_fqn` to
+    /// `new_name` where it is named by text rather than by a type reference:
+    /// a `json_name` spelling out its default JSON name, field paths of
+    /// `google.api.http` rules, `google.api.method_signature` options and
+    /// backquoted paths in comments, `[default = ...]` options, and the keys
+    /// and values of custom option literals. Nothing is returned for other
+    /// symbols.
+    pub fn rename_propagation_edits(
+        &self,
+        target_fqn: &str,
+        new_name: &str,
+    ) -> BTreeMap<Url, Vec<TextEdit>> {
+        let mut edits: BTreeMap<Url, Vec<TextEdit>> = BTreeMap::new();
+        let Some(element) = self.lookup_fqn(target_fqn).into_iter().next() else {
+            return edits;
+        };
+        let is_enum_value = match element.element.kind {
+            ElementKind::Field { .. }
+            | ElementKind::MapField { .. }
+            | ElementKind::OneofField { .. } => false,
+            ElementKind::EnumValue { .. } => true,
+            _ => return edits,
+        };
+        let target = Target {
+            fqn: target_fqn,
+            parent: parent_scope(target_fqn),
+            old: &element.element.meta.name,
+            new: new_name,
+            is_enum_value,
+        };
+
+        for document in self.get_documents() {
+            if is_external_document(&document.uri) {
+                continue;
+            }
+            let content = self.get_content(&document.uri);
+            let mut document_edits = Vec::new();
+            for option in &document.options {
+                self.option_edits(
+                    &target,
+                    &document,
+                    None,
+                    option,
+                    &content,
+                    &mut document_edits,
+                );
+            }
+            for element in &document.elements {
+                for option in &element.meta.options {
+                    self.option_edits(
+                        &target,
+                        &document,
+                        Some(element),
+                        option,
+                        &content,
+                        &mut document_edits,
+                    );
+                }
+                self.comment_edits(&target, &document, element, &content, &mut document_edits);
+            }
+            if !document_edits.is_empty() {
+                edits.insert(document.uri.clone(), document_edits);
+            }
+        }
+
+        for edits_in_file in edits.values_mut() {
+            edits_in_file.sort_by_key(|e| (e.range.start, e.range.end));
+            edits_in_file.dedup_by_key(|e| e.range);
+        }
+        edits
+    }
+
+    /// Returns a warning when renaming the field or enum value `target_fqn`
+    /// to `new_name` keeps the binary encoding but changes the JSON one, as
+    /// fields are serialized under their JSON name and enum values under
+    /// their name.
+    pub fn rename_json_warning(&self, target_fqn: &str, new_name: &str) -> Option<String> {
+        let target = self.lookup_fqn(target_fqn).into_iter().next()?.element;
+        let old = target.meta.name.as_str();
+        match target.kind {
+            ElementKind::Field { .. }
+            | ElementKind::MapField { .. }
+            | ElementKind::OneofField { .. } => {
+                // A custom `json_name` keeps the JSON name; one spelling out the
+                // default is renamed along.
+                if let Some(json_name) = target.option("json_name").and_then(OptionEntry::string_value)
+                    && json_name != old
+                    && json_name != to_json_name(old)
+                {
+                    return None;
+                }
+                let (old_json, new_json) = (to_json_name(old), to_json_name(new_name));
+                (old_json != new_json).then(|| {
+                    format!(
+                        "Renaming \"{old}\" to \"{new_name}\" keeps the binary encoding but breaks JSON: the field is serialized as \"{new_json}\" instead of \"{old_json}\"."
+                    )
+                })
+            }
+            ElementKind::EnumValue { .. } => (old != new_name).then(|| {
+                format!(
+                    "Renaming \"{old}\" to \"{new_name}\" keeps the binary encoding but breaks JSON: the value is serialized as \"{new_name}\" instead of \"{old}\"."
+                )
+            }),
+            _ => None,
+        }
+    }
+
+    /// Collects the edits of `option`, declared on `element` or on the file.
+    fn option_edits(
+        &self,
+        target: &Target,
+        document: &ProtoDocument,
+        element: Option<&ModelElement>,
+        option: &OptionEntry,
+        content: &str,
+        out: &mut Vec<TextEdit>,
+    ) {
+        let value = option.value.as_str();
+        let start = option.value_range.start;
+        let mut spans = Vec::new();
+
+        if let Some(element) = element {
+            let fqn = element.kind.fqn().unwrap_or(&document.package);
+            match option.name.as_str() {
+                "json_name" if fqn == target.fqn && !target.is_enum_value => {
+                    if let Some(json_name) = option.string_value() {
+                        let renamed = if json_name == target.old {
+                            target.new.to_owned()
+                        } else if json_name == to_json_name(target.old) {
+                            to_json_name(target.new)
+                        } else {
+                            return;
+                        };
+                        let quote = &value[..1];
+                        out.push(TextEdit {
+                            range: option.value_range,
+                            new_text: format!("{quote}{renamed}{quote}"),
+                        });
+                    }
+                    return;
+                }
+                "default" => {
+                    if target.is_enum_value
+                        && value.trim() == target.old
+                        && self.element_type(element, parent_scope(fqn)).as_deref()
+                            == Some(target.parent)
+                    {
+                        out.push(TextEdit {
+                            range: option.value_range,
+                            new_text: target.new.to_owned(),
+                        });
+                    }
+                    return;
+                }
+                _ => {}
+            }
+
+            if let ElementKind::Rpc {
+                request_type_ref,
+                response_type_ref,
+                ..
+            } = &element.kind
+                && !target.is_enum_value
+            {
+                let request = self.resolve_type(fqn, &request_type_ref.name);
+                let response = self.resolve_type(fqn, &response_type_ref.name);
+                if let Some(key) = option.name.strip_prefix(HTTP_OPTION) {
+                    // The aggregate form names each string by the key before
+                    // it, the sub-field form by the option name.
+                    let mut key = key.strip_prefix('.').map(trailing_segment);
+                    for (token, from, to) in tokenize(value) {
+                        match token {
+                            Token::Ident => key = Some(&value[from..to]),
+                            Token::Str => {
+                                if let Some(key) = key.take() {
+                                    self.http_rule_edits(
+                                        target,
+                                        key,
+                                        (&request, &response),
+                                        &value[from..to],
+                                        from,
+                                        &mut spans,
+                                    );
+                                }
+                            }
+                            Token::Punct(':') => {}
+                            _ => key = None,
+                        }
+                    }
+                    out.extend(to_edits(start, value, spans, target.new));
+                    return;
+                }
+                if option.name == METHOD_SIGNATURE_OPTION {
+                    for (token, from, to) in tokenize(value) {
+                        if token == Token::Str {
+                            self.path_list_edits(
+                                target,
+                                &request,
+                                &value[from..to],
+                                from,
+                                &mut spans,
+                            );
+                        }
+                    }
+                    out.extend(to_edits(start, value, spans, target.new));
+                    return;
+                }
+            }
+        }
+
+        if option.name.starts_with('(') {
+            let scope = element
+                .and_then(|e| e.kind.fqn())
+                .unwrap_or(&document.package);
+            self.custom_option_edits(target, element, scope, option, content, out);
+        }
+    }
+
+    /// Collects the spans of the field paths in the `key` string of a
+    /// `google.api.http` rule of an rpc, given by its request and response
+    /// types.
+    fn http_rule_edits(
+        &self,
+        target: &Target,
+        key: &str,
+        (request, response): (&str, &str),
+        text: &str,
+        offset: usize,
+        spans: &mut Vec<(usize, usize)>,
+    ) {
+        match key {
+            "body" => self.path_edits(target, request, text, offset, spans),
+            "response_body" => self.path_edits(target, response, text, offset, spans),
+            key if HTTP_TEMPLATES.contains(&key) => {
+                // Variables are written `{path}` or `{path=pattern}`.
+                let mut rest = text;
+                let mut base = offset;
+                while let Some(open) = rest.find('{') {
+                    let Some(close) = rest[open..].find('}') else {
+                        break;
+                    };
+                    let variable = &rest[open + 1..open + close];
+                    let path = variable.split('=').next().unwrap_or_default();
+                    self.path_edits(target, request, path.trim_end(), base + open + 1, spans);
+                    base += open + close + 1;
+                    rest = &rest[open + close + 1..];
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Collects the edits of a custom option: its name when it is the renamed
+    /// extension, the field names following it, and the keys and values of
+    /// its literal.
+    fn custom_option_edits(
+        &self,
+        target: &Target,
+        element: Option<&ModelElement>,
+        scope: &str,
+        option: &OptionEntry,
+        content: &str,
+        out: &mut Vec<TextEdit>,
+    ) {
+        let Some((extension, rest)) = option
+            .name
+            .strip_prefix('(')
+            .and_then(|name| name.split_once(')'))
+        else {
+            return;
+        };
+        let Some(FieldInfo { fqn, type_fqn }) = self.resolve_field(scope, extension.trim()) else {
+            return;
+        };
+
+        // Locate the option name in the source to edit it.
+        let source = &content
+            [byte_offset(content, option.range.start)..byte_offset(content, option.range.end)];
+        let name_offset = source.find(&option.name);
+        let mut name_spans = Vec::new();
+        if fqn == target.fqn
+            && !target.is_enum_value
+            && let Some(offset) = name_offset
+        {
+            let end = 1 + extension.trim_end().len();
+            name_spans.push((offset + end - target.old.len(), offset + end));
+        }
+
+        // Fields of the extension named after it, as in `(ext).field = 1`.
+        let mut current = type_fqn;
+        if let Some(path) = rest.strip_prefix('.') {
+            let mut position = name_offset.map(|offset| offset + option.name.len() - path.len());
+            for segment in path.split('.') {
+                let Some(field) = current
+                    .as_deref()
+                    .and_then(|message| self.message_field(message, segment))
+                else {
+                    current = None;
+                    break;
+                };
+                if field.fqn == target.fqn
+                    && let Some(position) = position
+                {
+                    name_spans.push((position, position + segment.len()));
+                }
+                position = position.map(|position| position + segment.len() + 1);
+                current = field.type_fqn;
+            }
+        }
+        out.extend(to_edits(option.range.start, source, name_spans, target.new));
+
+        // The message the option applies to, which `FieldMask` paths name
+        // fields of.
+        let mask_message = element.and_then(|element| match element.kind {
+            ElementKind::Message { .. } => element.kind.fqn(),
+            _ => element.kind.fqn().map(parent_scope),
+        });
+        let value = option.value.as_str();
+        let tokens = tokenize(value);
+        let mut spans = Vec::new();
+        let mut i = 0;
+        let mut walker = LiteralWalker {
+            state: self,
+            target,
+            value,
+            tokens: &tokens,
+            mask_message,
+            spans: &mut spans,
+        };
+        walker.value(&mut i, current.as_deref(), None, "");
+        out.extend(to_edits(option.value_range.start, value, spans, target.new));
+    }
+
+    /// Collects the edits of the backquoted field paths of the comments of
+    /// `element`, naming fields of the messages it relates to.
+    fn comment_edits(
+        &self,
+        target: &Target,
+        document: &ProtoDocument,
+        element: &ModelElement,
+        content: &str,
+        out: &mut Vec<TextEdit>,
+    ) {
+        if target.is_enum_value
+            || (element.meta.documentation.is_empty() && element.meta.trailing_comments.is_empty())
+        {
+            return;
+        }
+        let Some(fqn) = element.kind.fqn() else {
+            return;
+        };
+
+        let mut contexts = Vec::new();
+        match &element.kind {
+            ElementKind::Message { .. } => contexts.push(fqn.to_owned()),
+            ElementKind::Rpc {
+                request_type_ref,
+                response_type_ref,
+                ..
+            } => {
+                contexts.push(self.resolve_type(fqn, &request_type_ref.name));
+                contexts.push(self.resolve_type(fqn, &response_type_ref.name));
+            }
+            _ => {
+                let owner = parent_scope(fqn);
+                contexts.push(owner.to_owned());
+                // The paths of a `FieldMask` usually name fields of the
+                // resource it is sent with.
+                if self.element_type(element, owner).as_deref() == Some(FIELD_MASK) {
+                    contexts.extend(
+                        document
+                            .elements
+                            .iter()
+                            .filter(|e| e.kind.fqn().map(parent_scope) == Some(owner))
+                            .filter_map(|e| self.element_type(e, owner)),
+                    );
+                }
+            }
+        }
+
+        for block in element
+            .meta
+            .documentation
+            .iter()
+            .chain(&element.meta.trailing_comments)
+        {
+            let source = &content
+                [byte_offset(content, block.range.start)..byte_offset(content, block.range.end)];
+            let mut spans = Vec::new();
+            let mut parts = source.split('`');
+            let mut offset = parts.next().map_or(0, |p| p.len() + 1);
+            while let (Some(quoted), Some(after)) = (parts.next(), parts.next()) {
+                let is_path_list = !quoted.is_empty()
+                    && quoted
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | ',' | ' '));
+                if is_path_list {
+                    let first = quoted.split(['.', ',']).next().unwrap_or_default().trim();
+                    if let Some(context) = contexts
+                        .iter()
+                        .find(|context| self.message_field(context, first).is_some())
+                    {
+                        self.path_list_edits(target, context, quoted, offset, &mut spans);
+                    }
+                }
+                offset += quoted.len() + 1 + after.len() + 1;
+            }
+            out.extend(to_edits(block.range.start, source, spans, target.new));
+        }
+    }
+
+    /// Collects the spans naming the target in a comma-separated list of
+    /// field paths of `message`, starting at `offset`.
+    fn path_list_edits(
+        &self,
+        target: &Target,
+        message: &str,
+        text: &str,
+        offset: usize,
+        spans: &mut Vec<(usize, usize)>,
+    ) {
+        let mut position = offset;
+        for path in text.split(',') {
+            let trimmed = path.trim_start();
+            let start = position + path.len() - trimmed.len();
+            self.path_edits(target, message, trimmed.trim_end(), start, spans);
+            position += path.len() + 1;
+        }
+    }
+
+    /// Collects the spans naming the target in the field path `path` of
+    /// `message`, starting at `offset`.
+    fn path_edits(
+        &self,
+        target: &Target,
+        message: &str,
+        path: &str,
+        offset: usize,
+        spans: &mut Vec<(usize, usize)>,
+    ) {
+        let mut message = message.to_owned();
+        let mut position = offset;
+        for segment in path.split('.') {
+            let Some(field) = self.message_field(&message, segment) else {
+                return;
+            };
+            if field.fqn == target.fqn {
+                spans.push((position, position + segment.len()));
+            }
+            let Some(type_fqn) = field.type_fqn else {
+                return;
+            };
+            message = type_fqn;
+            position += segment.len() + 1;
+        }
+    }
+
+    /// Finds the field `name` of `message`.
+    fn message_field(&self, message: &str, name: &str) -> Option<FieldInfo> {
+        let fqn = format!("{message}.{name}");
+        let field = self
+            .lookup_fqn(&fqn)
+            .into_iter()
+            .find(|t| is_field(&t.element))?;
+        Some(FieldInfo {
+            type_fqn: self.element_type(&field.element, message),
+            fqn,
+        })
+    }
+
+    /// Resolves `name` from `scope` to a field, such as the extension named
+    /// by a custom option.
+    fn resolve_field(&self, scope: &str, name: &str) -> Option<FieldInfo> {
+        let field = self
+            .resolve_reference(scope, name)
+            .into_iter()
+            .find(|t| is_field(&t.element))?;
+        let fqn = field.element.kind.fqn()?.to_owned();
+        Some(FieldInfo {
+            type_fqn: self.element_type(&field.element, parent_scope(&fqn)),
+            fqn,
+        })
+    }
+
+    /// The FQN of the type of the field `element` declared in `scope`, or
+    /// the written type name without its leading dot when it is not indexed.
+    fn element_type(&self, element: &ModelElement, scope: &str) -> Option<String> {
+        let type_ref = match &element.kind {
+            ElementKind::Field { type_ref, .. } | ElementKind::OneofField { type_ref, .. } => {
+                type_ref
+            }
+            ElementKind::MapField { value_type_ref, .. } => value_type_ref,
+            _ => return None,
+        };
+        Some(self.resolve_type(scope, &type_ref.name))
+    }
+
+    fn resolve_type(&self, scope: &str, name: &str) -> String {
+        self.resolve_reference(scope, name)
+            .into_iter()
+            .find_map(|t| t.element.kind.fqn().map(ToOwned::to_owned))
+            .unwrap_or_else(|| name.trim_start_matches('.').to_owned())
+    }
+}
+
+/// Walks a protobuf text-format literal, collecting the spans naming the
+/// target: keys naming the field, enum constants naming the value, and
+/// `FieldMask` paths naming the field.
+struct LiteralWalker<'a> {
+    state: &'a ProtoLanguageState,
+    target: &'a Target<'a>,
+    value: &'a str,
+    tokens: &'a [(Token, usize, usize)],
+    /// The message `FieldMask` paths name fields of.
+    mask_message: Option<&'a str>,
+    spans: &'a mut Vec<(usize, usize)>,
+}
+
+impl LiteralWalker<'_> {
+    /// Walks the fields of a message literal of type `message` up to its
+    /// closing brace.
+    fn fields(&mut self, i: &mut usize, message: Option<&str>) {
+        while let Some((token, from, to)) = self.tokens.get(*i) {
+            *i += 1;
+            let field = match token {
+                Token::Punct('}' | '>') => return,
+                Token::Ident => {
+                    let name = &self.value[*from..*to];
+                    let field = message.and_then(|m| self.state.message_field(m, name));
+                    if field.as_ref().is_some_and(|f| f.fqn == self.target.fqn) {
+                        self.spans.push((*from, *to));
+                    }
+                    Some((name, field.and_then(|f| f.type_fqn)))
+                }
+                Token::Punct('[') => {
+                    // Extension and `Any` keys are not followed.
+                    while let Some((token, ..)) = self.tokens.get(*i) {
+                        *i += 1;
+                        if *token == Token::Punct(']') {
+                            break;
+                        }
+                    }
+                    None
+                }
+                _ => continue,
+            };
+            if self
+                .tokens
+                .get(*i)
+                .is_some_and(|(t, ..)| *t == Token::Punct(':'))
+            {
+                *i += 1;
+            }
+            let (key, type_fqn) = field.unwrap_or_default();
+            self.value(i, type_fqn.as_deref(), message, key);
+        }
+    }
+
+    /// Walks the value of the field `key` of `parent`, of type `type_fqn`.
+    fn value(&mut self, i: &mut usize, type_fqn: Option<&str>, parent: Option<&str>, key: &str) {
+        let Some((token, from, to)) = self.tokens.get(*i) else {
+            return;
+        };
+        *i += 1;
+        match token {
+            Token::Punct('{' | '<') => self.fields(i, type_fqn),
+            Token::Punct('[') => {
+                while let Some((token, ..)) = self.tokens.get(*i) {
+                    match token {
+                        Token::Punct(']') => {
+                            *i += 1;
+                            break;
+                        }
+                        Token::Punct(',') => *i += 1,
+                        _ => self.value(i, type_fqn, parent, key),
+                    }
+                }
+            }
+            Token::Punct('-') => self.value(i, type_fqn, parent, key),
+            Token::Ident => {
+                if self.target.is_enum_value
+                    && type_fqn == Some(self.target.parent)
+                    && &self.value[*from..*to] == self.target.old
+                {
+                    self.spans.push((*from, *to));
+                }
+            }
+            Token::Str => {
+                if parent == Some(FIELD_MASK)
+                    && key == "paths"
+                    && let Some(message) = self.mask_message
+                {
+                    self.state.path_list_edits(
+                        self.target,
+                        message,
+                        &self.value[*from..*to],
+                        *from,
+                        self.spans,
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn is_field(element: &ModelElement) -> bool {
+    matches!(
+        element.kind,
+        ElementKind::Field { .. } | ElementKind::MapField { .. } | ElementKind::OneofField { .. }
+    )
+}
+
+/// Splits an option constant into tokens with their byte offsets.
+fn tokenize(text: &str) -> Vec<(Token, usize, usize)> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '#' => while chars.next_if(|(_, c)| *c != '\n').is_some() {},
+            '/' if chars.peek().is_some_and(|(_, c)| *c == '/') => {
+                while chars.next_if(|(_, c)| *c != '\n').is_some() {}
+            }
+            '"' | '\'' => {
+                let mut end = text.len();
+                while let Some((i, v)) = chars.next() {
+                    if v == '\\' {
+                        chars.next();
+                    } else if v == c {
+                        end = i;
+                        break;
+                    }
+                }
+                tokens.push((Token::Str, start + 1, end));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, n)) = chars.next_if(|(_, n)| n.is_alphanumeric() || *n == '_') {
+                    end = i + n.len_utf8();
+                }
+                tokens.push((Token::Ident, start, end));
+            }
+            '{' | '}' | '[' | ']' | '<' | '>' | ':' | ',' | ';' | '-' => {
+                tokens.push((Token::Punct(c), start, start + 1));
+            }
+            _ => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, n)) = chars.next_if(|(_, n)| n.is_alphanumeric() || *n == '.') {
+                    end = i + n.len_utf8();
+                }
+                tokens.push((Token::Other, start, end));
+            }
+        }
+    }
+    tokens
+}
+
+/// The edits replacing the byte spans of `text`, which starts at `start`,
+/// with `new_text`.
+fn to_edits(
+    start: Position,
+    text: &str,
+    spans: Vec<(usize, usize)>,
+    new_text: &str,
+) -> impl Iterator<Item = TextEdit> {
+    spans.into_iter().map(move |(from, to)| TextEdit {
+        range: Range {
+            start: offset_position(start, text, from),
+            end: offset_position(start, text, to),
+        },
+        new_text: new_text.to_owned(),
+    })
+}
+
+/// The position of the byte `offset` of `text`, which starts at `start`.
+pub(super) fn offset_position(start: Position, text: &str, offset: usize) -> Position {
+    let before = &text[..offset];
+    let line = u32::try_from(before.matches('\n').count()).unwrap_or(u32::MAX);
+    let character = match before.rfind('\n') {
+        Some(newline) => offset - newline - 1,
+        None => offset + start.character as usize,
+    };
+    Position {
+        line: start.line.saturating_add(line),
+        character: u32::try_from(character).unwrap_or(u32::MAX),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use insta::assert_yaml_snapshot;
+
+    use crate::config::Config;
+    use crate::state::ProtoLanguageState;
+
+    fn make_state() -> ProtoLanguageState {
+        let ipath = vec![PathBuf::from("src/state/input/fields")];
+        let mut state = ProtoLanguageState::new();
+        for (uri, content) in [
+            (
+                "file://input/options.proto",
+                include_str!("input/fields/options.proto"),
+            ),
+            (
+                "file://input/library.proto",
+                include_str!("input/fields/library.proto"),
+            ),
+            (
+                "file://input/service.proto",
+                include_str!("input/fields/service.proto"),
+            ),
+        ] {
+            let uri = uri.parse().unwrap();
+            state.upsert_file(&uri, content, &ipath, 2, &Config::default(), false);
+        }
+        state
+    }
+
+    #[test]
+    fn test_rename_propagation_edits() {
+        let state = make_state();
+
+        // The field is renamed in its `json_name`, option literals, http rules,
+        // method signatures and comments, but not where a field of the same
+        // name of another message is meant.
+        assert_yaml_snapshot!(state.rename_propagation_edits("library.Book.title", "headline"));
+        // The enum value is renamed in defaults and option literals.
+        assert_yaml_snapshot!(
+            state.rename_propagation_edits("library.Genre.GENRE_NOVEL", "GENRE_FICTION")
+        );
+        // The extension is renamed where options name it.
+        assert_yaml_snapshot!(state.rename_propagation_edits("library.field_info", "info"));
+        assert!(
+            state
+                .rename_propagation_edits("library.Book", "Volume")
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_rename_json_warning() {
+        let state = make_state();
+
+        assert_eq!(
+            state
+                .rename_json_warning("library.Book.title", "head_line")
+                .as_deref(),
+            Some(
+                "Renaming \"title\" to \"head_line\" keeps the binary encoding but breaks JSON: the field is serialized as \"headLine\" instead of \"title\"."
+            )
+        );
+        // A custom `json_name` keeps the JSON name.
+        assert!(
+            state
+                .rename_json_warning("library.Book.subtitle", "caption")
+                .is_none()
+        );
+        // So does a name with the same JSON name.
+        assert!(
+            state
+                .rename_json_warning("library.Author.name", "name")
+                .is_none()
+        );
+        assert!(
+            state
+                .rename_json_warning("library.Genre.GENRE_NOVEL", "GENRE_FICTION")
+                .is_some()
+        );
+        assert!(
+            state
+                .rename_json_warning("library.Book", "Volume")
+                .is_none()
+        );
+    }
+}
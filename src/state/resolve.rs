@@ -7,9 +7,11 @@
 
 use std::collections::BTreeMap;
 
-use async_lsp::lsp_types::{Location, Position, TextEdit, Url};
+use async_lsp::lsp_types::{Location, Position, Range, TextEdit, Url};
 
-use crate::model::{ModelElement, SpatialEntry, TypeReference};
+use crate::descriptor::declared_extendees;
+use crate::document::ProtoDocument;
+use crate::model::{ElementKind, ModelElement, SpatialEntry, TypeReference};
 use crate::state::ProtoLanguageState;
 use crate::utils::{is_position_inside_range, split_identifier_package, trailing_segment};
 
@@ -52,7 +54,7 @@ impl ProtoLanguageState {
     }
 
     /// Finds every element whose Fully Qualified Name equals `fqn`.
-    fn lookup_fqn(&self, fqn: &str) -> Vec<ResolvedTarget> {
+    pub(super) fn lookup_fqn(&self, fqn: &str) -> Vec<ResolvedTarget> {
         let mut out = Vec::new();
         for document in self.get_documents() {
             for element in &document.elements {
@@ -292,9 +294,28 @@ fn scope_prefixes(scope: &str) -> Vec<&str> {
     prefixes
 }
 
+/// Lists the types extended by the `extend` blocks of `document`, each with
+/// the scope its name resolves in and the range of the name.
+pub(super) fn extendees(document: &ProtoDocument, content: &str) -> Vec<(String, String, Range)> {
+    declared_extendees(&document.tree, content)
+        .into_iter()
+        .map(|(name, range, message)| {
+            let scope = message
+                .and_then(|message| {
+                    document.elements.iter().find_map(|e| match &e.kind {
+                        ElementKind::Message { fqn, .. } if e.meta.range == message => Some(fqn),
+                        _ => None,
+                    })
+                })
+                .unwrap_or(&document.package);
+            (scope.clone(), name, range)
+        })
+        .collect()
+}
+
 /// Returns `true` for documents that should never be edited: vendored
 /// well-known types supplied under a `google/protobuf/` path.
-fn is_external_document(uri: &Url) -> bool {
+pub fn is_external_document(uri: &Url) -> bool {
     uri.path().contains("google/protobuf/")
 }
 
@@ -0,0 +1,234 @@
+//! Semantic diagnostics computed in process, without protoc.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+
+use async_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Url};
+
+use crate::descriptor::{self, FileDescriptor, Origin};
+use crate::document::ProtoDocument;
+use crate::model::ElementKind;
+use crate::state::ProtoLanguageState;
+use crate::utils::{import_path, resolve_import};
+
+impl ProtoLanguageState {
+    /// Builds the descriptor of `document` and the errors protoc would report
+    /// on it, resolving names against the files it imports.
+    ///
+    /// Names are looked up in the symbols of the whole workspace, kept up to
+    /// date as files change, to tell names that need an import apart from
+    /// undefined ones.
+    pub fn compile_document(
+        &self,
+        document: &ProtoDocument,
+        ipath: &[PathBuf],
+    ) -> (FileDescriptor, Vec<Diagnostic>) {
+        let mut origins = HashMap::from([(document.uri.clone(), Origin::Current)]);
+        let content = self.get_content(&document.uri);
+
+        let mut missing_imports = false;
+        let mut imported = Vec::new();
+        for path in document.import_paths() {
+            match self.import_reach(&path, ipath) {
+                Some(reach) => imported.extend(reach),
+                None => missing_imports = true,
+            }
+        }
+
+        // Direct imports first, then whatever they import.
+        let mut pending: VecDeque<(Url, Origin)> = imported
+            .into_iter()
+            .map(|uri| (uri, Origin::Imported))
+            .collect();
+        while let Some((uri, origin)) = pending.pop_front() {
+            if origins.contains_key(&uri) {
+                continue;
+            }
+            let Some(dependency) = self.get_document(&uri) else {
+                continue;
+            };
+            origins.insert(uri, origin);
+            pending.extend(
+                dependency
+                    .import_paths()
+                    .iter()
+                    .filter_map(|path| resolve_import(path, ipath))
+                    .map(|uri| (uri, Origin::Transitive)),
+            );
+        }
+
+        let name = |uri: &Url| file_name(uri, ipath);
+        let symbols = self.symbols.read().expect("poison");
+        descriptor::compile(
+            document,
+            &content,
+            &file_name(&document.uri, ipath),
+            &symbols.view(origins, &name),
+            missing_imports,
+        )
+    }
+
+    /// Reports what protoc would reject in `document` beyond syntax errors and
+    /// missing imports: unresolved names, misused numbers, duplicate
+    /// definitions and import cycles. Nothing is reported while the document
+    /// has syntax errors, which would only cause more. The compiled descriptor
+    /// is kept for the breaking change check.
+    pub(super) fn semantic_diagnostics(
+        &self,
+        document: &ProtoDocument,
+        ipath: &[PathBuf],
+    ) -> Vec<Diagnostic> {
+        if document.tree.root_node().has_error() {
+            return vec![];
+        }
+        let mut diagnostics = self.import_cycle_diagnostics(document, ipath);
+        let (descriptor, errors) = self.compile_document(document, ipath);
+        diagnostics.extend(errors);
+        self.compiled
+            .write()
+            .expect("poison")
+            .insert(document.uri.clone(), descriptor);
+        diagnostics
+    }
+
+    /// Reports the first import of `document` leading back to it.
+    pub(super) fn import_cycle_diagnostics(
+        &self,
+        document: &ProtoDocument,
+        ipath: &[PathBuf],
+    ) -> Vec<Diagnostic> {
+        for import in &document.elements {
+            let ElementKind::Import { path } = &import.kind else {
+                continue;
+            };
+            let Some(uri) = resolve_import(path, ipath) else {
+                continue;
+            };
+            let mut chain = vec![file_name(&document.uri, ipath)];
+            if self.imports_back(&uri, &document.uri, ipath, &mut chain, &mut HashSet::new()) {
+                return vec![Diagnostic {
+                    range: import.meta.range,
+                    severity: Some(DiagnosticSeverity::ERROR),
+                    source: Some(String::from("protols")),
+                    message: format!("File recursively imports itself: {}", chain.join(" -> ")),
+                    ..Default::default()
+                }];
+            }
+        }
+        vec![]
+    }
+
+    /// Whether `uri` imports `target`, directly or not, pushing the names of
+    /// the files on the way onto `chain`.
+    fn imports_back(
+        &self,
+        uri: &Url,
+        target: &Url,
+        ipath: &[PathBuf],
+        chain: &mut Vec<String>,
+        visited: &mut HashSet<Url>,
+    ) -> bool {
+        chain.push(file_name(uri, ipath));
+        if uri == target {
+            return true;
+        }
+        if visited.insert(uri.clone())
+            && let Some(document) = self.get_document(uri)
+        {
+            for next in document
+                .import_paths()
+                .iter()
+                .filter_map(|path| resolve_import(path, ipath))
+            {
+                if self.imports_back(&next, target, ipath, chain, visited) {
+                    return true;
+                }
+            }
+        }
+        chain.pop();
+        false
+    }
+}
+
+/// Returns the name of `uri` relative to the include paths, or its file name.
+pub(super) fn file_name(uri: &Url, ipath: &[PathBuf]) -> String {
+    import_path(uri, ipath).unwrap_or_else(|| {
+        uri.path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .unwrap_or_default()
+            .to_string()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use async_lsp::lsp_types::Url;
+    use insta::assert_yaml_snapshot;
+    use std::path::PathBuf;
+
+    use crate::config::Config;
+    use crate::state::ProtoLanguageState;
+
+    const FILES: &[&str] = &[
+        "common.proto",
+        "hidden.proto",
+        "via.proto",
+        "valid.proto",
+        "errors.proto",
+        "proto2.proto",
+        "cycle_a.proto",
+        "cycle_b.proto",
+    ];
+
+    fn setup() -> (PathBuf, ProtoLanguageState) {
+        ProtoLanguageState::with_fixtures("semantic", FILES)
+    }
+
+    #[test]
+    fn test_compile_document() {
+        let (dir, state) = setup();
+        let uri = Url::from_file_path(dir.join("valid.proto")).unwrap();
+        let document = state.get_document(&uri).unwrap();
+
+        let (descriptor, diagnostics) = state.compile_document(&document, &[dir]);
+        assert!(diagnostics.is_empty(), "{diagnostics:#?}");
+        assert_yaml_snapshot!(descriptor);
+    }
+
+    #[test]
+    fn test_semantic_diagnostics() {
+        let (dir, state) = setup();
+        let ipath = vec![dir.clone()];
+        for file in ["errors.proto", "proto2.proto", "cycle_a.proto"] {
+            let uri = Url::from_file_path(dir.join(file)).unwrap();
+            let document = state.get_document(&uri).unwrap();
+            assert_yaml_snapshot!(file, state.semantic_diagnostics(&document, &ipath));
+        }
+    }
+
+    #[test]
+    fn test_compile_document_after_update() {
+        let (dir, mut state) = setup();
+        let ipath = vec![dir.clone()];
+        let common = Url::from_file_path(dir.join("common.proto")).unwrap();
+        let valid = Url::from_file_path(dir.join("valid.proto")).unwrap();
+        let content = state.get_content(&common);
+        let compile = |state: &ProtoLanguageState| {
+            let document = state.get_document(&valid).unwrap();
+            state.compile_document(&document, &ipath).1
+        };
+
+        // The symbols of a file follow its edits.
+        let renamed = content.replace("enum Kind", "enum Sort");
+        state.upsert_file(&common, &renamed, &ipath, 2, &Config::default(), false);
+        let diagnostics = compile(&state);
+        assert_eq!(diagnostics.len(), 1, "{diagnostics:#?}");
+        assert_eq!(
+            diagnostics[0].message,
+            "\"sem.common.Kind\" is not defined."
+        );
+
+        state.upsert_file(&common, &content, &ipath, 2, &Config::default(), false);
+        assert!(compile(&state).is_empty());
+    }
+}
@@ -0,0 +1,104 @@
+//! Diagnostics for imports that provide nothing to the importing document.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use async_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, DiagnosticTag};
+
+use crate::document::ProtoDocument;
+use crate::model::ElementKind;
+use crate::state::ProtoLanguageState;
+use crate::utils::to_lsp_range;
+
+impl ProtoLanguageState {
+    /// Reports every import of `document` whose file, together with the files
+    /// it re-exports through `import public`, declares nothing the document
+    /// refers to.
+    ///
+    /// `public` and `weak` imports, repeated imports and imports whose file is
+    /// not indexed are not reported.
+    pub fn collect_unused_import_diagnostics(
+        &self,
+        document: &ProtoDocument,
+        ipath: &[PathBuf],
+    ) -> Vec<Diagnostic> {
+        let requirements = self.required_documents(document);
+        let root = document.tree.root_node();
+
+        let mut seen = HashSet::new();
+        let mut diagnostics = Vec::new();
+        for node in root
+            .children(&mut root.walk())
+            .filter(|n| n.kind() == "import")
+        {
+            if node
+                .children(&mut node.walk())
+                .any(|c| matches!(c.kind(), "public" | "weak"))
+            {
+                continue;
+            }
+            let range = to_lsp_range(node);
+            let Some(path) = document.elements.iter().find_map(|e| match &e.kind {
+                ElementKind::Import { path } if e.meta.range == range => Some(path),
+                _ => None,
+            }) else {
+                continue;
+            };
+            if !seen.insert(path) {
+                continue;
+            }
+            let Some(reach) = self.import_reach(path, ipath) else {
+                continue;
+            };
+            if requirements
+                .iter()
+                .any(|candidates| candidates.iter().any(|c| reach.contains(c)))
+            {
+                continue;
+            }
+            diagnostics.push(Diagnostic {
+                range,
+                severity: Some(DiagnosticSeverity::WARNING),
+                source: Some(String::from("protols")),
+                message: format!("import \"{path}\" is unused"),
+                tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+                ..Default::default()
+            });
+        }
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use insta::assert_yaml_snapshot;
+
+    use crate::config::{Config, DiagnosticsEngine};
+    use crate::state::{ProtoLanguageState, fixture_dir};
+
+    #[test]
+    fn test_unused_import_diagnostics() {
+        let dir = fixture_dir("organize");
+        let ipath = vec![dir.clone()];
+
+        let mut config = Config::default();
+        config.diagnostics.engine = DiagnosticsEngine::Native;
+        let mut state = ProtoLanguageState::new();
+        let mut upsert = |file: &str| {
+            state
+                .upsert_fixture(&dir, file, &ipath, &config)
+                .remove(0)
+                .diagnostics
+        };
+
+        // `extra.proto` provides nothing, `common.proto` is listed twice.
+        assert_yaml_snapshot!(upsert("main.proto"));
+        // Used through the `import public` of `reexport.proto`.
+        assert!(upsert("reexported.proto").is_empty());
+        // The re-exporting file itself uses nothing, but its import is public.
+        assert!(upsert("reexport.proto").is_empty());
+        // Extending `google.protobuf.FieldOptions` uses `descriptor.proto`.
+        upsert("google/protobuf/descriptor.proto");
+        assert!(upsert("options.proto").is_empty());
+    }
+}
@@ -1,4 +1,6 @@
-use async_lsp::lsp_types::{Position, Range};
+use std::path::{Path, PathBuf};
+
+use async_lsp::lsp_types::{Position, Range, Url};
 use tree_sitter::{Node, Point};
 
 /// Converts a Tree-sitter [`Point`] into an LSP [`Position`].
@@ -49,6 +51,46 @@ pub fn is_position_inside_range(position: Position, range: Range) -> bool {
     position >= range.start && position <= range.end
 }
 
+/// Parses a protobuf integer literal: decimal, hexadecimal (`0x`) or octal
+/// (leading `0`).
+pub fn parse_int_lit(text: &str) -> Option<i64> {
+    let text = text.trim();
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()
+    } else if text.len() > 1 && text.starts_with('0') {
+        i64::from_str_radix(&text[1..], 8).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+/// Converts a position in UTF-16 code units to a byte offset in `content`,
+/// clamped to the end of its line.
+pub fn byte_offset(content: &str, position: Position) -> usize {
+    let line_start: usize = content
+        .split_inclusive('\n')
+        .take(position.line as usize)
+        .map(str::len)
+        .sum();
+    let line = content[line_start..].split('\n').next().unwrap_or_default();
+
+    let mut units = 0;
+    for (offset, c) in line.char_indices() {
+        if units >= position.character as usize {
+            return line_start + offset;
+        }
+        units += c.len_utf16();
+    }
+    line_start + line.len()
+}
+
+/// Whether `name` is a protobuf identifier: a letter or underscore followed by
+/// letters, digits and underscores.
+pub fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
 fn is_title_case(s: &str) -> bool {
     s.chars().next().is_some_and(char::is_uppercase)
 }
@@ -70,6 +112,12 @@ pub fn trailing_segment(qualified: &str) -> &str {
     qualified.rsplit_once('.').map_or(qualified, |(_, t)| t)
 }
 
+/// Returns the scope declaring `fqn`: `com.book.Book.title` yields
+/// `com.book.Book`, and a name without dot the empty root scope.
+pub fn parent_scope(fqn: &str) -> &str {
+    fqn.rsplit_once('.').map_or("", |(scope, _)| scope)
+}
+
 pub fn split_identifier_package(s: &str) -> (&str, &str) {
     let s = s.trim_start_matches('.');
     if is_inner_identifier(s) || !s.contains('.') {
@@ -91,6 +139,56 @@ pub fn split_identifier_package(s: &str) -> (&str, &str) {
     (package, identifier.trim_matches('.'))
 }
 
+/// Finds the file `path` names: itself when it is absolute, or else the
+/// first include path of `ipath` holding it.
+pub fn resolve_import(path: impl AsRef<Path>, ipath: &[PathBuf]) -> Option<Url> {
+    let path = path.as_ref();
+    let file = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        ipath.iter().map(|p| p.join(path)).find(|p| p.exists())?
+    };
+    Url::from_file_path(file).ok()
+}
+
+/// Returns the path under which `uri` is imported from the include paths.
+pub fn import_path(uri: &Url, ipath: &[PathBuf]) -> Option<String> {
+    let file = uri.to_file_path().ok()?;
+    let relative = ipath.iter().find_map(|p| file.strip_prefix(p).ok())?;
+    let parts: Vec<String> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    Some(parts.join("/"))
+}
+
+/// Converts a protobuf field name into its default JSON name, matching
+/// `protoc`'s `ToJsonName`: underscores are dropped and the character following
+/// each underscore is uppercased.
+///
+/// # Examples
+///
+/// * `"book_title"` becomes `"bookTitle"`
+/// * `"isbn"` remains `"isbn"`
+/// * `"foo__bar_2"` becomes `"fooBar2"`
+pub fn to_json_name(field_name: &str) -> String {
+    let mut json_name = String::with_capacity(field_name.len());
+    let mut capitalize_next = false;
+
+    for c in field_name.chars() {
+        if c == '_' {
+            capitalize_next = true;
+        } else if capitalize_next {
+            json_name.extend(c.to_uppercase());
+            capitalize_next = false;
+        } else {
+            json_name.push(c);
+        }
+    }
+
+    json_name
+}
+
 /// Strips syntax markers and normalizes whitespace from raw protobuf comment tokens.
 ///
 /// This utility processes both multi-line block comments (`/* ... */`) and single-line
@@ -131,8 +229,8 @@ pub fn compile_test_query() -> tree_sitter::Query {
 #[cfg(test)]
 mod test {
     use crate::utils::{
-        clean_proto_comment, is_inner_identifier, split_identifier_package, to_lsp_position,
-        trailing_segment,
+        clean_proto_comment, is_inner_identifier, split_identifier_package, to_json_name,
+        to_lsp_position, trailing_segment,
     };
     use tree_sitter::Point;
 
@@ -240,6 +338,16 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_to_json_name() {
+        assert_eq!(to_json_name("isbn"), "isbn");
+        assert_eq!(to_json_name("book_title"), "bookTitle");
+        assert_eq!(to_json_name("foo__bar_2"), "fooBar2");
+        assert_eq!(to_json_name("_leading"), "Leading");
+        assert_eq!(to_json_name("trailing_"), "trailing");
+        assert_eq!(to_json_name("alreadyCamel"), "alreadyCamel");
+    }
+
     #[test]
     fn test_clean_proto_comment() {
         assert_eq!(clean_proto_comment("// My comment"), "My comment");